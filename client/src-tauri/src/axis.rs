@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisId {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    TriggerLeft,
+    TriggerRight,
+}
+
+impl AxisId {
+    const ALL: [AxisId; 6] = [
+        AxisId::LeftX,
+        AxisId::LeftY,
+        AxisId::RightX,
+        AxisId::RightY,
+        AxisId::TriggerLeft,
+        AxisId::TriggerRight,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The other half of a stick, or `None` for triggers.
+    fn partner(self) -> Option<AxisId> {
+        match self {
+            AxisId::LeftX => Some(AxisId::LeftY),
+            AxisId::LeftY => Some(AxisId::LeftX),
+            AxisId::RightX => Some(AxisId::RightY),
+            AxisId::RightY => Some(AxisId::RightX),
+            AxisId::TriggerLeft | AxisId::TriggerRight => None,
+        }
+    }
+
+    fn is_x(self) -> bool {
+        matches!(self, AxisId::LeftX | AxisId::RightX)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadzoneMode {
+    /// Circular deadzone over the whole stick, using the larger of the two
+    /// axes' deadzones. Keeps diagonals smooth.
+    #[default]
+    Radial,
+    /// Each axis is deadzoned on its own. Makes it easier to hold a pure
+    /// horizontal or vertical direction.
+    Axial,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    Quadratic,
+    Cubic,
+    /// Arbitrary exponent; values above 1 give finer control near the centre.
+    Power(f32),
+}
+
+impl ResponseCurve {
+    fn apply(self, magnitude: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => magnitude,
+            ResponseCurve::Quadratic => magnitude * magnitude,
+            ResponseCurve::Cubic => magnitude * magnitude * magnitude,
+            ResponseCurve::Power(exp) => magnitude.powf(exp.max(0.01)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AxisSettings {
+    /// Set to `false` to stop streaming this axis.
+    pub enabled: bool,
+    /// Fraction of travel (0..1) treated as rest.
+    pub deadzone: f32,
+    pub curve: ResponseCurve,
+    pub invert: bool,
+}
+
+impl Default for AxisSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            deadzone: 0.15,
+            curve: ResponseCurve::Linear,
+            invert: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AxisConfig {
+    /// Minimum time between two `gamepad_axis` events for the same axis.
+    pub min_interval_ms: u32,
+    /// Smallest change in the processed value worth reporting.
+    pub min_delta: f32,
+    pub left_stick_mode: DeadzoneMode,
+    pub right_stick_mode: DeadzoneMode,
+    pub axes: HashMap<AxisId, AxisSettings>,
+}
+
+impl Default for AxisConfig {
+    fn default() -> Self {
+        Self {
+            min_interval_ms: 16,
+            min_delta: 0.01,
+            left_stick_mode: DeadzoneMode::Radial,
+            right_stick_mode: DeadzoneMode::Radial,
+            axes: HashMap::new(),
+        }
+    }
+}
+
+impl AxisConfig {
+    fn settings(&self, axis: AxisId) -> AxisSettings {
+        self.axes.get(&axis).copied().unwrap_or_else(|| match axis {
+            AxisId::TriggerLeft | AxisId::TriggerRight => AxisSettings {
+                deadzone: 0.05,
+                ..Default::default()
+            },
+            _ => AxisSettings::default(),
+        })
+    }
+
+    fn stick_mode(&self, axis: AxisId) -> DeadzoneMode {
+        match axis {
+            AxisId::LeftX | AxisId::LeftY => self.left_stick_mode,
+            _ => self.right_stick_mode,
+        }
+    }
+}
+
+/// Hysteresis for turning the analog triggers into button 6/7 presses. The
+/// latch state itself is kept per controller by the registry.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TriggerConfig {
+    /// Trigger travel (0..1) above which the button is pressed.
+    pub press_threshold: f32,
+    /// Trigger travel (0..1) below which it is released again. Kept at or
+    /// under `press_threshold`.
+    pub release_threshold: f32,
+}
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self {
+            press_threshold: 0.25,
+            release_threshold: 0.12,
+        }
+    }
+}
+
+impl TriggerConfig {
+    /// `Some(true)` past the press threshold, `Some(false)` under the release
+    /// threshold, `None` in between, where the trigger keeps its state.
+    pub fn crossed(&self, raw: i16) -> Option<bool> {
+        let value = normalize(raw);
+        let press = self.press_threshold.clamp(0.01, 1.0);
+        if value > press {
+            Some(true)
+        } else if value < self.release_threshold.min(press) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// Processed axis value: -1..1 for sticks, 0..1 for triggers.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AxisEvent {
+    pub axis: AxisId,
+    pub value: f32,
+    pub timestamp: u32,
+}
+
+#[derive(Default)]
+struct ControllerAxes {
+    raw: [i16; 6],
+    sent: [f32; 6],
+    last_emit: [Option<u32>; 6],
+    dirty: [bool; 6],
+}
+
+/// Applies deadzones, curves and inversion to raw SDL axis values and
+/// rate-limits the resulting stream per controller and axis.
+#[derive(Default)]
+pub struct AxisProcessor {
+    config: AxisConfig,
+    controllers: HashMap<u32, ControllerAxes>,
+}
+
+impl AxisProcessor {
+    pub fn set_config(&mut self, config: AxisConfig) {
+        self.config = config;
+        for axes in self.controllers.values_mut() {
+            axes.dirty = [true; 6];
+        }
+    }
+
+    pub fn remove_controller(&mut self, controller: u32) {
+        self.controllers.remove(&controller);
+    }
+
+    pub fn update(&mut self, controller: u32, axis: AxisId, value: i16) {
+        let axes = self.controllers.entry(controller).or_default();
+        axes.raw[axis.index()] = value;
+        axes.dirty[axis.index()] = true;
+        // With a radial deadzone one axis moving changes the other's output.
+        if let Some(partner) = axis.partner() {
+            axes.dirty[partner.index()] = true;
+        }
+    }
+
+    /// Milliseconds until `flush` has a throttled change to send, if any.
+    pub fn next_due(&self, now: u32) -> Option<u32> {
+        self.controllers
+            .values()
+            .flat_map(|axes| AxisId::ALL.map(|axis| (axes, axis.index())))
+            .filter(|(axes, i)| axes.dirty[*i])
+            .map(|(axes, i)| match axes.last_emit[i] {
+                Some(t) => self.config.min_interval_ms.saturating_sub(now.wrapping_sub(t)),
+                None => 0,
+            })
+            .min()
+    }
+
+    /// Emits pending axis changes whose rate limit has elapsed. Changes that
+    /// are still throttled stay pending so the final resting value is never lost.
+    /// Each event comes with its controller.
+    pub fn flush(&mut self, now: u32) -> Vec<(u32, AxisEvent)> {
+        let mut events = Vec::new();
+
+        for (&controller, axes) in self.controllers.iter_mut() {
+            for axis in AxisId::ALL {
+                let i = axis.index();
+                if !axes.dirty[i] {
+                    continue;
+                }
+                let settings = self.config.settings(axis);
+                if !settings.enabled {
+                    axes.dirty[i] = false;
+                    continue;
+                }
+                if axes.last_emit[i].is_some_and(|t| now.wrapping_sub(t) < self.config.min_interval_ms) {
+                    continue;
+                }
+                axes.dirty[i] = false;
+
+                let value = process(&self.config, axes, axis);
+                let changed = (value - axes.sent[i]).abs() >= self.config.min_delta;
+                let settled = value == 0.0 && axes.sent[i] != 0.0;
+                if changed || settled {
+                    axes.sent[i] = value;
+                    axes.last_emit[i] = Some(now);
+                    events.push((controller, AxisEvent { axis, value, timestamp: now }));
+                }
+            }
+        }
+
+        events
+    }
+}
+
+/// Runs one axis through deadzone, response curve and inversion.
+fn process(config: &AxisConfig, axes: &ControllerAxes, axis: AxisId) -> f32 {
+    let settings = config.settings(axis);
+    let own = normalize(axes.raw[axis.index()]);
+
+    let value = match axis.partner() {
+        None => settings.curve.apply(rescale(own.max(0.0), settings.deadzone)),
+        Some(_) if config.stick_mode(axis) == DeadzoneMode::Axial => {
+            own.signum() * settings.curve.apply(rescale(own.abs(), settings.deadzone))
+        }
+        Some(partner) => {
+            let other = normalize(axes.raw[partner.index()]);
+            let (x, y) = if axis.is_x() { (own, other) } else { (other, own) };
+            let magnitude = x.hypot(y);
+            let deadzone = settings.deadzone.max(config.settings(partner).deadzone);
+            let scaled = rescale(magnitude.min(1.0), deadzone);
+            if scaled == 0.0 {
+                0.0
+            } else {
+                (own / magnitude) * settings.curve.apply(scaled)
+            }
+        }
+    };
+
+    if settings.invert {
+        match axis.partner() {
+            Some(_) => -value,
+            None => 1.0 - value,
+        }
+    } else {
+        value
+    }
+}
+
+fn normalize(raw: i16) -> f32 {
+    (raw as f32 / i16::MAX as f32).clamp(-1.0, 1.0)
+}
+
+/// Maps `deadzone..1` onto `0..1`, and everything below the deadzone to 0.
+fn rescale(magnitude: f32, deadzone: f32) -> f32 {
+    let deadzone = deadzone.clamp(0.0, 0.99);
+    if magnitude <= deadzone {
+        0.0
+    } else {
+        ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(fraction: f32) -> i16 {
+        (fraction * i16::MAX as f32).round() as i16
+    }
+
+    fn values(events: &[(u32, AxisEvent)]) -> Vec<(AxisId, f32)> {
+        events.iter().map(|(_, e)| (e.axis, (e.value * 1000.0).round() / 1000.0)).collect()
+    }
+
+    fn stick(config: AxisConfig, x: f32, y: f32) -> (f32, f32) {
+        let mut axes = ControllerAxes::default();
+        axes.raw[AxisId::LeftX.index()] = raw(x);
+        axes.raw[AxisId::LeftY.index()] = raw(y);
+        (process(&config, &axes, AxisId::LeftX), process(&config, &axes, AxisId::LeftY))
+    }
+
+    #[test]
+    fn deadzone_edges_rescale_to_the_full_range() {
+        assert_eq!(rescale(0.0, 0.15), 0.0);
+        assert_eq!(rescale(0.15, 0.15), 0.0);
+        assert!(rescale(0.151, 0.15) > 0.0);
+        assert!((rescale(0.575, 0.15) - 0.5).abs() < 1e-6);
+        assert_eq!(rescale(1.0, 0.15), 1.0);
+        // A deadzone of 1 would divide by zero
+        assert_eq!(rescale(1.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn curves_keep_their_ends_and_bend_the_middle() {
+        for curve in [ResponseCurve::Linear, ResponseCurve::Quadratic, ResponseCurve::Cubic, ResponseCurve::Power(1.5)] {
+            assert_eq!(curve.apply(0.0), 0.0, "{curve:?}");
+            assert_eq!(curve.apply(1.0), 1.0, "{curve:?}");
+        }
+        assert_eq!(ResponseCurve::Linear.apply(0.5), 0.5);
+        assert_eq!(ResponseCurve::Quadratic.apply(0.5), 0.25);
+        assert_eq!(ResponseCurve::Cubic.apply(0.5), 0.125);
+        assert!((ResponseCurve::Power(2.0).apply(0.5) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn radial_deadzone_covers_the_whole_stick() {
+        let radial = AxisConfig::default();
+        // Both axes inside the deadzone, and so is their diagonal
+        assert_eq!(stick(radial.clone(), 0.1, 0.1), (0.0, 0.0));
+        // Outside it, a small X still counts so diagonals stay smooth
+        let (x, y) = stick(radial.clone(), 0.1, 0.9);
+        assert!(x > 0.0 && y > 0.8);
+        // Full deflection reaches 1
+        assert!((stick(radial, -1.0, 0.0).0 + 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn axial_deadzone_applies_per_axis() {
+        let axial = AxisConfig { left_stick_mode: DeadzoneMode::Axial, ..Default::default() };
+        let (x, y) = stick(axial.clone(), 0.1, 0.9);
+        assert_eq!(x, 0.0);
+        assert!((y - 0.8824).abs() < 1e-3);
+        assert!((stick(axial, -0.575, 0.0).0 + 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn invert_flips_sticks_and_triggers() {
+        let mut config = AxisConfig::default();
+        config.axes.insert(AxisId::LeftX, AxisSettings { invert: true, ..Default::default() });
+        config.axes.insert(AxisId::TriggerLeft, AxisSettings { invert: true, deadzone: 0.0, ..Default::default() });
+        let mut axes = ControllerAxes::default();
+        axes.raw[AxisId::LeftX.index()] = i16::MAX;
+        axes.raw[AxisId::TriggerLeft.index()] = raw(0.25);
+        assert!((process(&config, &axes, AxisId::LeftX) + 1.0).abs() < 1e-4);
+        assert!((process(&config, &axes, AxisId::TriggerLeft) - 0.75).abs() < 1e-3);
+    }
+
+    #[test]
+    fn triggers_press_and_release_with_hysteresis() {
+        let triggers = TriggerConfig::default();
+        assert_eq!(triggers.crossed(0), Some(false));
+        assert_eq!(triggers.crossed(raw(0.11)), Some(false));
+        // Between the thresholds the latch keeps whatever state it had
+        assert_eq!(triggers.crossed(raw(0.13)), None);
+        assert_eq!(triggers.crossed(raw(0.24)), None);
+        assert_eq!(triggers.crossed(raw(0.26)), Some(true));
+        assert_eq!(triggers.crossed(i16::MAX), Some(true));
+    }
+
+    #[test]
+    fn release_threshold_is_kept_under_the_press_threshold() {
+        let inverted = TriggerConfig { press_threshold: 0.3, release_threshold: 0.5 };
+        assert_eq!(inverted.crossed(raw(0.4)), Some(true));
+        assert_eq!(inverted.crossed(raw(0.29)), Some(false));
+        // A zero press threshold would hold a resting trigger down
+        let zero = TriggerConfig { press_threshold: 0.0, release_threshold: 0.0 };
+        assert_eq!(zero.crossed(0), None);
+        assert_eq!(zero.crossed(raw(0.02)), Some(true));
+    }
+
+    #[test]
+    fn changes_are_rate_limited_without_losing_the_last_value() {
+        let mut axes = AxisProcessor::default();
+        axes.update(0, AxisId::TriggerRight, i16::MAX);
+        assert_eq!(values(&axes.flush(100)), [(AxisId::TriggerRight, 1.0)]);
+
+        axes.update(0, AxisId::TriggerRight, raw(0.5));
+        assert!(axes.flush(105).is_empty());
+        assert_eq!(axes.next_due(105), Some(11));
+        axes.update(0, AxisId::TriggerRight, 0);
+        // Only the value it settled on goes out
+        assert_eq!(values(&axes.flush(116)), [(AxisId::TriggerRight, 0.0)]);
+        assert_eq!(axes.next_due(116), None);
+    }
+
+    #[test]
+    fn tiny_changes_and_disabled_axes_are_not_reported() {
+        let mut config = AxisConfig::default();
+        config.axes.insert(AxisId::RightY, AxisSettings { enabled: false, ..Default::default() });
+        let mut axes = AxisProcessor::default();
+        axes.set_config(config);
+
+        axes.update(0, AxisId::RightX, raw(0.6));
+        axes.update(0, AxisId::RightY, raw(0.6));
+        assert_eq!(values(&axes.flush(100)).iter().map(|(a, _)| *a).collect::<Vec<_>>(), [AxisId::RightX]);
+        axes.update(0, AxisId::RightX, raw(0.6) + 10);
+        assert!(axes.flush(200).is_empty());
+    }
+}
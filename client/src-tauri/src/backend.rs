@@ -0,0 +1,372 @@
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::axis::AxisId;
+use crate::battery::BatteryStatus;
+use crate::connection::ServerConnection;
+use crate::controller::ControllerInfo;
+use crate::haptic::Rumble;
+use crate::led::LedColor;
+use crate::mapping::{RawInput, RawJoystick};
+use crate::motion::SensorKind;
+use crate::touchpad::TouchpadEvent;
+
+/// Hardware-agnostic input event. Buttons are already W3C indices; `id` is
+/// the instance id of an opened controller, `index` a device slot to open.
+#[derive(Debug, Clone)]
+pub enum BackendEvent {
+    DeviceAdded { index: u32 },
+    DeviceRemoved { id: u32, timestamp: u32 },
+    Button { id: u32, button: u8, pressed: bool, timestamp: u32 },
+    Axis { id: u32, axis: AxisId, value: i16, timestamp: u32 },
+    Touchpad { id: u32, event: TouchpadEvent },
+    Sensor { id: u32, sensor: SensorKind, data: [f32; 3], timestamp: u32 },
+    /// Unmapped joystick input, only sent while raw capture is on.
+    Raw { id: u32, input: RawInput },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Core,
+    Timer,
+    Joystick,
+    GameController,
+    Haptic,
+    Events,
+    /// The user's controller mapping file, which failed to load.
+    Mappings,
+}
+
+/// A backend subsystem that failed to initialise, with the error it gave.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubsystemError {
+    pub subsystem: Subsystem,
+    pub error: String,
+}
+
+impl SubsystemError {
+    pub fn new(subsystem: Subsystem, error: impl ToString) -> Self {
+        Self {
+            subsystem,
+            error: error.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for SubsystemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.subsystem, self.error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendState {
+    /// Not started yet.
+    #[default]
+    Starting,
+    Running,
+    /// Running, but some optional subsystem (e.g. haptics) is missing.
+    Degraded,
+    Failed,
+}
+
+/// Payload of `gamepad_backend_status`.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendStatus {
+    pub state: BackendState,
+    pub errors: Vec<SubsystemError>,
+}
+
+impl BackendStatus {
+    /// Status of a backend that started, possibly without some optional
+    /// subsystems.
+    pub fn running(errors: Vec<SubsystemError>) -> Self {
+        let state = if errors.is_empty() {
+            BackendState::Running
+        } else {
+            BackendState::Degraded
+        };
+        Self { state, errors }
+    }
+
+    pub fn failed(error: SubsystemError) -> Self {
+        Self {
+            state: BackendState::Failed,
+            errors: vec![error],
+        }
+    }
+}
+
+/// Where controller input comes from. The gamepad thread drives SDL2 through
+/// this; tests drive a scripted backend instead.
+pub trait InputBackend {
+    /// Handle that keeps an opened controller alive.
+    type Controller;
+    type Haptic;
+
+    /// Milliseconds since the backend started, on the same clock as event
+    /// timestamps.
+    fn ticks(&self) -> u32;
+    /// Number of device slots present when the backend started.
+    fn device_count(&self) -> u32;
+    /// Blocks until an event arrives, the backend's waker fires or
+    /// `timeout_ms` passes, then returns everything queued.
+    fn wait(&mut self, timeout_ms: u32) -> Vec<BackendEvent>;
+    /// Function that interrupts a `wait` in progress from another thread.
+    fn waker(&self) -> WakeFn;
+    /// Instance id the device in slot `index` has or will get once opened,
+    /// so an already open controller isn't opened a second time.
+    fn instance_id(&self, index: u32) -> Option<u32>;
+    /// Opens the device in slot `index`, or `None` if it isn't a usable
+    /// game controller.
+    fn open(&mut self, index: u32) -> Option<(ControllerInfo, Self::Controller, Option<Self::Haptic>)>;
+    fn set_motion_sensors(&mut self, controller: &Self::Controller, enabled: bool);
+    /// Drives the controller's own low/high-frequency and trigger motors.
+    /// Returns `false` if it has none, so the caller can fall back to the
+    /// haptic device.
+    fn set_rumble(&mut self, controller: &mut Self::Controller, rumble: &Rumble) -> bool;
+    /// Plays `rumble` on the trackpad actuators of controllers that have them
+    /// (the Steam Deck). Returns `false` if it has none or they fail.
+    fn pulse_trackpads(&mut self, controller: &mut Self::Controller, rumble: &Rumble) -> bool;
+    fn rumble(&mut self, haptic: &mut Self::Haptic, strength: f32, duration_ms: u32);
+    fn set_led(&mut self, controller: &mut Self::Controller, color: LedColor);
+    fn stop_rumble(&mut self, haptic: &mut Self::Haptic);
+    /// Current power state, polled since SDL has no battery events.
+    fn battery(&self, controller: &Self::Controller) -> BatteryStatus;
+    /// Adds or replaces an SDL controller mapping. Controllers it applies to
+    /// are remapped, or show up as newly added if they weren't usable before.
+    fn add_mapping(&mut self, mapping: &str) -> Result<(), String>;
+    /// Opens every joystick, mapped or not, and starts sending their raw
+    /// input; or closes them again. Returns the joysticks opened.
+    fn set_raw_capture(&mut self, enabled: bool) -> Vec<RawJoystick>;
+}
+
+pub type WakeFn = Box<dyn Fn() + Send + Sync>;
+
+/// Shared handle for waking the gamepad thread from Tauri commands. It
+/// forwards to whichever backend is attached, and does nothing in between.
+#[derive(Clone, Default)]
+pub struct Waker(Arc<Mutex<Option<WakeFn>>>);
+
+impl Waker {
+    pub fn wake(&self) {
+        if let Ok(wake) = self.0.lock() {
+            if let Some(wake) = wake.as_ref() {
+                wake();
+            }
+        }
+    }
+
+    pub fn set(&self, wake: Option<WakeFn>) {
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = wake;
+        }
+    }
+}
+
+/// Where frontend events go; `AppHandle::emit` in the app.
+pub trait EventSink {
+    fn emit<T: serde::Serialize + Clone>(&self, event: &str, payload: T);
+    /// A press that got past combo detection, alongside its `gamepad_button`
+    /// event. `controller` is the GUID.
+    fn button_pressed(&self, _controller: &str, _button: u8) {}
+}
+
+impl<R: Runtime> EventSink for AppHandle<R> {
+    fn emit<T: serde::Serialize + Clone>(&self, event: &str, payload: T) {
+        let _ = tauri::Emitter::emit(self, event, payload);
+    }
+
+    /// Sends the press to the server directly, so server bindings don't
+    /// depend on the webview being responsive.
+    fn button_pressed(&self, controller: &str, button: u8) {
+        if let Some(server) = self.try_state::<ServerConnection>() {
+            server.button_pressed(controller, button);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet, VecDeque};
+    use std::sync::Condvar;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Backend fed from a script of events. Controller and haptic handles are
+    /// just the instance id, and side effects are recorded for assertions.
+    #[derive(Default)]
+    pub struct ScriptedBackend {
+        /// Device slots; `open(i)` hands out `devices[i]`.
+        pub devices: Vec<ControllerInfo>,
+        /// Whether the device in each slot has a rumble motor.
+        pub haptics: Vec<bool>,
+        pub pending: VecDeque<BackendEvent>,
+        pub now: u32,
+        /// Single-strength rumbles sent to haptic devices.
+        pub rumbles: Vec<(u32, f32, u32)>,
+        /// Controllers with their own rumble motors, by instance id.
+        pub dual_motor: HashSet<u32>,
+        pub motor_rumbles: Vec<(u32, Rumble)>,
+        /// Controllers with trackpad actuators, by instance id.
+        pub trackpads: HashSet<u32>,
+        pub trackpad_pulses: Vec<(u32, Rumble)>,
+        /// Controllers with an LED, by instance id.
+        pub leds: HashSet<u32>,
+        /// Controllers with a gyro, by instance id.
+        pub gyros: HashSet<u32>,
+        pub led_colors: Vec<(u32, LedColor)>,
+        /// Power state by instance id; unlisted controllers report unknown.
+        pub batteries: HashMap<u32, BatteryStatus>,
+        /// Every mapping added, in order.
+        pub mappings: Vec<String>,
+        /// Make `add_mapping` fail, as SDL does for mappings it can't parse.
+        pub reject_mappings: bool,
+        /// Joysticks handed out while raw capture is on.
+        pub joysticks: Vec<RawJoystick>,
+        pub capturing: bool,
+        pub motion: HashMap<u32, bool>,
+        /// Slot of every `open` call.
+        pub opened: Vec<u32>,
+        /// Timeout of every `wait` call, to check how long the thread sleeps.
+        pub waits: Vec<u32>,
+        /// Make `wait` actually block while nothing is pending, until woken.
+        /// Off by default so tests don't stall on an empty script.
+        pub blocking: bool,
+        pub wake: Arc<(Mutex<bool>, Condvar)>,
+    }
+
+    impl ScriptedBackend {
+        pub fn push(&mut self, event: BackendEvent) {
+            self.pending.push_back(event);
+        }
+    }
+
+    impl InputBackend for ScriptedBackend {
+        type Controller = u32;
+        type Haptic = u32;
+
+        fn ticks(&self) -> u32 {
+            self.now
+        }
+
+        fn device_count(&self) -> u32 {
+            self.devices.len() as u32
+        }
+
+        fn wait(&mut self, timeout_ms: u32) -> Vec<BackendEvent> {
+            self.waits.push(timeout_ms);
+            if self.blocking && self.pending.is_empty() {
+                let (woken, signal) = &*self.wake;
+                let guard = woken.lock().unwrap();
+                let timeout = Duration::from_millis(timeout_ms.into());
+                let (mut guard, _) = signal.wait_timeout_while(guard, timeout, |woken| !*woken).unwrap();
+                *guard = false;
+            }
+            self.pending.drain(..).collect()
+        }
+
+        fn waker(&self) -> WakeFn {
+            let wake = self.wake.clone();
+            Box::new(move || {
+                *wake.0.lock().unwrap() = true;
+                wake.1.notify_all();
+            })
+        }
+
+        fn instance_id(&self, index: u32) -> Option<u32> {
+            self.devices.get(index as usize).map(|d| d.id)
+        }
+
+        fn open(&mut self, index: u32) -> Option<(ControllerInfo, u32, Option<u32>)> {
+            self.opened.push(index);
+            let mut info = self.devices.get(index as usize)?.clone();
+            let haptic = self.haptics.get(index as usize).copied().unwrap_or(false).then_some(info.id);
+            info.rumble = haptic.is_some() || self.dual_motor.contains(&info.id) || self.trackpads.contains(&info.id);
+            info.led = self.leds.contains(&info.id);
+            info.motion = self.gyros.contains(&info.id);
+            let id = info.id;
+            Some((info, id, haptic))
+        }
+
+        fn set_motion_sensors(&mut self, controller: &u32, enabled: bool) {
+            self.motion.insert(*controller, enabled);
+        }
+
+        fn set_rumble(&mut self, controller: &mut u32, rumble: &Rumble) -> bool {
+            if !self.dual_motor.contains(controller) {
+                return false;
+            }
+            self.motor_rumbles.push((*controller, *rumble));
+            true
+        }
+
+        fn pulse_trackpads(&mut self, controller: &mut u32, rumble: &Rumble) -> bool {
+            if !self.trackpads.contains(controller) {
+                return false;
+            }
+            self.trackpad_pulses.push((*controller, *rumble));
+            true
+        }
+
+        fn rumble(&mut self, haptic: &mut u32, strength: f32, duration_ms: u32) {
+            self.rumbles.push((*haptic, strength, duration_ms));
+        }
+
+        fn set_led(&mut self, controller: &mut u32, color: LedColor) {
+            self.led_colors.push((*controller, color));
+        }
+
+        /// Recorded as a zero-strength rumble.
+        fn stop_rumble(&mut self, haptic: &mut u32) {
+            self.rumbles.push((*haptic, 0.0, 0));
+        }
+
+        fn battery(&self, controller: &u32) -> BatteryStatus {
+            self.batteries.get(controller).copied().unwrap_or_default()
+        }
+
+        fn add_mapping(&mut self, mapping: &str) -> Result<(), String> {
+            if self.reject_mappings {
+                return Err("invalid mapping".to_string());
+            }
+            self.mappings.push(mapping.to_string());
+            Ok(())
+        }
+
+        fn set_raw_capture(&mut self, enabled: bool) -> Vec<RawJoystick> {
+            self.capturing = enabled;
+            if enabled {
+                self.joysticks.clone()
+            } else {
+                Vec::new()
+            }
+        }
+    }
+
+    /// Sink that keeps every emitted event as JSON.
+    #[derive(Default)]
+    pub struct RecordingSink {
+        pub events: RefCell<Vec<(String, serde_json::Value)>>,
+    }
+
+    impl RecordingSink {
+        /// Drains the recorded events.
+        pub fn take(&self) -> Vec<(String, serde_json::Value)> {
+            self.events.take()
+        }
+    }
+
+    impl EventSink for RecordingSink {
+        fn emit<T: serde::Serialize + Clone>(&self, event: &str, payload: T) {
+            let value = serde_json::to_value(payload).expect("payload serializes");
+            self.events.borrow_mut().push((event.to_string(), value));
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+/// How often controllers are asked for their power level. SDL has no battery
+/// event in the version we bundle, so it has to be polled.
+const BATTERY_POLL_MS: u32 = 10_000;
+/// `gamepad_battery` is re-sent at least this often, even without a change.
+const BATTERY_REPORT_MS: u32 = 60_000;
+/// `gamepad_battery_low` is repeated this often while a pad stays low.
+const LOW_BATTERY_REPEAT_MS: u32 = 5 * 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatteryLevel {
+    Empty,
+    Low,
+    Medium,
+    Full,
+}
+
+/// A controller's power state, as far as its driver reports it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryStatus {
+    /// `None` while wired or when the driver can't tell.
+    pub level: Option<BatteryLevel>,
+    pub wired: bool,
+}
+
+impl BatteryStatus {
+    pub fn is_low(&self) -> bool {
+        !self.wired && matches!(self.level, Some(BatteryLevel::Empty | BatteryLevel::Low))
+    }
+}
+
+/// What to do with a fresh reading.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatteryUpdate {
+    /// The status differs from the last reading.
+    pub changed: bool,
+    /// Emit `gamepad_battery`.
+    pub report: bool,
+    /// Emit `gamepad_battery_low`.
+    pub warn: bool,
+}
+
+struct Tracked {
+    status: BatteryStatus,
+    reported_at: u32,
+    warned_at: Option<u32>,
+}
+
+/// Tracks power readings per controller and decides when they are worth
+/// reporting: on a change, periodically, and as repeated low-battery warnings.
+#[derive(Default)]
+pub struct BatteryMonitor {
+    controllers: HashMap<u32, Tracked>,
+    polled_at: u32,
+}
+
+impl BatteryMonitor {
+    /// Milliseconds until the next poll, if any controller is tracked.
+    pub fn next_due(&self, now: u32) -> Option<u32> {
+        if self.controllers.is_empty() {
+            return None;
+        }
+        Some(BATTERY_POLL_MS.saturating_sub(now.wrapping_sub(self.polled_at)))
+    }
+
+    /// Whether it's time to read every controller again. Marks the poll as
+    /// done if so.
+    pub fn poll(&mut self, now: u32) -> bool {
+        if self.next_due(now) != Some(0) {
+            return false;
+        }
+        self.polled_at = now;
+        true
+    }
+
+    /// Records a reading. The first one for a controller goes out with its
+    /// `gamepad_status`, so only a low warning is raised for it.
+    pub fn update(&mut self, controller: u32, status: BatteryStatus, now: u32) -> BatteryUpdate {
+        if self.controllers.is_empty() {
+            self.polled_at = now;
+        }
+        let Some(tracked) = self.controllers.get_mut(&controller) else {
+            let warned_at = status.is_low().then_some(now);
+            self.controllers.insert(controller, Tracked { status, reported_at: now, warned_at });
+            return BatteryUpdate { warn: status.is_low(), ..Default::default() };
+        };
+
+        let changed = tracked.status != status;
+        let was_low = tracked.status.is_low();
+        tracked.status = status;
+
+        let report = changed || now.wrapping_sub(tracked.reported_at) >= BATTERY_REPORT_MS;
+        if report {
+            tracked.reported_at = now;
+        }
+
+        let warn = status.is_low()
+            && (!was_low || tracked.warned_at.is_none_or(|at| now.wrapping_sub(at) >= LOW_BATTERY_REPEAT_MS));
+        if warn {
+            tracked.warned_at = Some(now);
+        } else if !status.is_low() {
+            tracked.warned_at = None;
+        }
+        BatteryUpdate { changed, report, warn }
+    }
+
+    pub fn remove_controller(&mut self, controller: u32) {
+        self.controllers.remove(&controller);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(level: BatteryLevel) -> BatteryStatus {
+        BatteryStatus { level: Some(level), wired: false }
+    }
+
+    const WIRED: BatteryStatus = BatteryStatus { level: None, wired: true };
+
+    #[test]
+    fn reports_changes_and_periodically() {
+        let mut monitor = BatteryMonitor::default();
+        assert_eq!(monitor.next_due(0), None);
+
+        let first = monitor.update(0, battery(BatteryLevel::Full), 0);
+        assert_eq!(first, BatteryUpdate::default());
+        assert_eq!(monitor.next_due(4_000), Some(6_000));
+        assert!(!monitor.poll(4_000));
+        assert!(monitor.poll(10_000));
+
+        let same = monitor.update(0, battery(BatteryLevel::Full), 10_000);
+        assert_eq!(same, BatteryUpdate::default());
+        let drop = monitor.update(0, battery(BatteryLevel::Medium), 20_000);
+        assert!(drop.changed && drop.report);
+        // Nothing new, but a minute has passed since the last report.
+        let heartbeat = monitor.update(0, battery(BatteryLevel::Medium), 80_000);
+        assert!(!heartbeat.changed && heartbeat.report);
+    }
+
+    #[test]
+    fn low_battery_warns_and_repeats_until_charged() {
+        let mut monitor = BatteryMonitor::default();
+        monitor.update(0, battery(BatteryLevel::Medium), 0);
+        assert!(monitor.update(0, battery(BatteryLevel::Low), 10_000).warn);
+        assert!(!monitor.update(0, battery(BatteryLevel::Low), 20_000).warn);
+        assert!(monitor.update(0, battery(BatteryLevel::Empty), 320_000).warn);
+
+        // Plugging in clears the warning, unplugging low warns again at once.
+        assert!(!monitor.update(0, WIRED, 330_000).warn);
+        assert!(monitor.update(0, battery(BatteryLevel::Low), 340_000).warn);
+    }
+
+    #[test]
+    fn pads_connected_low_warn_immediately() {
+        let mut monitor = BatteryMonitor::default();
+        assert!(monitor.update(3, battery(BatteryLevel::Empty), 0).warn);
+        monitor.remove_controller(3);
+        assert_eq!(monitor.next_due(0), None);
+    }
+}
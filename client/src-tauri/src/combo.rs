@@ -0,0 +1,481 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComboKind {
+    /// All buttons held at once, pressed within `window_ms` of each other
+    /// (default 80 ms).
+    #[default]
+    Chord,
+    /// Buttons pressed in order, each within `window_ms` of the previous one
+    /// (default 500 ms).
+    Sequence,
+}
+
+/// A user-defined combo, identified by `id` in the emitted `gamepad_combo` event.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComboDefinition {
+    pub id: String,
+    pub buttons: Vec<u8>,
+    #[serde(default)]
+    pub kind: ComboKind,
+    #[serde(default)]
+    pub window_ms: Option<u32>,
+}
+
+impl ComboDefinition {
+    fn window(&self) -> u32 {
+        self.window_ms.unwrap_or(match self.kind {
+            ComboKind::Chord => 80,
+            ComboKind::Sequence => 500,
+        })
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComboEvent {
+    pub id: String,
+    pub timestamp: u32,
+}
+
+/// What the recognizer lets through to the rest of the input pipeline.
+#[derive(Debug, Clone)]
+pub enum ComboOutput {
+    Button { button: u8, pressed: bool, timestamp: u32 },
+    Combo(ComboEvent),
+}
+
+#[derive(Default)]
+struct ControllerCombos {
+    /// Held buttons and the time each went down.
+    held: BTreeMap<u8, u32>,
+    /// Presses of chord members held back until the chord window closes, as
+    /// `(button, pressed_at, window_ms)`.
+    pending: Vec<(u8, u32, u32)>,
+    /// Buttons consumed by a combo; their release is swallowed too.
+    suppressed: HashSet<u8>,
+    /// Presses that may start a sequence, and releases of those buttons,
+    /// oldest first as `(button, pressed, timestamp)`. Held back until the
+    /// sequence completes or can't anymore.
+    sequence: Vec<(u8, bool, u32)>,
+    /// Longest gap to the next press that could still continue `sequence`.
+    sequence_window: u32,
+}
+
+impl ControllerCombos {
+    fn sequence_presses(&self) -> impl Iterator<Item = (u8, u32)> + '_ {
+        self.sequence.iter().filter(|&&(_, pressed, _)| pressed).map(|&(button, _, at)| (button, at))
+    }
+
+    /// Delivers the held-back events that come before held-back press number
+    /// `count` (counting from 0), or all of them if there are fewer presses.
+    fn flush_sequence(&mut self, count: usize, out: &mut Vec<ComboOutput>) {
+        let mut presses = 0;
+        let end = self
+            .sequence
+            .iter()
+            .position(|&(_, pressed, _)| {
+                presses += usize::from(pressed);
+                pressed && presses > count
+            })
+            .unwrap_or(self.sequence.len());
+        out.extend(
+            self.sequence
+                .drain(..end)
+                .map(|(button, pressed, timestamp)| ComboOutput::Button { button, pressed, timestamp }),
+        );
+        // Releases of presses just delivered don't wait for the rest
+        let mut kept = HashSet::new();
+        self.sequence.retain(|&(button, pressed, timestamp)| {
+            if pressed || kept.contains(&button) {
+                kept.insert(button);
+                return true;
+            }
+            out.push(ComboOutput::Button { button, pressed, timestamp });
+            false
+        });
+    }
+}
+
+/// How far a run of presses got into the configured sequences.
+enum SequenceProgress<'a> {
+    /// The start of at least one sequence; the next press may come within
+    /// `window` ms.
+    Partial { window: u32 },
+    Complete(&'a ComboDefinition),
+}
+
+/// Recognises chords and ordered sequences per controller. Presses of buttons
+/// that take part in a chord are delayed by the chord's window so they can be
+/// swallowed if the chord completes. Presses that start a sequence are held
+/// back, with their releases, until the sequence completes (and swallows them)
+/// or breaks off (and delivers them late).
+#[derive(Default)]
+pub struct ComboRecognizer {
+    combos: Vec<ComboDefinition>,
+    controllers: HashMap<u32, ControllerCombos>,
+}
+
+impl ComboRecognizer {
+    /// Replaces the combos. Presses held back for the old ones are let
+    /// through, so their releases don't arrive without them; buttons already
+    /// consumed by a combo still have their releases swallowed.
+    pub fn set_combos(&mut self, mut combos: Vec<ComboDefinition>) -> Vec<(u32, ComboOutput)> {
+        combos.retain(|c| !c.buttons.is_empty());
+        // Larger chords win over any chord they contain (L4+A+B over L4+A).
+        combos.sort_by_key(|c| std::cmp::Reverse(c.buttons.len()));
+        self.combos = combos;
+
+        let mut out = Vec::new();
+        for (&controller, state) in self.controllers.iter_mut() {
+            let mut flushed = Vec::new();
+            state.flush_sequence(usize::MAX, &mut flushed);
+            flushed.extend(
+                state
+                    .pending
+                    .drain(..)
+                    .map(|(button, timestamp, _)| ComboOutput::Button { button, pressed: true, timestamp }),
+            );
+            // Chord and sequence hold-backs interleave; put them back in order
+            flushed.sort_by_key(|output| match output {
+                ComboOutput::Button { timestamp, .. } => *timestamp,
+                ComboOutput::Combo(combo) => combo.timestamp,
+            });
+            out.extend(flushed.into_iter().map(|output| (controller, output)));
+        }
+        out
+    }
+
+    pub fn remove_controller(&mut self, controller: u32) {
+        self.controllers.remove(&controller);
+    }
+
+    /// Longest chord window among chords containing `button`, or `None` if the
+    /// button isn't part of any chord and can pass through immediately.
+    fn chord_hold_ms(&self, button: u8) -> Option<u32> {
+        self.combos
+            .iter()
+            .filter(|c| c.kind == ComboKind::Chord && c.buttons.len() > 1 && c.buttons.contains(&button))
+            .map(|c| c.window())
+            .max()
+    }
+
+    pub fn press(&mut self, controller: u32, button: u8, timestamp: u32) -> Vec<ComboOutput> {
+        let hold_ms = self.chord_hold_ms(button);
+        let state = self.controllers.entry(controller).or_default();
+        state.held.insert(button, timestamp);
+
+        let chord = self.combos.iter().find(|c| {
+            c.kind == ComboKind::Chord
+                && c.buttons.contains(&button)
+                && c.buttons.iter().all(|b| {
+                    state
+                        .held
+                        .get(b)
+                        .is_some_and(|&t| timestamp.wrapping_sub(t) <= c.window())
+                })
+        });
+        if let Some(chord) = chord {
+            state.pending.retain(|(b, _, _)| !chord.buttons.contains(b));
+            state.suppressed.extend(chord.buttons.iter().copied());
+            state.sequence.retain(|(b, _, _)| !chord.buttons.contains(b));
+            let mut out = Vec::new();
+            state.flush_sequence(usize::MAX, &mut out);
+            out.push(ComboOutput::Combo(ComboEvent {
+                id: chord.id.clone(),
+                timestamp,
+            }));
+            return out;
+        }
+
+        // The shortest run of recent presses, ending with this one, that a
+        // sequence starts with; anything held back before it is let through.
+        let mut presses: Vec<(u8, u32)> = state.sequence_presses().collect();
+        presses.push((button, timestamp));
+        let (start, progress) = (0..presses.len())
+            .find_map(|i| sequence_progress(&self.combos, &presses[i..]).map(|progress| (i, progress)))
+            .map_or((presses.len(), None), |(i, progress)| (i, Some(progress)));
+        let mut out = Vec::new();
+        state.flush_sequence(start, &mut out);
+
+        match progress {
+            Some(SequenceProgress::Complete(sequence)) => {
+                // Buttons of the sequence still down: their releases go too
+                let mut down: HashSet<u8> = HashSet::from([button]);
+                for &(b, pressed, _) in &state.sequence {
+                    if pressed {
+                        down.insert(b);
+                    } else {
+                        down.remove(&b);
+                    }
+                }
+                state.suppressed.extend(down);
+                state.sequence.clear();
+                out.push(ComboOutput::Combo(ComboEvent {
+                    id: sequence.id.clone(),
+                    timestamp,
+                }));
+            }
+            Some(SequenceProgress::Partial { window }) => {
+                state.sequence.push((button, true, timestamp));
+                state.sequence_window = window;
+            }
+            None if hold_ms.is_some_and(|ms| ms > 0) => {
+                state.pending.push((button, timestamp, hold_ms.unwrap_or_default()));
+            }
+            None => out.push(ComboOutput::Button { button, pressed: true, timestamp }),
+        }
+        out
+    }
+
+    pub fn release(&mut self, controller: u32, button: u8, timestamp: u32) -> Vec<ComboOutput> {
+        let Some(state) = self.controllers.get_mut(&controller) else {
+            return vec![ComboOutput::Button { button, pressed: false, timestamp }];
+        };
+        state.held.remove(&button);
+
+        if state.suppressed.remove(&button) {
+            return Vec::new();
+        }
+        // Its press is still held back for a sequence
+        let last = state.sequence.iter().rev().find(|(b, _, _)| *b == button);
+        if last.is_some_and(|&(_, pressed, _)| pressed) {
+            state.sequence.push((button, false, timestamp));
+            return Vec::new();
+        }
+
+        let mut out = Vec::new();
+        // A tap shorter than the chord window: deliver the held-back press first.
+        if let Some(pos) = state.pending.iter().position(|(b, _, _)| *b == button) {
+            let (_, pressed_at, _) = state.pending.remove(pos);
+            out.push(ComboOutput::Button { button, pressed: true, timestamp: pressed_at });
+        }
+        out.push(ComboOutput::Button { button, pressed: false, timestamp });
+        out
+    }
+
+    /// Milliseconds until `tick` has a held-back press to release, if any.
+    pub fn next_due(&self, now: u32) -> Option<u32> {
+        self.controllers
+            .values()
+            .flat_map(|state| {
+                let sequence = state.sequence_presses().last().map(|(_, at)| (at, state.sequence_window));
+                state.pending.iter().map(|&(_, at, window)| (at, window)).chain(sequence)
+            })
+            .map(|(pressed_at, window)| window.saturating_add(1).saturating_sub(now.wrapping_sub(pressed_at)))
+            .min()
+    }
+
+    /// Releases held-back presses whose chord window has passed without the
+    /// chord completing, and sequence starts that weren't continued in time.
+    /// Each output comes with its controller.
+    pub fn tick(&mut self, now: u32) -> Vec<(u32, ComboOutput)> {
+        let mut out = Vec::new();
+        for (&controller, state) in self.controllers.iter_mut() {
+            let last = state.sequence_presses().last().map(|(_, at)| at);
+            if last.is_some_and(|at| now.wrapping_sub(at) > state.sequence_window) {
+                let mut flushed = Vec::new();
+                state.flush_sequence(usize::MAX, &mut flushed);
+                out.extend(flushed.into_iter().map(|output| (controller, output)));
+            }
+            state.pending.retain(|&(button, pressed_at, window)| {
+                if now.wrapping_sub(pressed_at) > window {
+                    let press = ComboOutput::Button { button, pressed: true, timestamp: pressed_at };
+                    out.push((controller, press));
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        out
+    }
+}
+
+/// Whether `presses` are the start of a sequence in `combos`, or all of one.
+fn sequence_progress<'a>(combos: &'a [ComboDefinition], presses: &[(u8, u32)]) -> Option<SequenceProgress<'a>> {
+    let mut window = None;
+    for combo in combos.iter().filter(|c| c.kind == ComboKind::Sequence) {
+        if !sequence_starts_with(&combo.buttons, presses, combo.window()) {
+            continue;
+        }
+        if combo.buttons.len() == presses.len() {
+            return Some(SequenceProgress::Complete(combo));
+        }
+        window = window.max(Some(combo.window()));
+    }
+    window.map(|window| SequenceProgress::Partial { window })
+}
+
+/// Whether `presses` are `buttons`, or the start of them, each within
+/// `window_ms` of the previous one.
+fn sequence_starts_with(buttons: &[u8], presses: &[(u8, u32)], window_ms: u32) -> bool {
+    presses.len() <= buttons.len()
+        && presses.iter().zip(buttons).all(|(&(pressed, _), &expected)| pressed == expected)
+        && presses.windows(2).all(|pair| pair[1].1.wrapping_sub(pair[0].1) <= window_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const L4: u8 = 20;
+    const A: u8 = 0;
+    const B: u8 = 1;
+    const UP: u8 = 12;
+    const DOWN: u8 = 13;
+
+    fn recognizer() -> ComboRecognizer {
+        let mut combos = ComboRecognizer::default();
+        combos.set_combos(vec![
+            ComboDefinition { id: "l4a".into(), buttons: vec![L4, A], kind: ComboKind::Chord, window_ms: None },
+            ComboDefinition {
+                id: "konami".into(),
+                buttons: vec![UP, UP, DOWN],
+                kind: ComboKind::Sequence,
+                window_ms: None,
+            },
+        ]);
+        combos
+    }
+
+    /// Outputs as `("press"/"release", button, timestamp)` or `("combo id", 0, timestamp)`.
+    fn describe(outputs: impl IntoIterator<Item = ComboOutput>) -> Vec<(String, u8, u32)> {
+        outputs
+            .into_iter()
+            .map(|output| match output {
+                ComboOutput::Button { button, pressed, timestamp } => {
+                    ((if pressed { "press" } else { "release" }).to_string(), button, timestamp)
+                }
+                ComboOutput::Combo(combo) => (combo.id, 0, combo.timestamp),
+            })
+            .collect()
+    }
+
+    fn ticked(combos: &mut ComboRecognizer, now: u32) -> Vec<(String, u8, u32)> {
+        describe(combos.tick(now).into_iter().map(|(_, output)| output))
+    }
+
+    #[test]
+    fn chords_swallow_their_presses() {
+        let mut combos = recognizer();
+        assert!(combos.press(0, L4, 1000).is_empty());
+        assert_eq!(describe(combos.press(0, A, 1040)), [("l4a".into(), 0, 1040)]);
+        assert!(ticked(&mut combos, 2000).is_empty());
+        assert!(combos.release(0, A, 2100).is_empty());
+        assert!(combos.release(0, L4, 2100).is_empty());
+        assert_eq!(combos.next_due(2100), None);
+    }
+
+    #[test]
+    fn chord_members_pass_once_the_window_closes() {
+        let mut combos = recognizer();
+        assert!(combos.press(0, L4, 1000).is_empty());
+        assert_eq!(combos.next_due(1000), Some(81));
+        assert!(ticked(&mut combos, 1080).is_empty());
+        assert_eq!(ticked(&mut combos, 1081), [("press".into(), L4, 1000)]);
+        // Too late to form the chord now
+        assert!(combos.press(0, A, 1100).is_empty());
+        assert_eq!(ticked(&mut combos, 1181), [("press".into(), A, 1100)]);
+        assert_eq!(describe(combos.release(0, L4, 1200)), [("release".into(), L4, 1200)]);
+    }
+
+    #[test]
+    fn quick_taps_of_chord_members_come_out_on_release() {
+        let mut combos = recognizer();
+        combos.press(0, L4, 1000);
+        assert_eq!(
+            describe(combos.release(0, L4, 1030)),
+            [("press".into(), L4, 1000), ("release".into(), L4, 1030)]
+        );
+        assert_eq!(combos.next_due(1030), None);
+        assert_eq!(describe(combos.press(0, B, 1040)), [("press".into(), B, 1040)]);
+    }
+
+    #[test]
+    fn new_combos_let_held_back_presses_through() {
+        let mut combos = recognizer();
+        // UP is held back for a sequence, and L4 on another pad for its chord
+        combos.press(0, UP, 1000);
+        combos.press(1, L4, 1010);
+        let mut flushed = combos.set_combos(Vec::new());
+        flushed.sort_by_key(|&(controller, _)| controller);
+        let flushed = describe(flushed.into_iter().map(|(_, output)| output));
+        assert_eq!(flushed, [("press".into(), UP, 1000), ("press".into(), L4, 1010)]);
+        assert_eq!(combos.next_due(1020), None);
+        assert_eq!(describe(combos.release(1, L4, 1100)), [("release".into(), L4, 1100)]);
+
+        // A button a chord consumed keeps its release swallowed
+        let mut combos = recognizer();
+        combos.press(0, L4, 1000);
+        combos.press(0, A, 1010);
+        assert!(combos.set_combos(Vec::new()).is_empty());
+        assert!(combos.release(0, A, 1100).is_empty());
+    }
+
+    #[test]
+    fn huge_windows_do_not_overflow() {
+        let mut combos = ComboRecognizer::default();
+        combos.set_combos(vec![ComboDefinition {
+            id: "ab".into(),
+            buttons: vec![A, B],
+            kind: ComboKind::Chord,
+            window_ms: Some(u32::MAX),
+        }]);
+        combos.press(0, A, 1000);
+        assert_eq!(combos.next_due(1000), Some(u32::MAX));
+    }
+
+    #[test]
+    fn sequences_swallow_every_press() {
+        let mut combos = recognizer();
+        assert!(combos.press(0, UP, 1000).is_empty());
+        assert!(combos.release(0, UP, 1050).is_empty());
+        assert!(combos.press(0, UP, 1200).is_empty());
+        assert!(combos.release(0, UP, 1250).is_empty());
+        assert_eq!(describe(combos.press(0, DOWN, 1400)), [("konami".into(), 0, 1400)]);
+        assert!(combos.release(0, DOWN, 1450).is_empty());
+        assert!(ticked(&mut combos, 5000).is_empty());
+    }
+
+    #[test]
+    fn broken_sequences_deliver_their_presses_in_order() {
+        let mut combos = recognizer();
+        combos.press(0, UP, 1000);
+        combos.release(0, UP, 1050);
+        assert_eq!(
+            describe(combos.press(0, B, 1100)),
+            [("press".into(), UP, 1000), ("release".into(), UP, 1050), ("press".into(), B, 1100)]
+        );
+    }
+
+    #[test]
+    fn sequences_restart_on_a_repeated_first_press() {
+        let mut combos = recognizer();
+        combos.press(0, UP, 1000);
+        combos.release(0, UP, 1050);
+        combos.press(0, UP, 1100);
+        combos.release(0, UP, 1150);
+        // UP UP UP: the first one can't be part of UP UP DOWN anymore
+        assert_eq!(
+            describe(combos.press(0, UP, 1200)),
+            [("press".into(), UP, 1000), ("release".into(), UP, 1050)]
+        );
+        assert_eq!(describe(combos.press(0, DOWN, 1300)), [("konami".into(), 0, 1300)]);
+    }
+
+    #[test]
+    fn sequences_give_up_after_their_window() {
+        let mut combos = recognizer();
+        combos.press(0, UP, 1000);
+        assert_eq!(combos.next_due(1000), Some(501));
+        assert!(ticked(&mut combos, 1500).is_empty());
+        assert_eq!(ticked(&mut combos, 1501), [("press".into(), UP, 1000)]);
+        assert_eq!(describe(combos.release(0, UP, 1600)), [("release".into(), UP, 1600)]);
+        // A press after the window starts over rather than continuing
+        combos.press(0, UP, 1700);
+        assert_eq!(ticked(&mut combos, 2300), [("press".into(), UP, 1700)]);
+    }
+}
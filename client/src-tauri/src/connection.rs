@@ -0,0 +1,894 @@
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use tungstenite::client::IntoClientRequest;
+use tungstenite::{Message, WebSocket};
+
+use crate::pairing::Pairing;
+use crate::protocol::{BindingKind, ClientMessage, DeckPilotConfig, ServerMessage};
+use crate::transport::{self, Endpoint, Stream, TransportError};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+/// A connection that hasn't heard from the server for this long (pongs
+/// included) is considered dead.
+const SILENCE_TIMEOUT: Duration = Duration::from_secs(25);
+/// After a network change, how long a ping gets to prove the connection
+/// survived it.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionError {
+    InvalidUrl(String),
+    /// The connection thread has exited.
+    Stopped,
+}
+
+impl std::fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionError::InvalidUrl(url) => write!(f, "Not a DeckPilot server URL: {url}"),
+            ConnectionError::Stopped => write!(f, "Server connection thread is not running"),
+        }
+    }
+}
+
+/// The server's WebSocket endpoint for a server URL as the user enters it
+/// (`http://host:port`, or `https://` for a TLS server), the same way the
+/// webview derives it.
+pub fn websocket_url(server_url: &str) -> Result<String, ConnectionError> {
+    let invalid = || ConnectionError::InvalidUrl(server_url.to_string());
+    let (scheme, rest) = server_url.trim().split_once("://").ok_or_else(invalid)?;
+    let scheme = match scheme {
+        "http" | "ws" => "ws",
+        "https" | "wss" => "wss",
+        _ => return Err(invalid()),
+    };
+    let host = rest.split('/').next().unwrap_or_default();
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    Ok(format!("{scheme}://{host}/ws"))
+}
+
+/// Reconnection and offline queue settings.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConnectionConfig {
+    /// Presses kept while the server is unreachable, oldest dropped first.
+    /// 0 turns the queue off.
+    pub queue_size: usize,
+    /// A press that reaches the server later than this after it happened is
+    /// dropped, unless its binding has `replayIfLate`.
+    pub late_after_ms: u32,
+    /// First reconnection delay; it doubles with every failed attempt.
+    pub backoff_min_ms: u32,
+    pub backoff_max_ms: u32,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            queue_size: 32,
+            late_after_ms: 2000,
+            backoff_min_ms: 500,
+            backoff_max_ms: 30_000,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Delay before reconnection attempt number `attempt` (1 for the first
+    /// retry): exponential, capped, and jittered over its upper half so a
+    /// room full of decks doesn't reconnect in lockstep.
+    fn backoff(&self, attempt: u32, rng: &mut fastrand::Rng) -> Duration {
+        let min = u64::from(self.backoff_min_ms.max(1));
+        let max = u64::from(self.backoff_max_ms).max(min);
+        let delay = min.saturating_mul(1 << attempt.saturating_sub(1).min(20)).min(max);
+        Duration::from_millis(delay / 2 + rng.u64(0..=delay / 2))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    /// No server URL given yet.
+    #[default]
+    Idle,
+    Connecting,
+    Connected,
+    /// Waiting to retry.
+    Disconnected,
+}
+
+/// Payload of `connection_status`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatus {
+    pub state: ConnectionState,
+    pub url: Option<String>,
+    /// Failed attempts since the last successful connection.
+    pub attempts: u32,
+    /// Time until the next attempt while disconnected.
+    pub retry_in_ms: Option<u64>,
+    /// Presses waiting to be sent.
+    pub queued: usize,
+    /// Why the last attempt failed or the connection dropped.
+    pub error: Option<String>,
+    /// Set while the server presents a different certificate than the one
+    /// pinned; cleared once the user trusts the new one.
+    pub certificate: Option<CertificateChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateChange {
+    pub expected: String,
+    pub found: String,
+}
+
+/// What the connection thread reports to its owner.
+pub enum ConnectionEvent<'a> {
+    Message(&'a ServerMessage),
+    Status(&'a ConnectionStatus),
+}
+
+/// A gamepad press on its way to the server.
+struct Press {
+    controller: Option<String>,
+    button: u8,
+    at: Instant,
+}
+
+/// What the connection thread waits on: requests from `ServerConnection`,
+/// and reports from the threads it starts, tagged with the `generation` they
+/// were started in.
+enum ConnectionCommand {
+    Connect(String),
+    SetPairing(Option<Pairing>),
+    SetConfig(ConnectionConfig),
+    Send(ClientMessage),
+    Press(Press),
+    NetworkChanged,
+    /// A connection attempt finished.
+    Opened {
+        generation: u64,
+        result: Result<Box<WebSocket<Stream>>, TransportError>,
+    },
+    /// The socket has something to read.
+    Readable(u64),
+    Stop,
+}
+
+/// Handle to the thread that keeps a WebSocket open to the DeckPilot server,
+/// so gamepad bindings fire even while the webview is busy or reloading.
+pub struct ServerConnection {
+    sender: mpsc::Sender<ConnectionCommand>,
+    status: Arc<Mutex<ConnectionStatus>>,
+}
+
+impl ServerConnection {
+    /// Starts the connection thread. It stays idle until `connect` is called;
+    /// `on_event` sees every server message and status change.
+    pub fn spawn(on_event: impl FnMut(ConnectionEvent) + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let status = Arc::new(Mutex::new(ConnectionStatus::default()));
+        let link = Link::new(on_event, status.clone(), sender.clone());
+        thread::spawn(move || run(receiver, link));
+        Self { sender, status }
+    }
+
+    /// Connects to the server at `server_url`, replacing any current
+    /// connection. Returns at once; the outcome is reported as status, and
+    /// failed attempts are retried until one succeeds.
+    pub fn connect(&self, server_url: &str) -> Result<(), ConnectionError> {
+        let url = websocket_url(server_url)?;
+        self.command(ConnectionCommand::Connect(url))
+    }
+
+    /// Credentials to connect with; used for the server they were issued by.
+    /// Reconnects with them straight away.
+    pub fn set_pairing(&self, pairing: Option<Pairing>) {
+        let _ = self.command(ConnectionCommand::SetPairing(pairing));
+    }
+
+    pub fn set_config(&self, config: ConnectionConfig) {
+        let _ = self.command(ConnectionCommand::SetConfig(config));
+    }
+
+    /// Sends `message` if connected; it is dropped otherwise.
+    pub fn send(&self, message: ClientMessage) -> Result<(), ConnectionError> {
+        self.command(ConnectionCommand::Send(message))
+    }
+
+    /// Forwards a gamepad press as `gamepad_button`, unless the server's
+    /// config binds it to a webview-side action. Presses while offline are
+    /// queued.
+    pub fn button_pressed(&self, controller: &str, button: u8) {
+        let controller = (!controller.is_empty()).then(|| controller.to_string());
+        let _ = self.command(ConnectionCommand::Press(Press { controller, button, at: Instant::now() }));
+    }
+
+    /// Hint that the network may have changed (wake from sleep, new WiFi):
+    /// retries at once if disconnected, or checks the connection survived.
+    pub fn network_changed(&self) {
+        let _ = self.command(ConnectionCommand::NetworkChanged);
+    }
+
+    pub fn status(&self) -> ConnectionStatus {
+        self.status.lock().map(|status| status.clone()).unwrap_or_default()
+    }
+
+    fn command(&self, command: ConnectionCommand) -> Result<(), ConnectionError> {
+        self.sender.send(command).map_err(|_| ConnectionError::Stopped)
+    }
+}
+
+impl Drop for ServerConnection {
+    /// The thread hands out its own senders, so it has to be told to stop.
+    fn drop(&mut self) {
+        let _ = self.command(ConnectionCommand::Stop);
+    }
+}
+
+fn run<F: FnMut(ConnectionEvent)>(commands: mpsc::Receiver<ConnectionCommand>, mut link: Link<F>) {
+    loop {
+        // Sleep until a command, something to read, or the next timer.
+        let command = match link.next_due() {
+            Some(due) => commands
+                .recv_timeout(due.saturating_duration_since(Instant::now()))
+                .map_err(|e| e == RecvTimeoutError::Disconnected),
+            None => commands.recv().map_err(|_| true),
+        };
+        match command {
+            Ok(ConnectionCommand::Stop) | Err(true) => return link.close(),
+            Ok(command) => link.command(command),
+            Err(false) => link.tick(),
+        }
+    }
+}
+
+/// Blocks on a clone of the connection's socket and reports `Readable` each
+/// time data arrives, so the connection thread can sleep on its channel
+/// rather than poll. It looks again once `resume` says everything was read,
+/// and exits when the connection thread drops `resume`.
+fn watch(tcp: TcpStream, generation: u64, resume: mpsc::Receiver<()>, commands: mpsc::Sender<ConnectionCommand>) {
+    let mut byte = [0u8; 1];
+    while resume.recv().is_ok() {
+        // Data, the end of the stream and errors all need a read to handle
+        let _ = tcp.peek(&mut byte);
+        if commands.send(ConnectionCommand::Readable(generation)).is_err() {
+            return;
+        }
+    }
+}
+
+/// State of the connection thread.
+struct Link<F> {
+    url: Option<String>,
+    socket: Option<WebSocket<Stream>>,
+    /// Bumped whenever an attempt starts or a connection is dropped, so
+    /// reports from an older one are ignored.
+    generation: u64,
+    /// A connection attempt is running.
+    opening: bool,
+    /// Lets the socket's `watch` thread look for more data.
+    watcher: Option<mpsc::Sender<()>>,
+    /// For the threads the connection starts to report back on.
+    commands: mpsc::Sender<ConnectionCommand>,
+    /// Latest config from the server, for resolving gamepad bindings. Kept
+    /// across reconnects so queued presses can be judged.
+    config: Option<DeckPilotConfig>,
+    settings: ConnectionConfig,
+    pairing: Option<Pairing>,
+    queue: VecDeque<Press>,
+    on_event: F,
+    status: Arc<Mutex<ConnectionStatus>>,
+    rng: fastrand::Rng,
+    attempts: u32,
+    error: Option<String>,
+    certificate: Option<CertificateChange>,
+    retry_at: Instant,
+    pinged_at: Instant,
+    /// When the connection counts as dead unless the server says something.
+    deadline: Instant,
+}
+
+impl<F: FnMut(ConnectionEvent)> Link<F> {
+    fn new(on_event: F, status: Arc<Mutex<ConnectionStatus>>, commands: mpsc::Sender<ConnectionCommand>) -> Self {
+        let now = Instant::now();
+        Self {
+            url: None,
+            socket: None,
+            generation: 0,
+            opening: false,
+            watcher: None,
+            commands,
+            config: None,
+            settings: ConnectionConfig::default(),
+            pairing: None,
+            queue: VecDeque::new(),
+            on_event,
+            status,
+            rng: fastrand::Rng::new(),
+            attempts: 0,
+            error: None,
+            certificate: None,
+            retry_at: now,
+            pinged_at: now,
+            deadline: now,
+        }
+    }
+
+    fn command(&mut self, command: ConnectionCommand) {
+        match command {
+            ConnectionCommand::Connect(url) => {
+                self.close();
+                self.config = None;
+                self.url = Some(url);
+                self.attempts = 0;
+                self.error = None;
+                self.retry_at = Instant::now();
+                self.publish();
+            }
+            ConnectionCommand::SetPairing(pairing) => {
+                self.pairing = pairing;
+                if self.url.is_some() {
+                    self.close();
+                    self.attempts = 0;
+                    self.error = None;
+                    self.retry_at = Instant::now();
+                    self.publish();
+                }
+            }
+            ConnectionCommand::SetConfig(settings) => {
+                self.settings = settings;
+                self.trim_queue();
+                self.publish();
+            }
+            ConnectionCommand::Send(message) => {
+                self.send(&message);
+            }
+            ConnectionCommand::Press(press) => {
+                // Until the config arrives there's no telling which presses
+                // the server should get, so they wait with the rest
+                if self.socket.is_none() || self.config.is_none() || !self.deliver(&press) {
+                    self.queue.push_back(press);
+                    self.trim_queue();
+                    self.publish();
+                }
+            }
+            ConnectionCommand::NetworkChanged => self.network_changed(),
+            ConnectionCommand::Opened { generation, result } if generation == self.generation => self.opened(result),
+            ConnectionCommand::Readable(generation) if generation == self.generation => self.read(),
+            ConnectionCommand::Opened { .. } | ConnectionCommand::Readable(_) | ConnectionCommand::Stop => {}
+        }
+    }
+
+    /// When `tick` next has something to do, or `None` to wait for commands
+    /// alone.
+    fn next_due(&self) -> Option<Instant> {
+        self.url.as_ref()?;
+        if self.socket.is_some() {
+            Some(self.deadline.min(self.pinged_at + PING_INTERVAL))
+        } else if self.opening {
+            None
+        } else {
+            Some(self.retry_at)
+        }
+    }
+
+    /// Keeps the connection alive, or reconnects when it's due. Network
+    /// changes aren't polled for; the frontend reports them, and a dead
+    /// connection misses its ping deadline.
+    fn tick(&mut self) {
+        if self.socket.is_none() {
+            if self.url.is_some() && !self.opening && Instant::now() >= self.retry_at {
+                self.open();
+            }
+        } else if Instant::now() >= self.deadline {
+            self.disconnect("server stopped responding".to_string());
+        } else if self.pinged_at.elapsed() >= PING_INTERVAL {
+            self.ping();
+        }
+    }
+
+    /// Handles everything the server has sent so far, then lets the watcher
+    /// look for more.
+    fn read(&mut self) {
+        let Some(socket) = self.socket.as_mut() else { return };
+        // A read can take in more than the watcher saw, so keep going until
+        // the socket runs dry rather than until the next message.
+        let _ = socket.get_ref().tcp().set_nonblocking(true);
+        let mut texts = Vec::new();
+        let mut heard = false;
+        let mut failed = loop {
+            match socket.read() {
+                Ok(Message::Text(text)) => texts.push(text),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => break None,
+                Err(error) => break Some(error.to_string()),
+            }
+            heard = true;
+        };
+        let _ = socket.get_ref().tcp().set_nonblocking(false);
+        // Send the pongs and close replies queued while reading
+        if failed.is_none() {
+            failed = socket.flush().err().map(|e| e.to_string());
+        }
+
+        if heard {
+            self.deadline = Instant::now() + SILENCE_TIMEOUT;
+        }
+        for text in texts {
+            // Messages from a newer server that this client doesn't know
+            // yet are skipped.
+            if let Ok(message) = serde_json::from_str::<ServerMessage>(&text) {
+                self.receive(message);
+            }
+        }
+        match failed {
+            Some(error) => self.disconnect(error),
+            None => {
+                if let Some(watcher) = &self.watcher {
+                    let _ = watcher.send(());
+                }
+            }
+        }
+    }
+
+    fn receive(&mut self, message: ServerMessage) {
+        if let ServerMessage::Config { payload } | ServerMessage::ConfigUpdated { payload } = &message {
+            self.config = Some(payload.clone());
+            if !self.queue.is_empty() {
+                self.flush();
+                self.publish();
+            }
+        }
+        (self.on_event)(ConnectionEvent::Message(&message));
+    }
+
+    /// Sends `message`, returning whether it went out.
+    fn send(&mut self, message: &ClientMessage) -> bool {
+        let Some(socket) = self.socket.as_mut() else { return false };
+        let text = serde_json::to_string(message).expect("client messages serialize");
+        match socket.send(Message::text(text)) {
+            Ok(()) => true,
+            Err(error) => {
+                self.disconnect(error.to_string());
+                false
+            }
+        }
+    }
+
+    /// Sends a press, or drops it if its binding is handled in the webview
+    /// or it's too late to act on. Returns `false` if it couldn't be sent.
+    fn deliver(&mut self, press: &Press) -> bool {
+        let binding = self
+            .config
+            .as_ref()
+            .and_then(|config| config.gamepad_binding(press.button, press.controller.as_deref()));
+        if binding.is_some_and(|b| b.kind == BindingKind::Client) {
+            return true;
+        }
+        let late = press.at.elapsed() > Duration::from_millis(self.settings.late_after_ms.into());
+        if late && !binding.is_some_and(|b| b.replay_if_late) {
+            return true;
+        }
+        self.send(&ClientMessage::GamepadButton { button: press.button, controller: press.controller.clone() })
+    }
+
+    fn trim_queue(&mut self) {
+        while self.queue.len() > self.settings.queue_size {
+            self.queue.pop_front();
+        }
+    }
+
+    fn ping(&mut self) {
+        self.pinged_at = Instant::now();
+        self.send(&ClientMessage::Ping);
+    }
+
+    fn network_changed(&mut self) {
+        if self.socket.is_some() {
+            // The socket may be dead without having noticed yet
+            self.deadline = self.deadline.min(Instant::now() + PROBE_TIMEOUT);
+            self.ping();
+        } else if self.url.is_some() {
+            self.attempts = 0;
+            self.retry_at = Instant::now();
+            self.publish();
+        }
+    }
+
+    /// Starts a connection attempt on its own thread, so commands are still
+    /// handled while it waits on the network. It reports back as `Opened`.
+    fn open(&mut self) {
+        let Some(url) = self.url.clone() else { return };
+        if self.attempts == 0 {
+            self.publish_state(ConnectionState::Connecting);
+        }
+        let pairing = self
+            .pairing
+            .as_ref()
+            .filter(|pairing| websocket_url(&pairing.server).as_ref() == Ok(&url));
+        let token = pairing.map(|pairing| pairing.token("GET", "/ws", SystemTime::now()));
+        let pin = pairing.and_then(|pairing| pairing.fingerprint.clone());
+        self.generation += 1;
+        self.opening = true;
+        let (generation, commands) = (self.generation, self.commands.clone());
+        thread::spawn(move || {
+            let result = open_socket(&url, token.as_deref(), pin.as_deref()).map(Box::new);
+            let _ = commands.send(ConnectionCommand::Opened { generation, result });
+        });
+    }
+
+    fn opened(&mut self, result: Result<Box<WebSocket<Stream>>, TransportError>) {
+        self.opening = false;
+        match result.and_then(|socket| Ok((socket.get_ref().tcp().try_clone()?, *socket))) {
+            Ok((tcp, socket)) => {
+                let now = Instant::now();
+                self.certificate = None;
+                self.socket = Some(socket);
+                // The server sends its config first on every connection
+                self.config = None;
+                let (resume, resumed) = mpsc::channel();
+                let (generation, commands) = (self.generation, self.commands.clone());
+                thread::spawn(move || watch(tcp, generation, resumed, commands));
+                self.watcher = Some(resume);
+                self.pinged_at = now;
+                self.deadline = now + SILENCE_TIMEOUT;
+                self.attempts = 0;
+                self.error = None;
+                self.publish();
+                // The handshake may have read past its end
+                self.read();
+            }
+            Err(error) => {
+                if let TransportError::CertificateChanged { expected, found } = &error {
+                    self.certificate = Some(CertificateChange { expected: expected.clone(), found: found.clone() });
+                }
+                self.attempts += 1;
+                self.error = Some(error.to_string());
+                self.retry_at = Instant::now() + self.settings.backoff(self.attempts, &mut self.rng);
+                self.publish();
+            }
+        }
+    }
+
+    /// Sends the presses queued while offline, in order, once the server's
+    /// config says which of them it should get.
+    fn flush(&mut self) {
+        while let Some(press) = self.queue.pop_front() {
+            if !self.deliver(&press) {
+                self.queue.push_front(press);
+                return;
+            }
+        }
+    }
+
+    /// Drops the connection, or abandons the attempt in progress. Shutting
+    /// the socket down wakes its watcher so it can exit.
+    fn close(&mut self) {
+        if let Some(socket) = self.socket.take() {
+            let _ = socket.get_ref().tcp().shutdown(Shutdown::Both);
+        }
+        self.watcher = None;
+        self.opening = false;
+        self.generation += 1;
+    }
+
+    fn disconnect(&mut self, reason: String) {
+        self.close();
+        self.attempts = 1;
+        self.error = Some(reason);
+        self.retry_at = Instant::now() + self.settings.backoff(self.attempts, &mut self.rng);
+        self.publish();
+    }
+
+    fn publish(&mut self) {
+        let state = match (&self.url, &self.socket) {
+            (None, _) => ConnectionState::Idle,
+            (Some(_), Some(_)) => ConnectionState::Connected,
+            (Some(_), None) if self.attempts == 0 => ConnectionState::Connecting,
+            (Some(_), None) => ConnectionState::Disconnected,
+        };
+        self.publish_state(state);
+    }
+
+    /// Reports the status with `state`, if anything changed.
+    fn publish_state(&mut self, state: ConnectionState) {
+        let status = ConnectionStatus {
+            state,
+            url: self.url.clone(),
+            attempts: self.attempts,
+            retry_in_ms: (state == ConnectionState::Disconnected)
+                .then(|| self.retry_at.saturating_duration_since(Instant::now()).as_millis() as u64),
+            queued: self.queue.len(),
+            error: self.error.clone(),
+            certificate: self.certificate.clone(),
+        };
+        let Ok(mut current) = self.status.lock() else { return };
+        if *current == status {
+            return;
+        }
+        *current = status.clone();
+        drop(current);
+        (self.on_event)(ConnectionEvent::Status(&status));
+    }
+}
+
+/// Connects to `url`, authenticating with `token` if paired. A `wss`
+/// server's certificate has to match `pin`, the fingerprint pinned when
+/// pairing.
+fn open_socket(url: &str, token: Option<&str>, pin: Option<&str>) -> Result<WebSocket<Stream>, TransportError> {
+    let endpoint = Endpoint::parse(url)?;
+    if endpoint.tls && pin.is_none() {
+        // Without a pin any certificate would do
+        return Err(TransportError::Io("pair with this server to pin its certificate".to_string()));
+    }
+    let mut request = url.into_client_request().map_err(|e| TransportError::Io(e.to_string()))?;
+    if let Some(token) = token {
+        let value = format!("Bearer {token}").parse().map_err(|_| TransportError::Io("invalid token".to_string()))?;
+        request.headers_mut().insert("Authorization", value);
+    }
+    let connected = transport::connect(&endpoint, pin, CONNECT_TIMEOUT)?;
+    let (socket, _) = tungstenite::client(request, connected.stream).map_err(|e| match e {
+        tungstenite::HandshakeError::Failure(tungstenite::Error::Http(response)) if response.status() == 401 => {
+            TransportError::Io("not paired with this server".to_string())
+        }
+        e => TransportError::Io(e.to_string()),
+    })?;
+    // Only the watcher blocks on reads, and it waits for as long as it takes
+    socket.get_ref().tcp().set_read_timeout(None)?;
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn server_urls_map_to_the_websocket_endpoint() {
+        assert_eq!(websocket_url("http://192.168.1.5:9900"), Ok("ws://192.168.1.5:9900/ws".into()));
+        assert_eq!(websocket_url("http://deck.local:9900/app/"), Ok("ws://deck.local:9900/ws".into()));
+        assert!(websocket_url("192.168.1.5:9900").is_err());
+        assert_eq!(websocket_url("https://deck.local:9900"), Ok("wss://deck.local:9900/ws".into()));
+        assert!(websocket_url("http://").is_err());
+    }
+
+    #[test]
+    fn backoff_grows_to_the_cap_with_jitter() {
+        let config = ConnectionConfig { backoff_min_ms: 100, backoff_max_ms: 1000, ..Default::default() };
+        let mut rng = fastrand::Rng::with_seed(7);
+        for _ in 0..20 {
+            let first = config.backoff(1, &mut rng).as_millis();
+            assert!((50..=100).contains(&first), "{first}");
+            let third = config.backoff(3, &mut rng).as_millis();
+            assert!((200..=400).contains(&third), "{third}");
+            let capped = config.backoff(30, &mut rng).as_millis();
+            assert!((500..=1000).contains(&capped), "{capped}");
+        }
+    }
+
+    fn config_message(port: u16) -> Message {
+        let config = json!({
+            "type": "config",
+            "payload": {
+                "version": 1,
+                "server": { "port": port, "host": "127.0.0.1" },
+                "activeProfile": "default",
+                "profiles": [],
+                "gamepadBindings": [
+                    { "button": 1, "kind": "client", "clientAction": "nav.page.next" },
+                    { "button": 2, "kind": "server", "replayIfLate": true }
+                ]
+            }
+        });
+        Message::text(config.to_string())
+    }
+
+    /// Reads `count` text messages and returns them as JSON.
+    fn read_messages(socket: &mut WebSocket<TcpStream>, count: usize) -> Vec<Value> {
+        (0..count)
+            .map(|_| serde_json::from_str(socket.read().unwrap().to_text().unwrap()).unwrap())
+            .collect()
+    }
+
+    fn spawn_connection() -> (ServerConnection, mpsc::Receiver<ConnectionStatus>, mpsc::Receiver<ServerMessage>) {
+        let (status_tx, status_rx) = mpsc::channel();
+        let (message_tx, message_rx) = mpsc::channel();
+        let connection = ServerConnection::spawn(move |event| match event {
+            ConnectionEvent::Status(status) => {
+                let _ = status_tx.send(status.clone());
+            }
+            ConnectionEvent::Message(message) => {
+                let _ = message_tx.send(message.clone());
+            }
+        });
+        (connection, status_rx, message_rx)
+    }
+
+    fn wait_for(statuses: &mpsc::Receiver<ConnectionStatus>, done: impl Fn(&ConnectionStatus) -> bool) -> ConnectionStatus {
+        loop {
+            let status = statuses.recv_timeout(Duration::from_secs(5)).expect("status change");
+            if done(&status) {
+                return status;
+            }
+        }
+    }
+
+    #[test]
+    fn presses_reach_the_server_unless_bound_in_the_webview() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut socket = tungstenite::accept(listener.accept().unwrap().0).unwrap();
+            socket.send(config_message(port)).unwrap();
+            read_messages(&mut socket, 1)
+        });
+
+        let (connection, _, messages) = spawn_connection();
+        connection.connect(&format!("http://127.0.0.1:{port}")).unwrap();
+        let config = messages.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(config, ServerMessage::Config { .. }));
+
+        connection.button_pressed("deck", 1);
+        connection.button_pressed("deck", 3);
+        let sent = server.join().unwrap();
+        assert_eq!(sent, [json!({ "type": "gamepad_button", "button": 3, "controller": "deck" })]);
+    }
+
+    #[test]
+    fn a_stalled_attempt_does_not_hold_up_the_connection() {
+        // Takes the connection into its backlog but never answers the handshake
+        let stalled = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stalled_port = stalled.local_addr().unwrap().port();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut socket = tungstenite::accept(listener.accept().unwrap().0).unwrap();
+            socket.send(config_message(port)).unwrap();
+            read_messages(&mut socket, 1)
+        });
+
+        let (connection, statuses, _) = spawn_connection();
+        let started = Instant::now();
+        connection.connect(&format!("http://127.0.0.1:{stalled_port}")).unwrap();
+        wait_for(&statuses, |s| s.state == ConnectionState::Connecting);
+        connection.button_pressed("", 3);
+        wait_for(&statuses, |s| s.queued == 1);
+
+        connection.connect(&format!("http://127.0.0.1:{port}")).unwrap();
+        let status = wait_for(&statuses, |s| s.state == ConnectionState::Connected);
+        assert!(started.elapsed() < CONNECT_TIMEOUT / 2, "took {:?}", started.elapsed());
+        assert_eq!(status.url, Some(format!("ws://127.0.0.1:{port}/ws")));
+        assert_eq!(server.join().unwrap(), [json!({ "type": "gamepad_button", "button": 3 })]);
+        drop(stalled);
+    }
+
+    #[test]
+    fn paired_connections_send_a_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            // Look at the handshake before letting tungstenite consume it
+            let stream = listener.accept().unwrap().0;
+            let mut buffer = [0u8; 2048];
+            let handshake = loop {
+                let len = stream.peek(&mut buffer).unwrap();
+                let text = String::from_utf8_lossy(&buffer[..len]).into_owned();
+                if text.contains("\r\n\r\n") {
+                    break text;
+                }
+            };
+            let _socket = tungstenite::accept(stream).unwrap();
+            handshake
+                .lines()
+                .find_map(|line| line.strip_prefix("authorization: ").or_else(|| line.strip_prefix("Authorization: ")))
+                .map(str::to_string)
+        });
+
+        let (connection, statuses, _) = spawn_connection();
+        let pairing = Pairing {
+            server: format!("http://127.0.0.1:{port}"),
+            device_id: "d1".into(),
+            key: "00ff".into(),
+            fingerprint: None,
+        };
+        connection.set_pairing(Some(pairing));
+        connection.connect(&format!("http://127.0.0.1:{port}/")).unwrap();
+        wait_for(&statuses, |s| s.state == ConnectionState::Connected);
+        let authorization = server.join().unwrap().expect("an Authorization header");
+        assert!(authorization.starts_with("Bearer d1."), "{authorization}");
+    }
+
+    #[test]
+    fn reconnects_and_replays_queued_presses() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (reopen_tx, reopen_rx) = mpsc::channel::<()>();
+        let server = thread::spawn(move || {
+            // First connection: hand out the config, then go away entirely
+            // so reconnection attempts are refused.
+            let mut socket = tungstenite::accept(listener.accept().unwrap().0).unwrap();
+            socket.send(config_message(port)).unwrap();
+            drop((socket, listener));
+            // Come back once the test has queued its presses.
+            reopen_rx.recv().unwrap();
+            let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+            let mut socket = tungstenite::accept(listener.accept().unwrap().0).unwrap();
+            socket.send(config_message(port)).unwrap();
+            read_messages(&mut socket, 2)
+        });
+
+        let (connection, statuses, messages) = spawn_connection();
+        connection.set_config(ConnectionConfig {
+            late_after_ms: 100,
+            backoff_min_ms: 10,
+            backoff_max_ms: 50,
+            ..Default::default()
+        });
+        connection.connect(&format!("http://127.0.0.1:{port}")).unwrap();
+        messages.recv_timeout(Duration::from_secs(5)).unwrap();
+        wait_for(&statuses, |s| s.state == ConnectionState::Disconnected);
+
+        // Button 2 may replay late, button 3 may not, button 4 is still fresh.
+        connection.button_pressed("", 2);
+        connection.button_pressed("", 3);
+        thread::sleep(Duration::from_millis(150));
+        connection.button_pressed("", 4);
+        assert_eq!(wait_for(&statuses, |s| s.queued == 3).queued, 3);
+
+        reopen_tx.send(()).unwrap();
+        let sent = server.join().unwrap();
+        assert_eq!(sent, [
+            json!({ "type": "gamepad_button", "button": 2 }),
+            json!({ "type": "gamepad_button", "button": 4 }),
+        ]);
+        let status = wait_for(&statuses, |s| s.state == ConnectionState::Connected && s.queued == 0);
+        assert_eq!(status.attempts, 0);
+    }
+
+    #[test]
+    fn queued_presses_wait_for_the_config() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (config_tx, config_rx) = mpsc::channel::<()>();
+        let server = thread::spawn(move || {
+            let stream = listener.accept().unwrap().0;
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            // Nothing may arrive before the config goes out
+            config_rx.recv().unwrap();
+            socket.send(config_message(port)).unwrap();
+            read_messages(&mut socket, 2)
+        });
+
+        let (connection, statuses, _) = spawn_connection();
+        connection.set_config(ConnectionConfig { late_after_ms: 100, ..Default::default() });
+        // Button 1 is bound in the webview, button 2 may replay late
+        connection.button_pressed("", 1);
+        connection.button_pressed("", 2);
+        thread::sleep(Duration::from_millis(150));
+        connection.connect(&format!("http://127.0.0.1:{port}")).unwrap();
+        wait_for(&statuses, |s| s.state == ConnectionState::Connected);
+        connection.button_pressed("", 3);
+        assert_eq!(wait_for(&statuses, |s| s.queued == 3).queued, 3);
+
+        config_tx.send(()).unwrap();
+        let sent = server.join().unwrap();
+        assert_eq!(sent, [
+            json!({ "type": "gamepad_button", "button": 2 }),
+            json!({ "type": "gamepad_button", "button": 3 }),
+        ]);
+        assert_eq!(wait_for(&statuses, |s| s.queued == 0).queued, 0);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::battery::BatteryStatus;
+use sdl2::controller::GameController;
+use sdl2::joystick::Guid;
+use sdl2::sensor::SensorType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControllerKind {
+    SteamDeck,
+    Xbox,
+    PlayStation,
+    Nintendo,
+    Generic,
+}
+
+impl ControllerKind {
+    fn detect(vendor_id: Option<u16>, product_id: Option<u16>) -> Self {
+        match (vendor_id, product_id) {
+            (Some(0x28de), Some(0x1205)) => ControllerKind::SteamDeck,
+            (Some(0x045e), _) => ControllerKind::Xbox,
+            (Some(0x054c), _) => ControllerKind::PlayStation,
+            (Some(0x057e), _) => ControllerKind::Nintendo,
+            _ => ControllerKind::Generic,
+        }
+    }
+}
+
+/// Identity of a connected controller, reported in `gamepad_status` and by
+/// `list_controllers`.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControllerInfo {
+    /// SDL instance id. Unique while connected, but a replugged pad gets a new one.
+    pub id: u32,
+    /// SDL joystick GUID. Stable across reconnects and restarts, so this is
+    /// what per-controller bindings match on.
+    pub guid: String,
+    pub name: String,
+    pub kind: ControllerKind,
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+    /// Whether it can play haptics, through its own motors or a haptic device.
+    /// Filled in by the backend once the haptic side has been opened.
+    pub rumble: bool,
+    /// Whether `set_controller_led` can change its LED or lightbar.
+    pub led: bool,
+    /// Whether it has a gyro, for motion controls and `calibrate_motion`.
+    pub motion: bool,
+    /// Last power reading; refreshed by the gamepad thread's battery poll.
+    pub battery: BatteryStatus,
+}
+
+impl ControllerInfo {
+    pub fn new(controller: &GameController, guid: Guid) -> Self {
+        let vendor_id = controller.vendor_id();
+        let product_id = controller.product_id();
+        Self {
+            id: controller.instance_id(),
+            guid: guid.string(),
+            name: controller.name(),
+            kind: ControllerKind::detect(vendor_id, product_id),
+            vendor_id,
+            product_id,
+            rumble: false,
+            led: controller.has_led(),
+            motion: controller.has_sensor(SensorType::Gyroscope),
+            battery: BatteryStatus::default(),
+        }
+    }
+}
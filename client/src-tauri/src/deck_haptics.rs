@@ -0,0 +1,127 @@
+use crate::haptic::Rumble;
+
+/// Valve feature report that fires a trackpad actuator.
+const ID_TRIGGER_HAPTIC_PULSE: u8 = 0x8F;
+/// One on/off cycle of a pulse train. Strength sets how much of each period
+/// the actuator is on.
+const PULSE_PERIOD_US: u32 = 4000;
+
+/// Feature-report access to a HID device, so the Deck's reports can be
+/// checked without the hardware.
+pub trait HidDevice {
+    fn send_feature_report(&mut self, data: &[u8]) -> Result<(), String>;
+}
+
+/// Trackpad sides, numbered as the firmware expects them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trackpad {
+    Right = 0,
+    Left = 1,
+}
+
+/// The Steam Deck's trackpad actuators, which give far sharper feedback than
+/// SDL's rumble emulation on it.
+pub struct DeckHaptics<D> {
+    device: D,
+}
+
+impl<D: HidDevice> DeckHaptics<D> {
+    pub fn new(device: D) -> Self {
+        Self { device }
+    }
+
+    /// Plays `rumble` on both pads; a zero strength stops that side.
+    pub fn play(&mut self, rumble: &Rumble) -> Result<(), String> {
+        let (left, right) = rumble.pads();
+        self.pulse(Trackpad::Left, left, rumble.duration_ms)?;
+        self.pulse(Trackpad::Right, right, rumble.duration_ms)
+    }
+
+    /// Runs a pulse train on one pad for `duration_ms`, replacing whatever
+    /// that pad was playing.
+    pub fn pulse(&mut self, pad: Trackpad, strength: f32, duration_ms: u32) -> Result<(), String> {
+        let on_us = (strength.clamp(0.0, 1.0) * PULSE_PERIOD_US as f32) as u32;
+        let off_us = PULSE_PERIOD_US - on_us;
+        let repeat = match on_us {
+            0 => 0,
+            _ => (duration_ms.saturating_mul(1000) / PULSE_PERIOD_US).max(1),
+        };
+
+        let mut report = [0u8; 10];
+        // report[0] is the report id, always 0 on Valve controllers
+        report[1] = ID_TRIGGER_HAPTIC_PULSE;
+        report[2] = 7;
+        report[3] = pad as u8;
+        report[4..6].copy_from_slice(&(on_us as u16).to_le_bytes());
+        report[6..8].copy_from_slice(&(off_us as u16).to_le_bytes());
+        report[8..10].copy_from_slice(&(repeat.min(u16::MAX.into()) as u16).to_le_bytes());
+        self.device.send_feature_report(&report)
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::HidDevice;
+
+    /// HID device that records every feature report sent to it.
+    #[derive(Default)]
+    pub struct MockHid {
+        pub reports: Vec<Vec<u8>>,
+        /// Fail every send, as an unplugged device would.
+        pub broken: bool,
+    }
+
+    impl HidDevice for MockHid {
+        fn send_feature_report(&mut self, data: &[u8]) -> Result<(), String> {
+            if self.broken {
+                return Err("device disconnected".to_string());
+            }
+            self.reports.push(data.to_vec());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockHid;
+    use super::*;
+
+    /// Decodes a pulse report into `(pad, on_us, off_us, repeat)`.
+    fn decode(report: &[u8]) -> (u8, u16, u16, u16) {
+        assert_eq!(report[..3], [0, ID_TRIGGER_HAPTIC_PULSE, 7]);
+        let word = |i: usize| u16::from_le_bytes([report[i], report[i + 1]]);
+        (report[3], word(4), word(6), word(8))
+    }
+
+    fn rumble(low: f32, high: f32, left_pad: f32, right_pad: f32) -> Rumble {
+        Rumble { low, high, left_pad, right_pad, duration_ms: 40, ..Rumble::OFF }
+    }
+
+    #[test]
+    fn pads_are_separate_channels() {
+        let mut deck = DeckHaptics::new(MockHid::default());
+        deck.play(&rumble(0.0, 0.0, 1.0, 0.25)).unwrap();
+        let pulses: Vec<_> = deck.device.reports.iter().map(|r| decode(r)).collect();
+        assert_eq!(pulses, vec![(1, 4000, 0, 10), (0, 1000, 3000, 10)]);
+    }
+
+    #[test]
+    fn motor_patterns_map_onto_the_pads() {
+        let mut deck = DeckHaptics::new(MockHid::default());
+        // Low-frequency goes left, high-frequency right.
+        deck.play(&rumble(0.5, 0.0, 0.0, 0.0)).unwrap();
+        let pulses: Vec<_> = deck.device.reports.iter().map(|r| decode(r)).collect();
+        assert_eq!(pulses, vec![(1, 2000, 2000, 10), (0, 0, 4000, 0)]);
+
+        deck.device.reports.clear();
+        deck.play(&Rumble::OFF).unwrap();
+        assert!(deck.device.reports.iter().all(|r| decode(r).3 == 0));
+    }
+
+    #[test]
+    fn send_failures_are_reported() {
+        let mut deck = DeckHaptics::new(MockHid { broken: true, ..Default::default() });
+        assert!(deck.play(&rumble(1.0, 1.0, 0.0, 0.0)).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+
+/// DNS-SD service type the server advertises itself under.
+pub const SERVICE_TYPE: &str = "_deckpilot._tcp.local.";
+/// UDP port the server answers discovery probes on, for networks that drop
+/// multicast.
+pub const DISCOVERY_PORT: u16 = 9900;
+/// Datagram a probe sends; the server replies with a JSON `ProbeReply`.
+pub const PROBE: &[u8] = b"DECKPILOT_DISCOVER";
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// A server found on the LAN, as returned by `discover_servers`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredServer {
+    pub host: String,
+    pub port: u16,
+    pub name: String,
+    pub version: Option<String>,
+    /// Whether the server only speaks `https`/`wss`.
+    pub tls: bool,
+}
+
+impl DiscoveredServer {
+    fn from_service(info: &ServiceInfo) -> Self {
+        // Prefer an IPv4 address: it's what users would have typed, and the
+        // `.local` host name doesn't resolve everywhere.
+        let addresses = info.get_addresses();
+        let address = addresses.iter().find(|ip| ip.is_ipv4()).or_else(|| addresses.iter().next());
+        let host = match address {
+            Some(ip) => ip.to_string(),
+            None => info.get_hostname().trim_end_matches('.').to_string(),
+        };
+        let instance = info.get_fullname().strip_suffix(info.get_type()).unwrap_or(info.get_fullname());
+        Self {
+            host,
+            port: info.get_port(),
+            name: instance.trim_end_matches('.').to_string(),
+            version: info.get_property_val_str("version").map(str::to_string),
+            tls: info.get_property_val_str("tls") == Some("1"),
+        }
+    }
+}
+
+/// What the server sends back to a `PROBE`. The host is where it came from.
+#[derive(Debug, serde::Deserialize)]
+struct ProbeReply {
+    name: String,
+    port: u16,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    tls: bool,
+}
+
+/// Looks for servers for `timeout`, over mDNS and a UDP broadcast probe at
+/// once. A server seen both ways is listed once, as mDNS found it. Fails only
+/// if neither way could be tried.
+pub fn discover(timeout: Duration) -> Result<Vec<DiscoveredServer>, String> {
+    let probe = thread::spawn(move || {
+        probe_servers(SocketAddr::from((Ipv4Addr::BROADCAST, DISCOVERY_PORT)), timeout)
+    });
+    let browsed = browse(SERVICE_TYPE, timeout);
+    let probed = probe
+        .join()
+        .unwrap_or_else(|_| Err(std::io::Error::other("probe thread panicked")));
+    match (browsed, probed) {
+        (Err(mdns), Err(probe)) => Err(format!("mDNS discovery unavailable ({mdns}), probe failed ({probe})")),
+        (browsed, probed) => {
+            let mut servers = browsed.unwrap_or_default();
+            servers.extend(probed.unwrap_or_default());
+            Ok(dedup(servers))
+        }
+    }
+}
+
+/// Browses for `service_type` until `timeout`, returning each resolved
+/// instance.
+pub fn browse(service_type: &str, timeout: Duration) -> Result<Vec<DiscoveredServer>, String> {
+    let daemon = ServiceDaemon::new().map_err(|e| e.to_string())?;
+    let events = daemon.browse(service_type).map_err(|e| e.to_string())?;
+    let deadline = Instant::now() + timeout;
+    let mut servers = Vec::new();
+    while let Ok(event) = events.recv_deadline(deadline) {
+        if let ServiceEvent::ServiceResolved(info) = event {
+            servers.push(DiscoveredServer::from_service(&info));
+        }
+    }
+    let _ = daemon.shutdown();
+    Ok(servers)
+}
+
+/// Sends a `PROBE` to `target` (usually the broadcast address) and collects
+/// replies until `timeout`. Replies that aren't a `ProbeReply` are ignored.
+pub fn probe_servers(target: SocketAddr, timeout: Duration) -> std::io::Result<Vec<DiscoveredServer>> {
+    let bind: SocketAddr = match target.ip() {
+        IpAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        IpAddr::V6(_) => ([0u16; 8], 0).into(),
+    };
+    let socket = UdpSocket::bind(bind)?;
+    socket.set_broadcast(true)?;
+    socket.send_to(PROBE, target)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut servers = Vec::new();
+    let mut buffer = [0u8; 1024];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => break,
+            Err(e) => return Err(e),
+        };
+        if let Ok(reply) = serde_json::from_slice::<ProbeReply>(&buffer[..len]) {
+            servers.push(DiscoveredServer {
+                host: from.ip().to_string(),
+                port: reply.port,
+                name: reply.name,
+                version: reply.version,
+                tls: reply.tls,
+            });
+        }
+    }
+    Ok(servers)
+}
+
+/// Drops later entries for a host and port already listed.
+fn dedup(servers: Vec<DiscoveredServer>) -> Vec<DiscoveredServer> {
+    let mut seen = HashSet::new();
+    servers
+        .into_iter()
+        .filter(|server| seen.insert((server.host.clone(), server.port)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_collect_replies_from_a_local_responder() {
+        let responder = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = responder.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut buffer = [0u8; 64];
+            let (len, from) = responder.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..len], PROBE);
+            responder.send_to(b"not json", from).unwrap();
+            let reply = br#"{"name":"Studio Mac","port":9900,"version":"0.0.1","tls":true}"#;
+            responder.send_to(reply, from).unwrap();
+        });
+
+        let servers = probe_servers(target, Duration::from_millis(300)).unwrap();
+        server.join().unwrap();
+        assert_eq!(servers, [DiscoveredServer {
+            host: "127.0.0.1".into(),
+            port: 9900,
+            name: "Studio Mac".into(),
+            version: Some("0.0.1".into()),
+            tls: true,
+        }]);
+    }
+
+    #[test]
+    fn resolved_services_become_servers() {
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            "Studio Mac",
+            "studio.local.",
+            "192.168.1.20",
+            9900,
+            [("version", "0.0.1")].as_slice(),
+        )
+        .unwrap();
+        let server = DiscoveredServer::from_service(&info);
+        assert_eq!(server, DiscoveredServer {
+            host: "192.168.1.20".into(),
+            port: 9900,
+            name: "Studio Mac".into(),
+            version: Some("0.0.1".into()),
+            tls: false,
+        });
+
+        let probed = DiscoveredServer { name: "studio".into(), version: None, ..server.clone() };
+        assert_eq!(dedup(vec![server.clone(), probed]), [server]);
+    }
+}
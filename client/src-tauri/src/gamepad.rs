@@ -1,145 +1,1285 @@
-use sdl2::controller::Button;
-use sdl2::event::Event;
-use std::sync::mpsc;
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use tauri::{AppHandle, Emitter};
-
-use crate::haptic::HapticRequest;
-
-/// Maps SDL2 GameController buttons to W3C Gamepad API indices.
-fn button_to_w3c(button: Button) -> u8 {
-    match button {
-        Button::A => 0,
-        Button::B => 1,
-        Button::X => 2,
-        Button::Y => 3,
-        Button::LeftShoulder => 4,
-        Button::RightShoulder => 5,
-        Button::Back => 8,
-        Button::Start => 9,
-        Button::LeftStick => 10,
-        Button::RightStick => 11,
-        Button::DPadUp => 12,
-        Button::DPadDown => 13,
-        Button::DPadLeft => 14,
-        Button::DPadRight => 15,
-        Button::Guide => 16,
-        _ => 255,
-    }
-}
+use tauri::{AppHandle, Manager};
+
+use crate::axis::{AxisConfig, AxisId, AxisProcessor, TriggerConfig};
+use crate::backend::{BackendEvent, BackendStatus, EventSink, InputBackend, Waker};
+use crate::battery::{BatteryMonitor, BatteryStatus};
+use crate::combo::{ComboDefinition, ComboOutput, ComboRecognizer};
+use crate::controller::ControllerInfo;
+use crate::gesture::{GestureConfig, GestureDetector};
+use crate::haptic::{DeckHapticMode, HapticConfig, HapticRequest, Rumble};
+use crate::led::{LedColor, LedError, LedManager, LedState};
+use crate::mapping::{mapping_guid, MappingDb, MappingError, RawCapture, RawJoystick, MAPPING_DB_FILE};
+use crate::motion::{MotionConfig, MotionError, MotionOutput, MotionProcessor, SensorKind};
+use crate::registry::{ControllerRegistry, Device};
+use crate::sdl_backend::SdlBackend;
+use crate::touchpad::{TouchpadConfig, TouchpadEvent, TouchpadOutput, TouchpadProcessor};
 
 #[derive(Clone, serde::Serialize)]
 struct GamepadButtonEvent {
     button: u8,
+    pressed: bool,
+}
+
+/// Payload of `gamepad_raw_capture`, sent when capture starts or stops.
+#[derive(Clone, serde::Serialize)]
+struct RawCaptureEvent<'a> {
+    active: bool,
+    joysticks: &'a [RawJoystick],
+}
+
+/// Payload of `gamepad_mapping_error`: a mapping that passed the up-front
+/// check but SDL rejected or couldn't be saved.
+#[derive(Clone, serde::Serialize)]
+struct MappingErrorEvent<'a> {
+    mapping: &'a str,
+    error: String,
 }
 
 #[derive(Clone, serde::Serialize)]
 struct GamepadStatusEvent {
     connected: bool,
-    name: String,
+    #[serde(flatten)]
+    controller: ControllerInfo,
 }
 
-struct ControllerEntry {
-    controller: sdl2::controller::GameController,
+/// Payload wrapper that stamps every input event with the controller it came
+/// from, so the frontend can route bindings per device.
+#[derive(Clone, serde::Serialize)]
+struct ControllerEvent<'a, T> {
+    controller: u32,
+    guid: &'a str,
+    #[serde(flatten)]
+    event: T,
 }
 
-const TRIGGER_THRESHOLD: i16 = 8000;
+/// Settings changes and requests sent from Tauri commands to the gamepad thread.
+pub enum GamepadCommand {
+    SetGestureConfig(GestureConfig),
+    SetCombos(Vec<ComboDefinition>),
+    SetAxisConfig(AxisConfig),
+    SetTriggerConfig(TriggerConfig),
+    SetTouchpadConfig(TouchpadConfig),
+    SetMotionConfig(MotionConfig),
+    SetHapticConfig(HapticConfig),
+    /// Manual LED colour, or `None` to drop it.
+    SetLed { controller: Option<u32>, color: Option<LedColor> },
+    SetLedState(LedState),
+    ClearLedState(String),
+    /// An SDL mapping line, already checked by `GamepadState::add_mapping`.
+    AddMapping(String),
+    SetRawCapture(bool),
+    CalibrateMotion { duration_ms: u32 },
+    ZeroMotion,
+    /// Drops the input backend and initialises it again.
+    RestartBackend,
+}
 
-pub fn spawn_gamepad_thread(app: AppHandle, haptic_rx: mpsc::Receiver<HapticRequest>) {
-    thread::spawn(move || {
-        let sdl = sdl2::init().expect("Failed to init SDL2");
-        let game_controller = sdl.game_controller().expect("Failed to init GameController");
-        let haptic_sub = sdl.haptic().expect("Failed to init haptic subsystem");
-        let mut event_pump = sdl.event_pump().expect("Failed to get event pump");
-
-        let mut controllers: Vec<ControllerEntry> = Vec::new();
-        let mut haptic_devices: Vec<Option<sdl2::haptic::Haptic>> = Vec::new();
-        let mut lt_pressed = false;
-        let mut rt_pressed = false;
-
-        // Open any already-connected controllers
-        for i in 0..game_controller.num_joysticks().unwrap_or(0) {
-            if game_controller.is_game_controller(i) {
-                if let Ok(controller) = game_controller.open(i) {
-                    let _ = app.emit("gamepad_status", GamepadStatusEvent {
-                        connected: true,
-                        name: controller.name(),
-                    });
-                    let h = haptic_sub.open_from_joystick_id(i).ok();
-                    haptic_devices.push(h);
-                    controllers.push(ControllerEntry { controller });
+/// Sending half of a channel into the gamepad thread. Every send wakes the
+/// thread, so requests are handled at once rather than at its next timeout.
+pub struct GamepadSender<T> {
+    sender: mpsc::Sender<T>,
+    waker: Waker,
+}
+
+impl<T> GamepadSender<T> {
+    pub fn new(sender: mpsc::Sender<T>, waker: Waker) -> Self {
+        Self { sender, waker }
+    }
+
+    pub fn send(&self, value: T) -> Result<(), mpsc::SendError<T>> {
+        self.sender.send(value)?;
+        self.waker.wake();
+        Ok(())
+    }
+}
+
+/// State managed by Tauri to reconfigure the gamepad thread at runtime.
+pub struct GamepadState {
+    pub sender: GamepadSender<GamepadCommand>,
+    /// Snapshot of connected controllers, kept current by the gamepad thread.
+    pub controllers: Arc<Mutex<Vec<ControllerInfo>>>,
+    /// Outcome of the last backend (re)start.
+    pub backend_status: Arc<Mutex<BackendStatus>>,
+}
+
+impl GamepadState {
+    /// Sets the manual LED colour, failing up front if no connected
+    /// controller it targets has an LED.
+    pub fn set_led(&self, controller: Option<u32>, color: Option<LedColor>) -> Result<(), LedError> {
+        let controllers = self.controllers.lock().map(|list| list.clone()).unwrap_or_default();
+        match controller {
+            Some(id) => match controllers.iter().find(|c| c.id == id) {
+                None => return Err(LedError::UnknownController(id)),
+                Some(c) if !c.led => return Err(LedError::NoLed(id)),
+                Some(_) => {}
+            },
+            None if !controllers.iter().any(|c| c.led) => return Err(LedError::NoDevice),
+            None => {}
+        }
+        self.sender
+            .send(GamepadCommand::SetLed { controller, color })
+            .map_err(|_| LedError::Stopped)
+    }
+
+    /// Calibrates every controller with a gyro, failing up front if none
+    /// has one.
+    pub fn calibrate_motion(&self, duration_ms: u32) -> Result<(), MotionError> {
+        let controllers = self.controllers.lock().map(|list| list.clone()).unwrap_or_default();
+        if !controllers.iter().any(|c| c.motion) {
+            return Err(MotionError::NoSensors);
+        }
+        self.sender
+            .send(GamepadCommand::CalibrateMotion { duration_ms })
+            .map_err(|_| MotionError::Stopped)
+    }
+
+    /// Adds an SDL mapping line, failing up front if it's malformed. It is
+    /// saved to the user's mapping file once SDL accepts it.
+    pub fn add_mapping(&self, mapping: String) -> Result<(), MappingError> {
+        mapping_guid(&mapping)?;
+        self.sender
+            .send(GamepadCommand::AddMapping(mapping.trim().to_string()))
+            .map_err(|_| MappingError::Stopped)
+    }
+}
+
+/// Turns raw button edges into the events sent to the frontend: combos first,
+/// then whatever presses they let through as `gamepad_button` /
+/// `gamepad_button_up`, and the gestures those presses form. Analog axes are
+/// streamed separately as rate-limited `gamepad_axis` events, and touchpads as
+/// `gamepad_touchpad` plus whatever pointer stream each pad is configured for.
+/// Gyro and accelerometer samples, when enabled, become tilt and flick events.
+/// Every event is tagged with the controller that produced it.
+#[derive(Default)]
+struct InputPipeline {
+    combos: ComboRecognizer,
+    gestures: GestureDetector,
+    axes: AxisProcessor,
+    touchpads: TouchpadProcessor,
+    motion: MotionProcessor,
+    guids: HashMap<u32, String>,
+}
+
+impl InputPipeline {
+    fn emit<T: serde::Serialize + Clone>(&self, sink: &impl EventSink, controller: u32, name: &str, event: T) {
+        let guid = self.guids.get(&controller).map(String::as_str).unwrap_or_default();
+        sink.emit(name, ControllerEvent { controller, guid, event });
+    }
+
+    fn edge(&mut self, sink: &impl EventSink, controller: u32, button: u8, pressed: bool, timestamp: u32) {
+        let outputs = if pressed {
+            self.combos.press(controller, button, timestamp)
+        } else {
+            self.combos.release(controller, button, timestamp)
+        };
+        for output in outputs {
+            self.dispatch(sink, controller, output);
+        }
+    }
+
+    fn tick(&mut self, sink: &impl EventSink, now: u32) {
+        for (controller, output) in self.combos.tick(now) {
+            self.dispatch(sink, controller, output);
+        }
+        for (controller, gesture) in self.gestures.tick(now) {
+            self.emit(sink, controller, "gamepad_gesture", gesture);
+        }
+        for (controller, axis) in self.axes.flush(now) {
+            self.emit(sink, controller, "gamepad_axis", axis);
+        }
+        for (controller, output) in self.motion.tick(now) {
+            self.motion_output(sink, controller, output);
+        }
+    }
+
+    fn touch(&mut self, sink: &impl EventSink, controller: u32, event: TouchpadEvent) {
+        for output in self.touchpads.touch(controller, event) {
+            match output {
+                TouchpadOutput::Raw(e) => self.emit(sink, controller, "gamepad_touchpad", e),
+                TouchpadOutput::Cursor(e) => self.emit(sink, controller, "gamepad_cursor", e),
+                TouchpadOutput::Scroll(e) => self.emit(sink, controller, "gamepad_scroll", e),
+                TouchpadOutput::Radial(e) => self.emit(sink, controller, "gamepad_radial", e),
+            }
+        }
+    }
+
+    fn sensor(&mut self, sink: &impl EventSink, controller: u32, sensor: SensorKind, data: [f32; 3], timestamp: u32) {
+        for output in self.motion.sample(controller, sensor, data, timestamp) {
+            self.motion_output(sink, controller, output);
+        }
+    }
+
+    fn motion_output(&self, sink: &impl EventSink, controller: u32, output: MotionOutput) {
+        match output {
+            MotionOutput::Sensor(e) => self.emit(sink, controller, "gamepad_sensor", e),
+            MotionOutput::Tilt(e) => self.emit(sink, controller, "gamepad_tilt", e),
+            MotionOutput::Gesture(e) => self.emit(sink, controller, "gamepad_motion_gesture", e),
+            MotionOutput::Calibrated(e) => self.emit(sink, controller, "gamepad_motion_calibrated", e),
+            MotionOutput::CalibrationFailed => self.emit(sink, controller, "gamepad_motion_calibration_failed", ()),
+        }
+    }
+
+    /// Milliseconds until `tick` has something to emit, if anything is pending.
+    fn next_due(&self, now: u32) -> Option<u32> {
+        [
+            self.combos.next_due(now),
+            self.gestures.next_due(now),
+            self.axes.next_due(now),
+            self.motion.next_due(now),
+        ]
+        .into_iter()
+            .flatten()
+            .min()
+    }
+
+    fn add_controller(&mut self, info: &ControllerInfo) {
+        self.guids.insert(info.id, info.guid.clone());
+    }
+
+    fn remove_controller(&mut self, controller: u32) {
+        self.combos.remove_controller(controller);
+        self.gestures.remove_controller(controller);
+        self.axes.remove_controller(controller);
+        self.touchpads.remove_controller(controller);
+        self.motion.remove_controller(controller);
+        self.guids.remove(&controller);
+    }
+
+    fn dispatch(&mut self, sink: &impl EventSink, controller: u32, output: ComboOutput) {
+        match output {
+            ComboOutput::Button { button, pressed, timestamp } => {
+                let name = if pressed { "gamepad_button" } else { "gamepad_button_up" };
+                self.emit(sink, controller, name, GamepadButtonEvent { button, pressed });
+                if pressed {
+                    let guid = self.guids.get(&controller).map(String::as_str).unwrap_or_default();
+                    sink.button_pressed(guid, button);
+                }
+
+                let gesture = if pressed {
+                    self.gestures.press(controller, button, timestamp)
+                } else {
+                    self.gestures.release(controller, button, timestamp)
+                };
+                if let Some(gesture) = gesture {
+                    self.emit(sink, controller, "gamepad_gesture", gesture);
                 }
             }
+            ComboOutput::Combo(combo) => self.emit(sink, controller, "gamepad_combo", combo),
         }
+    }
+}
 
-        loop {
-            for event in event_pump.poll_iter() {
-                match event {
-                    Event::ControllerDeviceAdded { which, .. } => {
-                        if let Ok(controller) = game_controller.open(which) {
-                            let _ = app.emit("gamepad_status", GamepadStatusEvent {
-                                connected: true,
-                                name: controller.name(),
-                            });
-                            let h = haptic_sub.open_from_joystick_id(which).ok();
-                            haptic_devices.push(h);
-                            controllers.push(ControllerEntry { controller });
-                        }
-                    }
-                    Event::ControllerDeviceRemoved { which, .. } => {
-                        if let Some(idx) = controllers.iter().position(|e| e.controller.instance_id() == which) {
-                            let removed = controllers.remove(idx);
-                            haptic_devices.remove(idx);
-                            let _ = app.emit("gamepad_status", GamepadStatusEvent {
-                                connected: false,
-                                name: removed.controller.name(),
-                            });
-                            lt_pressed = false;
-                            rt_pressed = false;
-                        }
+/// Upper bound on how long the gamepad thread sleeps with nothing scheduled.
+/// Input and requests wake it anyway; this only caps how late a missed wake-up
+/// could be noticed.
+const IDLE_TIMEOUT_MS: u32 = 1000;
+
+/// The gamepad thread's state between sleeps: the open controllers and the
+/// input pipeline, independent of where input comes from and where events go.
+/// Settings survive the backend being detached and re-attached.
+pub struct Gamepad<B: InputBackend, S: EventSink> {
+    backend: Option<B>,
+    sink: S,
+    registry: ControllerRegistry<B::Controller, B::Haptic>,
+    input: InputPipeline,
+    triggers: TriggerConfig,
+    haptic_config: HapticConfig,
+    leds: LedManager,
+    batteries: BatteryMonitor,
+    /// User mapping file, if the config dir is known.
+    mappings: Option<MappingDb>,
+    capture: Option<RawCapture>,
+    /// Motion sensors were switched on just for a calibration, and go back
+    /// off once it's over.
+    calibration_sensors: bool,
+    controller_list: Arc<Mutex<Vec<ControllerInfo>>>,
+    waker: Waker,
+}
+
+impl<B: InputBackend, S: EventSink> Gamepad<B, S> {
+    pub fn new(sink: S, controller_list: Arc<Mutex<Vec<ControllerInfo>>>, waker: Waker) -> Self {
+        Self {
+            backend: None,
+            sink,
+            registry: ControllerRegistry::default(),
+            input: InputPipeline::default(),
+            triggers: TriggerConfig::default(),
+            haptic_config: HapticConfig::default(),
+            leds: LedManager::default(),
+            batteries: BatteryMonitor::default(),
+            mappings: None,
+            capture: None,
+            calibration_sensors: false,
+            controller_list,
+            waker,
+        }
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// Starts reading from `backend`, opening the controllers already
+    /// connected to it.
+    pub fn attach(&mut self, backend: B) {
+        self.detach();
+        let count = backend.device_count();
+        self.waker.set(Some(backend.waker()));
+        self.backend = Some(backend);
+        // Backends may report these again as DeviceAdded, which the registry
+        // ignores as duplicates.
+        for index in 0..count {
+            self.connect(index);
+        }
+        self.publish();
+    }
+
+    /// Disconnects every controller and drops the backend. Controller
+    /// handles go first, since they may depend on the backend's subsystems.
+    pub fn detach(&mut self) {
+        let Some(backend) = self.backend.as_ref() else { return };
+        let now = backend.ticks();
+        let ids: Vec<u32> = self.registry.ids().collect();
+        for id in ids {
+            self.disconnect(id, now);
+        }
+        if self.capture.take().is_some() {
+            self.sink.emit("gamepad_raw_capture", RawCaptureEvent { active: false, joysticks: &[] });
+        }
+        self.waker.set(None);
+        self.backend = None;
+    }
+
+    /// Sleeps until input arrives, the waker fires or the next gesture,
+    /// combo, axis, haptic, LED or battery timer is due, then handles
+    /// whatever is ready.
+    pub fn step(&mut self) {
+        let leds = self.leds.next_due(self.led_ids());
+        let Some(backend) = self.backend.as_mut() else { return };
+        let now = backend.ticks();
+        let haptics = self.registry.devices().filter_map(|d| d.player.next_due(now));
+        let timeout = [self.input.next_due(now), leds, self.batteries.next_due(now)]
+            .into_iter()
+            .flatten()
+            .chain(haptics)
+            .min()
+            .unwrap_or(IDLE_TIMEOUT_MS);
+        for event in backend.wait(timeout.min(IDLE_TIMEOUT_MS)) {
+            self.handle(event);
+        }
+        if let Some(backend) = &self.backend {
+            self.input.tick(&self.sink, backend.ticks());
+        }
+        self.play_haptics();
+        self.show_leds();
+        self.poll_batteries();
+        self.end_calibration();
+    }
+
+    pub fn command(&mut self, command: GamepadCommand) {
+        match command {
+            GamepadCommand::SetGestureConfig(config) => self.input.gestures.set_config(config),
+            GamepadCommand::SetCombos(combos) => {
+                for (controller, output) in self.input.combos.set_combos(combos) {
+                    self.input.dispatch(&self.sink, controller, output);
+                }
+            }
+            GamepadCommand::SetAxisConfig(config) => self.input.axes.set_config(config),
+            GamepadCommand::SetTriggerConfig(config) => self.triggers = config,
+            GamepadCommand::SetTouchpadConfig(config) => self.input.touchpads.set_config(config),
+            GamepadCommand::SetMotionConfig(config) => {
+                let enabled = config.enabled || self.input.motion.calibrating();
+                if let Some(backend) = self.backend.as_mut() {
+                    for device in self.registry.devices() {
+                        backend.set_motion_sensors(&device.controller, enabled);
                     }
-                    Event::ControllerButtonDown { button, .. } => {
-                        let idx = button_to_w3c(button);
-                        if idx != 255 {
-                            let _ = app.emit("gamepad_button", GamepadButtonEvent { button: idx });
-                        }
+                }
+                self.input.motion.set_config(config);
+            }
+            GamepadCommand::SetHapticConfig(config) => self.haptic_config = config,
+            GamepadCommand::SetLed { controller, color } => {
+                self.leds.set_color(controller, color);
+                self.show_leds();
+            }
+            GamepadCommand::SetLedState(state) => {
+                let now = self.backend.as_ref().map_or(0, |b| b.ticks());
+                self.leds.set_state(state, now);
+                self.show_leds();
+            }
+            GamepadCommand::ClearLedState(id) => {
+                self.leds.clear_state(&id);
+                self.show_leds();
+            }
+            GamepadCommand::AddMapping(mapping) => self.add_mapping(&mapping),
+            GamepadCommand::SetRawCapture(enabled) => {
+                let Some(backend) = self.backend.as_mut() else { return };
+                let joysticks = backend.set_raw_capture(enabled);
+                self.sink.emit("gamepad_raw_capture", RawCaptureEvent { active: enabled, joysticks: &joysticks });
+                self.capture = enabled.then(|| RawCapture::new(joysticks));
+            }
+            GamepadCommand::CalibrateMotion { duration_ms } => {
+                let Some(backend) = self.backend.as_mut() else { return };
+                let pads = || self.registry.devices().filter(|d| d.info.motion);
+                if !self.input.motion.config().enabled {
+                    for device in pads() {
+                        backend.set_motion_sensors(&device.controller, true);
                     }
-                    Event::ControllerAxisMotion { axis, value, .. } => {
-                        match axis {
-                            sdl2::controller::Axis::TriggerLeft => {
-                                if value > TRIGGER_THRESHOLD && !lt_pressed {
-                                    lt_pressed = true;
-                                    let _ = app.emit("gamepad_button", GamepadButtonEvent { button: 6 });
-                                } else if value < TRIGGER_THRESHOLD / 2 {
-                                    lt_pressed = false;
-                                }
-                            }
-                            sdl2::controller::Axis::TriggerRight => {
-                                if value > TRIGGER_THRESHOLD && !rt_pressed {
-                                    rt_pressed = true;
-                                    let _ = app.emit("gamepad_button", GamepadButtonEvent { button: 7 });
-                                } else if value < TRIGGER_THRESHOLD / 2 {
-                                    rt_pressed = false;
-                                }
-                            }
-                            _ => {}
-                        }
+                    self.calibration_sensors = true;
+                }
+                self.input.motion.calibrate(pads().map(|d| d.info.id), backend.ticks(), duration_ms);
+            }
+            GamepadCommand::ZeroMotion => self.input.motion.zero(),
+            // Rebuilding the backend is up to the owner of the loop; all that
+            // can be done here is letting go of the old one.
+            GamepadCommand::RestartBackend => self.detach(),
+        }
+    }
+
+    /// Switches sensors that were only on for a calibration back off once
+    /// it's over.
+    fn end_calibration(&mut self) {
+        if !self.calibration_sensors || self.input.motion.calibrating() {
+            return;
+        }
+        self.calibration_sensors = false;
+        let enabled = self.input.motion.config().enabled;
+        let Some(backend) = self.backend.as_mut() else { return };
+        for device in self.registry.devices() {
+            backend.set_motion_sensors(&device.controller, enabled);
+        }
+    }
+
+    /// Plays or stops patterns on the targeted controllers, or on all of
+    /// them. Controllers that can't rumble are skipped.
+    pub fn haptic(&mut self, request: HapticRequest) {
+        let Some(backend) = self.backend.as_mut() else { return };
+        let now = backend.ticks();
+        let (target, play) = match request {
+            HapticRequest::Play { steps, options } => (options.controller, Some((steps, options.policy))),
+            HapticRequest::Stop { controller } => (controller, None),
+        };
+        let targets = self
+            .registry
+            .devices_mut()
+            .filter(|d| d.info.rumble && target.is_none_or(|id| d.info.id == id));
+        for device in targets {
+            match &play {
+                Some((steps, policy)) => device.player.play(steps.clone(), *policy, now),
+                None => {
+                    if device.player.stop() {
+                        apply_rumble(backend, device, &Rumble::OFF, &self.haptic_config);
                     }
+                }
+            }
+        }
+        self.play_haptics();
+    }
+
+    /// Applies whatever each controller's haptic pattern wants its motors at
+    /// now.
+    fn play_haptics(&mut self) {
+        let Some(backend) = self.backend.as_mut() else { return };
+        let now = backend.ticks();
+        for device in self.registry.devices_mut() {
+            if let Some(level) = device.player.update(now) {
+                apply_rumble(backend, device, &level, &self.haptic_config);
+            }
+        }
+    }
+
+    fn led_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.registry.devices().filter(|d| d.info.led).map(|d| d.info.id)
+    }
+
+    /// Brings every LED in line with the manual colours and active states.
+    fn show_leds(&mut self) {
+        let Some(backend) = self.backend.as_mut() else { return };
+        let now = backend.ticks();
+        for device in self.registry.devices_mut().filter(|d| d.info.led) {
+            let color = match self.leds.color(device.info.id, now) {
+                Some(color) => color,
+                // Nothing to show any more: switch off what we lit
+                None if device.led.is_some() => LedColor::default(),
+                None => continue,
+            };
+            if device.led != Some(color) {
+                backend.set_led(&mut device.controller, color);
+                device.led = Some(color);
+            }
+        }
+    }
+
+    /// Hands `mapping` to SDL and, if it takes it, saves it for next time.
+    /// Either failing is reported as `gamepad_mapping_error`.
+    fn add_mapping(&mut self, mapping: &str) {
+        let Some(backend) = self.backend.as_mut() else { return };
+        let result = backend.add_mapping(mapping).and_then(|()| match &self.mappings {
+            Some(db) => db.save(mapping).map_err(|e| format!("could not save {}: {e}", db.path().display())),
+            None => Ok(()),
+        });
+        if let Err(error) = result {
+            self.sink.emit("gamepad_mapping_error", MappingErrorEvent { mapping, error });
+        }
+    }
+
+    /// Re-reads every controller's power state when the poll is due,
+    /// emitting `gamepad_battery` and `gamepad_battery_low` as the monitor
+    /// decides.
+    fn poll_batteries(&mut self) {
+        let Some(backend) = self.backend.as_ref() else { return };
+        let now = backend.ticks();
+        if !self.batteries.poll(now) {
+            return;
+        }
+        let readings: Vec<_> = self
+            .registry
+            .devices()
+            .map(|d| (d.info.id, backend.battery(&d.controller)))
+            .collect();
+        let mut changed = false;
+        for (id, status) in readings {
+            if self.report_battery(id, status, now) {
+                if let Some(device) = self.registry.get_mut(id) {
+                    device.info.battery = status;
+                }
+                changed = true;
+            }
+        }
+        if changed {
+            self.publish();
+        }
+    }
+
+    /// Records a reading and emits whatever it calls for. Returns whether the
+    /// status changed.
+    fn report_battery(&mut self, controller: u32, status: BatteryStatus, now: u32) -> bool {
+        let update = self.batteries.update(controller, status, now);
+        if update.report {
+            self.input.emit(&self.sink, controller, "gamepad_battery", status);
+        }
+        if update.warn {
+            self.input.emit(&self.sink, controller, "gamepad_battery_low", status);
+        }
+        update.changed
+    }
+
+    /// Opens the device in slot `index` and registers it, announcing it with
+    /// `gamepad_status`. Devices that can't be opened or are already
+    /// registered are skipped.
+    fn connect(&mut self, index: u32) {
+        let Some(backend) = self.backend.as_mut() else { return };
+        // Pads present at startup are reported again as added; opening them
+        // twice would reopen their haptic and HID handles too
+        if backend.instance_id(index).is_some_and(|id| self.registry.contains(id)) {
+            return;
+        }
+        let Some((mut info, controller, haptic)) = backend.open(index) else { return };
+        if self.registry.contains(info.id) {
+            return;
+        }
+        backend.set_motion_sensors(&controller, self.input.motion.config().enabled);
+        info.battery = backend.battery(&controller);
+        let now = backend.ticks();
+        self.input.add_controller(&info);
+        self.sink.emit("gamepad_status", GamepadStatusEvent {
+            connected: true,
+            controller: info.clone(),
+        });
+        self.report_battery(info.id, info.battery, now);
+        self.registry.insert(info, controller, haptic);
+        self.show_leds();
+    }
+
+    fn disconnect(&mut self, id: u32, timestamp: u32) {
+        let Some(removed) = self.registry.remove(id) else { return };
+        self.sink.emit("gamepad_status", GamepadStatusEvent {
+            connected: false,
+            controller: removed.info.clone(),
+        });
+        // Release held buttons and latched triggers so listeners don't see
+        // them stuck down
+        for button in removed.held() {
+            self.input.edge(&self.sink, id, button, false, timestamp);
+        }
+        self.input.remove_controller(id);
+        self.batteries.remove_controller(id);
+        self.publish();
+    }
+
+    fn publish(&self) {
+        if let Ok(mut list) = self.controller_list.lock() {
+            *list = self.registry.infos();
+        }
+    }
+
+    fn handle(&mut self, event: BackendEvent) {
+        let sink = &self.sink;
+        let input = &mut self.input;
+        match event {
+            BackendEvent::DeviceAdded { index } => {
+                self.connect(index);
+                self.publish();
+            }
+            BackendEvent::DeviceRemoved { id, timestamp } => self.disconnect(id, timestamp),
+            BackendEvent::Button { id, button, pressed, timestamp } => {
+                let Some(device) = self.registry.get_mut(id) else { return };
+                let changed = if pressed { device.press(button) } else { device.release(button) };
+                if changed {
+                    input.edge(sink, id, button, pressed, timestamp);
+                }
+            }
+            BackendEvent::Axis { id, axis, value, timestamp } => {
+                input.axes.update(id, axis, value);
+                let button = match axis {
+                    AxisId::TriggerLeft => 6,
+                    AxisId::TriggerRight => 7,
+                    _ => return,
+                };
+                let Some(device) = self.registry.get_mut(id) else { return };
+                match self.triggers.crossed(value) {
+                    Some(true) if device.press(button) => input.edge(sink, id, button, true, timestamp),
+                    Some(false) if device.release(button) => input.edge(sink, id, button, false, timestamp),
                     _ => {}
                 }
             }
+            BackendEvent::Touchpad { id, event } => input.touch(sink, id, event),
+            BackendEvent::Sensor { id, sensor, data, timestamp } => input.sensor(sink, id, sensor, data, timestamp),
+            BackendEvent::Raw { id, input } => {
+                let Some(capture) = self.capture.as_mut() else { return };
+                if let Some(source) = capture.input(id, input) {
+                    sink.emit("gamepad_raw_input", source);
+                }
+            }
+        }
+    }
+}
 
-            // Process haptic requests
-            while let Ok(req) = haptic_rx.try_recv() {
-                for h_opt in haptic_devices.iter_mut() {
-                    if let Some(ref mut h) = h_opt {
-                        h.rumble_play(req.strength, req.duration_ms);
-                    }
+/// Sends `rumble` to the Deck's trackpads if configured, else to the
+/// controller's own motors where it has them, else to its haptic device as a
+/// single strength.
+fn apply_rumble<B: InputBackend>(
+    backend: &mut B,
+    device: &mut Device<B::Controller, B::Haptic>,
+    rumble: &Rumble,
+    config: &HapticConfig,
+) {
+    if config.steam_deck == DeckHapticMode::Trackpads && backend.pulse_trackpads(&mut device.controller, rumble) {
+        return;
+    }
+    if backend.set_rumble(&mut device.controller, rumble) {
+        return;
+    }
+    let Some(haptic) = device.haptic.as_mut() else { return };
+    match rumble.strength() {
+        strength if strength > 0.0 => backend.rumble(haptic, strength, rumble.duration_ms),
+        _ => backend.stop_rumble(haptic),
+    }
+}
+
+/// Initialises SDL and attaches it, reporting the outcome as
+/// `gamepad_backend_status` and in the snapshot behind the command of the
+/// same name.
+fn start_backend(gamepad: &mut Gamepad<SdlBackend, AppHandle>, status: &Mutex<BackendStatus>) {
+    let report = match SdlBackend::new(gamepad.mappings.as_ref().map(MappingDb::path)) {
+        Ok((backend, errors)) => {
+            gamepad.attach(backend);
+            BackendStatus::running(errors)
+        }
+        Err(error) => BackendStatus::failed(error),
+    };
+    gamepad.sink.emit("gamepad_backend_status", report.clone());
+    if let Ok(mut status) = status.lock() {
+        *status = report;
+    }
+}
+
+pub fn spawn_gamepad_thread(
+    app: AppHandle,
+    haptic_rx: mpsc::Receiver<HapticRequest>,
+    command_rx: mpsc::Receiver<GamepadCommand>,
+    controller_list: Arc<Mutex<Vec<ControllerInfo>>>,
+    status: Arc<Mutex<BackendStatus>>,
+    waker: Waker,
+) {
+    thread::spawn(move || {
+        let mappings = app.path().app_config_dir().ok().map(|dir| MappingDb::new(dir.join(MAPPING_DB_FILE)));
+        let mut gamepad = Gamepad::new(app, controller_list, waker);
+        gamepad.mappings = mappings;
+        start_backend(&mut gamepad, &status);
+
+        loop {
+            let first = if gamepad.is_attached() {
+                gamepad.step();
+                None
+            } else {
+                // No backend to wait on; sleep until a command (restart) comes in
+                match command_rx.recv() {
+                    Ok(cmd) => Some(cmd),
+                    Err(_) => return,
+                }
+            };
+
+            for cmd in first.into_iter().chain(command_rx.try_iter()) {
+                let restart = matches!(cmd, GamepadCommand::RestartBackend);
+                gamepad.command(cmd);
+                if restart {
+                    start_backend(&mut gamepad, &status);
                 }
             }
 
-            thread::sleep(Duration::from_millis(8));
+            // Process haptic requests
+            for req in haptic_rx.try_iter() {
+                gamepad.haptic(req);
+            }
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{RecordingSink, ScriptedBackend};
+    use crate::battery::BatteryLevel;
+    use crate::controller::ControllerKind;
+    use crate::haptic::{HapticOptions, HapticPattern, HapticPolicy, HapticStep};
+    use crate::mapping::RawInput;
+    use serde_json::{json, Value};
+
+    fn pad(id: u32, guid: &str) -> ControllerInfo {
+        ControllerInfo {
+            id,
+            guid: guid.to_string(),
+            name: format!("Pad {id}"),
+            kind: ControllerKind::Generic,
+            vendor_id: None,
+            product_id: None,
+            rumble: false,
+            led: false,
+            motion: false,
+            battery: Default::default(),
+        }
+    }
+
+    fn gamepad(devices: Vec<ControllerInfo>) -> Gamepad<ScriptedBackend, RecordingSink> {
+        let backend = ScriptedBackend {
+            haptics: vec![true; devices.len()],
+            devices,
+            ..Default::default()
+        };
+        let mut gamepad = Gamepad::new(RecordingSink::default(), Arc::default(), Waker::default());
+        gamepad.attach(backend);
+        gamepad
+    }
+
+    fn run(gamepad: &mut Gamepad<ScriptedBackend, RecordingSink>, events: Vec<BackendEvent>) -> Vec<(String, Value)> {
+        for event in events {
+            gamepad.backend.as_mut().unwrap().push(event);
+        }
+        gamepad.step();
+        gamepad.sink.take()
+    }
+
+    fn buttons(events: &[(String, Value)]) -> Vec<(u64, u64, bool)> {
+        events
+            .iter()
+            .filter(|(name, _)| name == "gamepad_button" || name == "gamepad_button_up")
+            .map(|(_, v)| {
+                let controller = v["controller"].as_u64().unwrap();
+                (controller, v["button"].as_u64().unwrap(), v["pressed"].as_bool().unwrap())
+            })
+            .collect()
+    }
+
+    fn trigger(id: u32, value: i16) -> BackendEvent {
+        BackendEvent::Axis { id, axis: AxisId::TriggerLeft, value, timestamp: 0 }
+    }
+
+    #[test]
+    fn startup_devices_are_not_opened_twice() {
+        let mut gamepad = gamepad(vec![pad(0, "deck")]);
+        let events = gamepad.sink.take();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, "gamepad_status");
+        assert_eq!(events[0].1["guid"], "deck");
+
+        // SDL re-announces devices that were present at startup.
+        let events = run(&mut gamepad, vec![BackendEvent::DeviceAdded { index: 0 }]);
+        assert!(events.is_empty());
+        assert_eq!(gamepad.controller_list.lock().unwrap().len(), 1);
+        // Its controller and haptic handles are left alone, not reopened.
+        assert_eq!(gamepad.backend.as_ref().unwrap().opened, vec![0]);
+    }
+
+    #[test]
+    fn buttons_are_tagged_with_their_controller() {
+        let mut gamepad = gamepad(vec![pad(0, "a"), pad(1, "b")]);
+        gamepad.sink.take();
+
+        let events = run(&mut gamepad, vec![
+            BackendEvent::Button { id: 1, button: 19, pressed: true, timestamp: 10 },
+            BackendEvent::Button { id: 1, button: 19, pressed: true, timestamp: 11 },
+        ]);
+        assert_eq!(buttons(&events), vec![(1, 19, true)]);
+        assert_eq!(events[0].1["guid"], "b");
+
+        // Input from a controller that was never opened is dropped.
+        let events = run(&mut gamepad, vec![BackendEvent::Button { id: 7, button: 0, pressed: true, timestamp: 12 }]);
+        assert!(buttons(&events).is_empty());
+    }
+
+    #[test]
+    fn trigger_latches_are_per_controller() {
+        let mut gamepad = gamepad(vec![pad(0, "a"), pad(1, "b")]);
+        gamepad.sink.take();
+
+        let events = run(&mut gamepad, vec![trigger(0, 20000), trigger(1, 20000)]);
+        assert_eq!(buttons(&events), vec![(0, 6, true), (1, 6, true)]);
+
+        // Inside the hysteresis band nothing changes; below it only pad 0 lets go.
+        let events = run(&mut gamepad, vec![trigger(0, 6000), trigger(0, 1000), trigger(1, 6000)]);
+        assert_eq!(buttons(&events), vec![(0, 6, false)]);
+
+        gamepad.command(GamepadCommand::SetTriggerConfig(TriggerConfig {
+            press_threshold: 0.9,
+            release_threshold: 0.8,
+        }));
+        let events = run(&mut gamepad, vec![trigger(0, 20000), trigger(1, 20000)]);
+        assert_eq!(buttons(&events), vec![(1, 6, false)]);
+    }
+
+    #[test]
+    fn unplugging_releases_held_inputs() {
+        let mut gamepad = gamepad(vec![pad(0, "a"), pad(1, "b")]);
+        gamepad.sink.take();
+        run(&mut gamepad, vec![
+            trigger(0, 30000),
+            BackendEvent::Button { id: 0, button: 0, pressed: true, timestamp: 0 },
+            BackendEvent::Button { id: 1, button: 0, pressed: true, timestamp: 0 },
+        ]);
+
+        let events = run(&mut gamepad, vec![BackendEvent::DeviceRemoved { id: 0, timestamp: 50 }]);
+        assert_eq!(events[0].0, "gamepad_status");
+        assert_eq!(events[0].1["connected"], json!(false));
+        assert_eq!(buttons(&events), vec![(0, 0, false), (0, 6, false)]);
+        assert_eq!(gamepad.controller_list.lock().unwrap()[0].id, 1);
+    }
+
+    #[test]
+    fn restart_keeps_settings_and_reopens_controllers() {
+        let mut gamepad = gamepad(vec![pad(0, "a")]);
+        gamepad.command(GamepadCommand::SetTriggerConfig(TriggerConfig {
+            press_threshold: 0.9,
+            release_threshold: 0.8,
+        }));
+        run(&mut gamepad, vec![BackendEvent::Button { id: 0, button: 3, pressed: true, timestamp: 0 }]);
+        gamepad.sink.take();
+
+        gamepad.command(GamepadCommand::RestartBackend);
+        assert!(gamepad.backend.is_none());
+        let events = gamepad.sink.take();
+        assert_eq!(events[0].1["connected"], json!(false));
+        assert_eq!(buttons(&events), vec![(0, 3, false)]);
+        assert!(gamepad.controller_list.lock().unwrap().is_empty());
+
+        // Nothing to drive while detached.
+        gamepad.step();
+        gamepad.haptic(play(vec![HapticStep::pulse(1.0, 10)]));
+        assert!(gamepad.sink.take().is_empty());
+
+        // SDL hands out fresh instance ids after a restart.
+        gamepad.attach(ScriptedBackend { devices: vec![pad(5, "a")], ..Default::default() });
+        assert_eq!(gamepad.controller_list.lock().unwrap()[0].id, 5);
+        let events = run(&mut gamepad, vec![trigger(5, 20000)]);
+        assert!(buttons(&events).is_empty());
+    }
+
+    #[test]
+    fn sleeps_until_the_next_timer() {
+        let mut gamepad = gamepad(vec![pad(0, "a")]);
+        gamepad.step();
+        // Nothing scheduled: block for the full idle timeout instead of polling.
+        assert_eq!(gamepad.backend.as_ref().unwrap().waits, vec![IDLE_TIMEOUT_MS]);
+
+        // A held button with the default 500 ms long press wakes up just in time.
+        run(&mut gamepad, vec![BackendEvent::Button { id: 0, button: 0, pressed: true, timestamp: 0 }]);
+        gamepad.backend.as_mut().unwrap().now = 100;
+        gamepad.step();
+        assert_eq!(gamepad.backend.as_ref().unwrap().waits.last(), Some(&400));
+
+        gamepad.backend.as_mut().unwrap().now = 500;
+        gamepad.step();
+        let events = gamepad.sink.take();
+        assert!(events.iter().any(|(_, v)| v["gesture"] == "long_press"));
+    }
+
+    #[test]
+    fn requests_wake_an_idle_thread() {
+        let waker = Waker::default();
+        let (tx, rx) = mpsc::channel();
+        let sender = GamepadSender::new(tx, waker.clone());
+
+        let backend = ScriptedBackend {
+            devices: vec![pad(0, "a")],
+            haptics: vec![true],
+            blocking: true,
+            ..Default::default()
+        };
+        let worker = thread::spawn(move || {
+            let mut gamepad = Gamepad::new(RecordingSink::default(), Arc::default(), waker);
+            gamepad.attach(backend);
+            let started = std::time::Instant::now();
+            gamepad.step();
+            for req in rx.try_iter() {
+                gamepad.haptic(req);
+            }
+            (started.elapsed(), gamepad.backend.unwrap().rumbles)
+        });
+
+        thread::sleep(std::time::Duration::from_millis(50));
+        sender.send(play(vec![HapticStep::pulse(1.0, 20)])).unwrap();
+        let (slept, rumbles) = worker.join().unwrap();
+
+        // Woken by the request long before the idle timeout would have expired.
+        assert!(slept.as_millis() < u128::from(IDLE_TIMEOUT_MS) / 2, "slept {slept:?}");
+        assert_eq!(rumbles, vec![(0, 1.0, 20)]);
+    }
+
+    #[test]
+    fn idle_thread_does_not_wake_periodically() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let waker = Waker::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let backend = ScriptedBackend {
+            devices: vec![pad(0, "a")],
+            blocking: true,
+            ..Default::default()
+        };
+        let worker = thread::spawn({
+            let (waker, stop) = (waker.clone(), stop.clone());
+            move || {
+                let mut gamepad = Gamepad::new(RecordingSink::default(), Arc::default(), waker);
+                gamepad.attach(backend);
+                while !stop.load(Ordering::SeqCst) {
+                    gamepad.step();
+                }
+                gamepad.backend.unwrap().waits
+            }
+        });
+
+        // A connected pad with nothing scheduled sits in a single wait for
+        // the whole interval, rather than looping on a short poll.
+        thread::sleep(std::time::Duration::from_millis(300));
+        stop.store(true, Ordering::SeqCst);
+        waker.wake();
+        assert_eq!(worker.join().unwrap(), vec![IDLE_TIMEOUT_MS]);
+    }
+
+    #[test]
+    fn haptics_follow_hotplug() {
+        let mut gamepad = gamepad(vec![pad(0, "a"), pad(1, "b")]);
+        run(&mut gamepad, vec![BackendEvent::DeviceRemoved { id: 0, timestamp: 0 }]);
+
+        gamepad.haptic(play(vec![HapticStep::pulse(0.5, 40)]));
+        assert_eq!(gamepad.backend.as_ref().unwrap().rumbles, vec![(1, 0.5, 40)]);
+    }
+
+    /// A pattern for every controller, replacing whatever is playing.
+    fn play(steps: Vec<HapticStep>) -> HapticRequest {
+        HapticRequest::Play { steps, options: HapticOptions::default() }
+    }
+
+    /// Advances the clock to `now` and runs one step, returning the rumbles
+    /// sent since the last call.
+    fn rumbles_at(gamepad: &mut Gamepad<ScriptedBackend, RecordingSink>, now: u32) -> Vec<(u32, f32, u32)> {
+        let backend = gamepad.backend.as_mut().unwrap();
+        backend.now = now;
+        backend.rumbles.clear();
+        gamepad.step();
+        gamepad.backend.as_ref().unwrap().rumbles.clone()
+    }
+
+    #[test]
+    fn patterns_play_step_by_step() {
+        let mut gamepad = gamepad(vec![pad(0, "a")]);
+        let pattern = HapticPattern::Preset("confirm".into()).steps().unwrap();
+        gamepad.haptic(play(pattern));
+        assert_eq!(gamepad.backend.as_ref().unwrap().rumbles, vec![(0, 0.5, 40)]);
+
+        // The thread wakes for the pause, then for the second pulse.
+        assert_eq!(rumbles_at(&mut gamepad, 40), vec![(0, 0.0, 0)]);
+        assert_eq!(gamepad.backend.as_ref().unwrap().waits.last(), Some(&40));
+        assert_eq!(rumbles_at(&mut gamepad, 80), vec![(0, 0.8, 60)]);
+        assert!(rumbles_at(&mut gamepad, 100).is_empty());
+        assert_eq!(rumbles_at(&mut gamepad, 140), vec![(0, 0.0, 0)]);
+
+        // Finished: back to idle sleeps.
+        assert!(rumbles_at(&mut gamepad, 200).is_empty());
+        assert_eq!(gamepad.backend.as_ref().unwrap().waits.last(), Some(&IDLE_TIMEOUT_MS));
+    }
+
+    #[test]
+    fn ramps_and_cancellation() {
+        let mut gamepad = gamepad(vec![pad(0, "a")]);
+        let ramp = HapticStep { low: 1.0, high: 1.0, duration_ms: 100, ramp: true, ..Default::default() };
+        gamepad.haptic(play(vec![ramp]));
+        // Ramps start from silence.
+        assert_eq!(gamepad.backend.as_ref().unwrap().rumbles, vec![(0, 0.0, 0)]);
+        assert_eq!(rumbles_at(&mut gamepad, 50), vec![(0, 0.5, 32)]);
+
+        // A new pattern replaces the ramp, and stopping silences it at once.
+        gamepad.haptic(play(vec![HapticStep::pulse(0.3, 500)]));
+        gamepad.backend.as_mut().unwrap().rumbles.clear();
+        gamepad.haptic(HapticRequest::Stop { controller: None });
+        assert_eq!(gamepad.backend.as_ref().unwrap().rumbles, vec![(0, 0.0, 0)]);
+        assert!(rumbles_at(&mut gamepad, 300).is_empty());
+
+        // Stopping when nothing plays doesn't touch the motors.
+        gamepad.haptic(HapticRequest::Stop { controller: None });
+        assert!(gamepad.backend.as_ref().unwrap().rumbles.is_empty());
+    }
+
+    #[test]
+    fn dual_motor_pads_get_every_motor() {
+        let mut gamepad = gamepad(vec![pad(0, "xbox"), pad(1, "generic")]);
+        gamepad.backend.as_mut().unwrap().dual_motor.insert(0);
+        let step = HapticStep { low: 0.2, high: 0.6, right_trigger: 1.0, duration_ms: 50, ..Default::default() };
+        gamepad.haptic(play(vec![step]));
+
+        let backend = gamepad.backend.as_ref().unwrap();
+        let expected = Rumble { low: 0.2, high: 0.6, right_trigger: 1.0, duration_ms: 50, ..Rumble::OFF };
+        assert_eq!(backend.motor_rumbles, vec![(0, expected)]);
+        // The other pad only has a haptic device: the stronger motor wins.
+        assert_eq!(backend.rumbles, vec![(1, 0.6, 50)]);
+
+        assert!(rumbles_at(&mut gamepad, 50).contains(&(1, 0.0, 0)));
+        assert_eq!(gamepad.backend.as_ref().unwrap().motor_rumbles.last(), Some(&(0, Rumble::OFF)));
+    }
+
+    #[test]
+    fn deck_plays_on_its_trackpads_when_selected() {
+        let mut gamepad = gamepad(vec![pad(0, "deck")]);
+        let backend = gamepad.backend.as_mut().unwrap();
+        backend.trackpads.insert(0);
+        backend.dual_motor.insert(0);
+
+        let step = HapticStep { left_pad: 0.8, duration_ms: 30, ..Default::default() };
+        gamepad.haptic(play(vec![step]));
+        let backend = gamepad.backend.as_ref().unwrap();
+        assert_eq!(backend.trackpad_pulses.len(), 1);
+        assert_eq!(backend.trackpad_pulses[0].1.left_pad, 0.8);
+        assert!(backend.motor_rumbles.is_empty());
+
+        // Switched to plain rumble, the left pad channel lands on the low motor.
+        gamepad.command(GamepadCommand::SetHapticConfig(HapticConfig { steam_deck: DeckHapticMode::Rumble }));
+        gamepad.haptic(play(vec![step]));
+        let backend = gamepad.backend.as_ref().unwrap();
+        assert_eq!(backend.trackpad_pulses.len(), 1);
+        assert_eq!(backend.motor_rumbles[0].1.motors(), (0.8, 0.0));
+    }
+
+    #[test]
+    fn haptics_can_target_one_controller() {
+        let mut gamepad = gamepad(vec![pad(0, "a"), pad(1, "b")]);
+        let options = HapticOptions { controller: Some(1), ..Default::default() };
+        gamepad.haptic(HapticRequest::Play { steps: vec![HapticStep::pulse(0.5, 100)], options });
+        assert_eq!(gamepad.backend.as_ref().unwrap().rumbles, vec![(1, 0.5, 100)]);
+
+        // Stopping a controller that isn't playing leaves the other one alone.
+        gamepad.haptic(HapticRequest::Stop { controller: Some(0) });
+        assert_eq!(gamepad.backend.as_ref().unwrap().rumbles.len(), 1);
+        gamepad.haptic(HapticRequest::Stop { controller: Some(1) });
+        assert_eq!(gamepad.backend.as_ref().unwrap().rumbles.last(), Some(&(1, 0.0, 0)));
+    }
+
+    #[test]
+    fn busy_controllers_drop_or_queue_patterns() {
+        let mut gamepad = gamepad(vec![pad(0, "a")]);
+        gamepad.haptic(play(vec![HapticStep::pulse(0.5, 100)]));
+
+        let request = |strength, policy| HapticRequest::Play {
+            steps: vec![HapticStep::pulse(strength, 50)],
+            options: HapticOptions { policy, ..Default::default() },
+        };
+        // A burst of slider ticks while busy is thrown away...
+        for _ in 0..10 {
+            gamepad.haptic(request(0.9, HapticPolicy::DropIfBusy));
+        }
+        // ...and queued patterns are capped.
+        for _ in 0..10 {
+            gamepad.haptic(request(0.3, HapticPolicy::Queue));
+        }
+        assert_eq!(gamepad.backend.as_ref().unwrap().rumbles, vec![(0, 0.5, 100)]);
+
+        let mut played = Vec::new();
+        for now in (100..=400).step_by(50) {
+            played.extend(rumbles_at(&mut gamepad, now));
+        }
+        let queued = played.iter().filter(|&&r| r == (0, 0.3, 50)).count();
+        assert_eq!(queued, 4);
+        assert_eq!(played.last(), Some(&(0, 0.0, 0)));
+
+        // The latest pattern replaces whatever is playing and queued.
+        gamepad.haptic(request(0.3, HapticPolicy::Queue));
+        gamepad.haptic(request(0.3, HapticPolicy::Queue));
+        gamepad.haptic(request(0.7, HapticPolicy::LatestWins));
+        assert_eq!(rumbles_at(&mut gamepad, 450), vec![(0, 0.0, 0)]);
+    }
+
+    #[test]
+    fn led_states_follow_pushed_events() {
+        let red = LedColor { r: 255, g: 0, b: 0 };
+        let mut gamepad = Gamepad::new(RecordingSink::default(), Arc::default(), Waker::default());
+        let leds = [0].into_iter().collect();
+        gamepad.attach(ScriptedBackend { devices: vec![pad(0, "ds"), pad(1, "xbox")], leds, ..Default::default() });
+
+        let muted = LedState {
+            id: "discord_muted".into(),
+            color: red,
+            effect: Default::default(),
+            period_ms: None,
+            priority: 10,
+            controller: None,
+        };
+        gamepad.command(GamepadCommand::SetLedState(muted));
+        // Only the pad with an LED is touched, and repeats are skipped.
+        gamepad.step();
+        assert_eq!(gamepad.backend.as_ref().unwrap().led_colors, vec![(0, red)]);
+
+        gamepad.command(GamepadCommand::ClearLedState("discord_muted".into()));
+        assert_eq!(gamepad.backend.as_ref().unwrap().led_colors.last(), Some(&(0, LedColor::default())));
+
+        // Pads plugged in later pick up the current colour.
+        gamepad.command(GamepadCommand::SetLed { controller: None, color: Some(red) });
+        gamepad.backend.as_mut().unwrap().devices.push(pad(2, "ds"));
+        gamepad.backend.as_mut().unwrap().leds.insert(2);
+        run(&mut gamepad, vec![BackendEvent::DeviceAdded { index: 2 }]);
+        assert_eq!(gamepad.backend.as_ref().unwrap().led_colors.last(), Some(&(2, red)));
+    }
+
+    #[test]
+    fn battery_is_polled_and_low_pads_warn() {
+        let medium = BatteryStatus { level: Some(BatteryLevel::Medium), wired: false };
+        let low = BatteryStatus { level: Some(BatteryLevel::Low), wired: false };
+        let batteries = [(0, medium)].into_iter().collect();
+        let mut gamepad = Gamepad::new(RecordingSink::default(), Arc::default(), Waker::default());
+        gamepad.attach(ScriptedBackend { devices: vec![pad(0, "ds")], batteries, ..Default::default() });
+        let events = gamepad.sink.take();
+        assert_eq!(events[0].1["battery"], json!({ "level": "medium", "wired": false }));
+
+        // Nothing is read again until the poll is due.
+        gamepad.backend.as_mut().unwrap().batteries.insert(0, low);
+        assert!(run(&mut gamepad, vec![]).is_empty());
+
+        gamepad.backend.as_mut().unwrap().now = 10_000;
+        let events = run(&mut gamepad, vec![]);
+        let names: Vec<_> = events.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["gamepad_battery", "gamepad_battery_low"]);
+        assert_eq!(events[1].1, json!({ "controller": 0, "guid": "ds", "level": "low", "wired": false }));
+        assert_eq!(gamepad.controller_list.lock().unwrap()[0].battery, low);
+    }
+
+    #[test]
+    fn raw_capture_reports_sources_and_mappings_reach_the_backend() {
+        let pedals = RawJoystick {
+            id: 5,
+            guid: "03000000aa0b0000cc0d000000010000".into(),
+            name: "Pedals".into(),
+            buttons: 2,
+            axes: 1,
+            hats: 0,
+            rest: vec![-32768],
+        };
+        let mut gamepad = Gamepad::new(RecordingSink::default(), Arc::default(), Waker::default());
+        gamepad.attach(ScriptedBackend { joysticks: vec![pedals], ..Default::default() });
+
+        // Raw input is ignored until capture starts.
+        assert!(run(&mut gamepad, vec![BackendEvent::Raw { id: 5, input: RawInput::Button { index: 1, pressed: true } }]).is_empty());
+
+        gamepad.command(GamepadCommand::SetRawCapture(true));
+        let events = gamepad.sink.take();
+        assert_eq!(events[0].0, "gamepad_raw_capture");
+        assert_eq!(events[0].1["joysticks"][0]["name"], "Pedals");
+
+        let events = run(&mut gamepad, vec![
+            BackendEvent::Raw { id: 5, input: RawInput::Button { index: 1, pressed: true } },
+            BackendEvent::Raw { id: 5, input: RawInput::Axis { index: 0, value: 20000 } },
+        ]);
+        let sources: Vec<_> = events.iter().map(|(_, v)| v["source"].clone()).collect();
+        assert_eq!(sources, [json!("b1"), json!("a0")]);
+
+        let mapping = "03000000aa0b0000cc0d000000010000,Pedals,a:b1,righttrigger:a0,";
+        gamepad.command(GamepadCommand::AddMapping(mapping.into()));
+        gamepad.command(GamepadCommand::SetRawCapture(false));
+        let backend = gamepad.backend.as_ref().unwrap();
+        assert_eq!(backend.mappings, [mapping]);
+        assert!(!backend.capturing);
+        assert!(!gamepad.sink.take().iter().any(|(name, _)| name == "gamepad_mapping_error"));
+    }
+
+    #[test]
+    fn rejected_mappings_are_reported() {
+        let mut gamepad = gamepad(vec![pad(0, "a")]);
+        gamepad.backend.as_mut().unwrap().reject_mappings = true;
+        gamepad.sink.take();
+
+        let mapping = "03000000aa0b0000cc0d000000010000,Pedals,a:b99,";
+        gamepad.command(GamepadCommand::AddMapping(mapping.into()));
+        let events = gamepad.sink.take();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, "gamepad_mapping_error");
+        assert_eq!(events[0].1["mapping"], mapping);
+        assert!(gamepad.backend.as_ref().unwrap().mappings.is_empty());
+    }
+
+    #[test]
+    fn motion_config_reaches_every_controller() {
+        let mut gamepad = gamepad(vec![pad(0, "a"), pad(1, "b")]);
+        assert_eq!(gamepad.backend.as_ref().unwrap().motion.get(&0), Some(&false));
+
+        let config = MotionConfig { enabled: true, ..Default::default() };
+        gamepad.command(GamepadCommand::SetMotionConfig(config));
+        assert_eq!(gamepad.backend.as_ref().unwrap().motion.get(&0), Some(&true));
+        assert_eq!(gamepad.backend.as_ref().unwrap().motion.get(&1), Some(&true));
+    }
+
+    #[test]
+    fn calibration_turns_sensors_on_until_it_times_out() {
+        let backend = ScriptedBackend {
+            devices: vec![pad(0, "deck"), pad(1, "xbox")],
+            gyros: [0].into(),
+            ..Default::default()
+        };
+        let mut gamepad = Gamepad::new(RecordingSink::default(), Arc::default(), Waker::default());
+        gamepad.attach(backend);
+        gamepad.sink.take();
+
+        gamepad.command(GamepadCommand::CalibrateMotion { duration_ms: 500 });
+        let motion = &gamepad.backend.as_ref().unwrap().motion;
+        assert_eq!((motion.get(&0), motion.get(&1)), (Some(&true), Some(&false)));
+        assert!(run(&mut gamepad, vec![]).is_empty());
+        assert_eq!(gamepad.backend.as_ref().unwrap().waits.last(), Some(&500));
+
+        // No sensor samples ever came in
+        gamepad.backend.as_mut().unwrap().now = 500;
+        let events = run(&mut gamepad, vec![]);
+        assert_eq!(events, [("gamepad_motion_calibration_failed".to_string(), json!({ "controller": 0, "guid": "deck" }))]);
+        assert_eq!(gamepad.backend.as_ref().unwrap().motion.get(&0), Some(&false));
+    }
+
+    #[test]
+    fn calibration_needs_a_controller_with_a_gyro() {
+        let (sender, _commands) = mpsc::channel();
+        let state = GamepadState {
+            sender: GamepadSender::new(sender, Waker::default()),
+            controllers: Arc::new(Mutex::new(vec![pad(0, "xbox")])),
+            backend_status: Arc::default(),
+        };
+        assert_eq!(state.calibrate_motion(1000), Err(MotionError::NoSensors));
+        state.controllers.lock().unwrap()[0].motion = true;
+        assert_eq!(state.calibrate_motion(1000), Ok(()));
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+
+/// Timing thresholds used to classify a button's presses, in milliseconds.
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GestureTiming {
+    /// Hold duration after which a press becomes a long-press. 0 disables it.
+    pub long_press_ms: u32,
+    /// Window for a second press to count as a double-tap. 0 disables double-tap,
+    /// which also makes single taps fire immediately on release.
+    pub double_tap_ms: u32,
+    /// Hold duration before auto-repeat starts. 0 disables repeat.
+    pub repeat_delay_ms: u32,
+    pub repeat_interval_ms: u32,
+}
+
+impl Default for GestureTiming {
+    fn default() -> Self {
+        Self {
+            long_press_ms: 500,
+            double_tap_ms: 250,
+            repeat_delay_ms: 0,
+            repeat_interval_ms: 100,
+        }
+    }
+}
+
+/// Gesture settings pushed from the frontend, with optional per-button overrides
+/// keyed by W3C button index.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GestureConfig {
+    pub default: GestureTiming,
+    pub buttons: HashMap<u8, GestureTiming>,
+}
+
+impl GestureConfig {
+    fn timing(&self, button: u8) -> GestureTiming {
+        self.buttons.get(&button).copied().unwrap_or(self.default)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GestureKind {
+    Tap,
+    DoubleTap,
+    LongPress,
+    Repeat,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GestureEvent {
+    pub button: u8,
+    pub gesture: GestureKind,
+    /// SDL timestamp (ms since init) at which the gesture was recognised.
+    pub timestamp: u32,
+    /// How long the button had been held, for long-press and repeat.
+    pub held_ms: u32,
+    /// 1-based repeat counter; 0 for other gestures.
+    pub repeat: u32,
+}
+
+#[derive(Default)]
+struct ButtonState {
+    down_at: Option<u32>,
+    /// Set once the current press produced a long-press, repeat or double-tap,
+    /// so its release doesn't also count as a tap.
+    consumed: bool,
+    long_fired: bool,
+    next_repeat: Option<u32>,
+    repeat_count: u32,
+    /// Release time of a tap that is waiting to see if a second press follows.
+    pending_tap: Option<u32>,
+}
+
+/// Classifies raw press/release edges into taps, double-taps, long-presses and
+/// auto-repeats, per controller. All times are SDL millisecond ticks, compared
+/// with wrapping arithmetic so the ~49 day rollover is harmless.
+#[derive(Default)]
+pub struct GestureDetector {
+    config: GestureConfig,
+    buttons: HashMap<(u32, u8), ButtonState>,
+}
+
+impl GestureDetector {
+    pub fn set_config(&mut self, config: GestureConfig) {
+        self.config = config;
+    }
+
+    /// Starts a press. Returns the double-tap it completes, or the earlier
+    /// tap whose window ran out before `tick` got to it.
+    pub fn press(&mut self, controller: u32, button: u8, timestamp: u32) -> Option<GestureEvent> {
+        let timing = self.config.timing(button);
+        let state = self.buttons.entry((controller, button)).or_default();
+
+        let mut expired = None;
+        if let Some(released_at) = state.pending_tap.take() {
+            if timestamp.wrapping_sub(released_at) > timing.double_tap_ms {
+                expired = Some(gesture(button, GestureKind::Tap, released_at, 0, 0));
+            } else {
+                // The second press only completes the double-tap; it can't
+                // also turn into a long-press or start repeating.
+                *state = ButtonState {
+                    down_at: Some(timestamp),
+                    consumed: true,
+                    long_fired: true,
+                    ..Default::default()
+                };
+                return Some(gesture(button, GestureKind::DoubleTap, timestamp, 0, 0));
+            }
+        }
+
+        *state = ButtonState {
+            down_at: Some(timestamp),
+            next_repeat: (timing.repeat_delay_ms > 0)
+                .then(|| timestamp.wrapping_add(timing.repeat_delay_ms)),
+            ..Default::default()
+        };
+        expired
+    }
+
+    pub fn release(&mut self, controller: u32, button: u8, timestamp: u32) -> Option<GestureEvent> {
+        let timing = self.config.timing(button);
+        let state = self.buttons.get_mut(&(controller, button))?;
+        state.down_at.take()?;
+        state.next_repeat = None;
+
+        if state.consumed {
+            return None;
+        }
+        if timing.double_tap_ms > 0 {
+            state.pending_tap = Some(timestamp);
+            return None;
+        }
+        Some(gesture(button, GestureKind::Tap, timestamp, 0, 0))
+    }
+
+    /// Advances timers to `now`, firing long-presses, repeats and taps whose
+    /// double-tap window has expired (stamped with their release). Each event
+    /// comes with its controller.
+    pub fn tick(&mut self, now: u32) -> Vec<(u32, GestureEvent)> {
+        let mut events = Vec::new();
+
+        for (&(controller, button), state) in self.buttons.iter_mut() {
+            let timing = self.config.timing(button);
+
+            if let Some(released_at) = state.pending_tap {
+                if now.wrapping_sub(released_at) > timing.double_tap_ms {
+                    state.pending_tap = None;
+                    events.push((controller, gesture(button, GestureKind::Tap, released_at, 0, 0)));
+                }
+            }
+
+            let Some(down_at) = state.down_at else { continue };
+            let held = now.wrapping_sub(down_at);
+
+            if !state.long_fired && timing.long_press_ms > 0 && held >= timing.long_press_ms {
+                state.long_fired = true;
+                state.consumed = true;
+                events.push((controller, gesture(button, GestureKind::LongPress, now, held, 0)));
+            }
+
+            if let Some(due) = state.next_repeat {
+                if now.wrapping_sub(due) as i32 >= 0 {
+                    state.repeat_count += 1;
+                    state.consumed = true;
+                    state.next_repeat = Some(due.wrapping_add(timing.repeat_interval_ms.max(1)));
+                    let repeat = gesture(button, GestureKind::Repeat, now, held, state.repeat_count);
+                    events.push((controller, repeat));
+                }
+            }
+        }
+
+        events
+    }
+
+    /// Milliseconds until `tick` could next produce a gesture, if anything is
+    /// pending at all.
+    pub fn next_due(&self, now: u32) -> Option<u32> {
+        let mut next: Option<u32> = None;
+        let mut due = |ms: u32| next = Some(next.map_or(ms, |n| n.min(ms)));
+
+        for (&(_, button), state) in &self.buttons {
+            let timing = self.config.timing(button);
+            if let Some(released_at) = state.pending_tap {
+                due(timing.double_tap_ms.saturating_add(1).saturating_sub(now.wrapping_sub(released_at)));
+            }
+            let Some(down_at) = state.down_at else { continue };
+            if !state.long_fired && timing.long_press_ms > 0 {
+                due(timing.long_press_ms.saturating_sub(now.wrapping_sub(down_at)));
+            }
+            if let Some(at) = state.next_repeat {
+                due((at.wrapping_sub(now) as i32).max(0) as u32);
+            }
+        }
+        next
+    }
+
+    /// Drops in-flight state for a controller that disconnected mid-press.
+    pub fn remove_controller(&mut self, controller: u32) {
+        self.buttons.retain(|&(c, _), _| c != controller);
+    }
+}
+
+fn gesture(button: u8, kind: GestureKind, timestamp: u32, held_ms: u32, repeat: u32) -> GestureEvent {
+    GestureEvent {
+        button,
+        gesture: kind,
+        timestamp,
+        held_ms,
+        repeat,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector() -> GestureDetector {
+        let mut detector = GestureDetector::default();
+        detector.set_config(GestureConfig::default());
+        detector
+    }
+
+    fn kinds(events: &[(u32, GestureEvent)]) -> Vec<(GestureKind, u32)> {
+        events.iter().map(|(_, e)| (e.gesture, e.timestamp)).collect()
+    }
+
+    #[test]
+    fn taps_fire_once_the_double_tap_window_passes() {
+        let mut gestures = detector();
+        assert!(gestures.press(0, 1, 1000).is_none());
+        assert!(gestures.release(0, 1, 1100).is_none());
+        assert!(gestures.tick(1300).is_empty());
+        assert_eq!(gestures.next_due(1300), Some(51));
+        // Stamped with the release, not when the window ran out
+        assert_eq!(kinds(&gestures.tick(1400)), [(GestureKind::Tap, 1100)]);
+        assert!(gestures.tick(2000).is_empty());
+    }
+
+    #[test]
+    fn taps_fire_on_release_without_double_tap() {
+        let mut gestures = detector();
+        gestures.set_config(GestureConfig {
+            default: GestureTiming { double_tap_ms: 0, ..Default::default() },
+            ..Default::default()
+        });
+        gestures.press(0, 1, 1000);
+        let tap = gestures.release(0, 1, 1100).unwrap();
+        assert_eq!((tap.gesture, tap.timestamp), (GestureKind::Tap, 1100));
+    }
+
+    #[test]
+    fn second_press_in_the_window_is_a_double_tap() {
+        let mut gestures = detector();
+        gestures.press(0, 1, 1000);
+        gestures.release(0, 1, 1050);
+        let double = gestures.press(0, 1, 1200).unwrap();
+        assert_eq!((double.gesture, double.timestamp), (GestureKind::DoubleTap, 1200));
+        // Holding the second press doesn't make it a long-press, nor its release a tap
+        assert!(gestures.tick(2000).is_empty());
+        assert!(gestures.release(0, 1, 2100).is_none());
+        assert!(gestures.tick(3000).is_empty());
+    }
+
+    #[test]
+    fn expired_tap_is_not_lost_to_the_next_press() {
+        let mut gestures = detector();
+        gestures.press(0, 1, 1000);
+        gestures.release(0, 1, 1050);
+        // No tick ran between the window closing and the next press
+        let tap = gestures.press(0, 1, 1400).unwrap();
+        assert_eq!((tap.gesture, tap.timestamp), (GestureKind::Tap, 1050));
+        gestures.release(0, 1, 1450);
+        assert_eq!(kinds(&gestures.tick(1800)), [(GestureKind::Tap, 1450)]);
+    }
+
+    #[test]
+    fn holding_fires_a_long_press_instead_of_a_tap() {
+        let mut gestures = detector();
+        gestures.press(0, 1, 1000);
+        assert_eq!(gestures.next_due(1200), Some(300));
+        assert!(gestures.tick(1499).is_empty());
+        let events = gestures.tick(1500);
+        assert_eq!(kinds(&events), [(GestureKind::LongPress, 1500)]);
+        assert_eq!(events[0].1.held_ms, 500);
+        assert!(gestures.release(0, 1, 1600).is_none());
+        assert!(gestures.tick(2000).is_empty());
+    }
+
+    #[test]
+    fn holding_repeats_after_the_delay_at_the_interval() {
+        let mut gestures = detector();
+        let timing =
+            GestureTiming { long_press_ms: 0, repeat_delay_ms: 400, repeat_interval_ms: 100, ..Default::default() };
+        gestures.set_config(GestureConfig { default: timing, ..Default::default() });
+        gestures.press(0, 1, 1000);
+        assert_eq!(gestures.next_due(1000), Some(400));
+        assert!(gestures.tick(1399).is_empty());
+
+        let first = gestures.tick(1400);
+        assert_eq!(kinds(&first), [(GestureKind::Repeat, 1400)]);
+        assert_eq!((first[0].1.repeat, first[0].1.held_ms), (1, 400));
+        assert_eq!(gestures.next_due(1400), Some(100));
+        assert!(gestures.tick(1499).is_empty());
+        let second = gestures.tick(1500);
+        assert_eq!((second[0].1.repeat, second[0].1.held_ms), (2, 500));
+
+        // Repeating consumed the press, so its release isn't also a tap
+        assert!(gestures.release(0, 1, 1550).is_none());
+        assert!(gestures.tick(1700).is_empty());
+        assert_eq!(gestures.next_due(1700), None);
+    }
+
+    #[test]
+    fn huge_double_tap_window_does_not_overflow() {
+        let mut gestures = detector();
+        gestures.set_config(GestureConfig {
+            default: GestureTiming { double_tap_ms: u32::MAX, ..Default::default() },
+            ..Default::default()
+        });
+        gestures.press(0, 1, 1000);
+        gestures.release(0, 1, 1050);
+        assert_eq!(gestures.next_due(1100), Some(u32::MAX - 50));
+    }
+
+    #[test]
+    fn controllers_are_tracked_apart() {
+        let mut gestures = detector();
+        gestures.press(0, 1, 1000);
+        gestures.release(0, 1, 1050);
+        assert!(gestures.press(1, 1, 1100).is_none());
+        gestures.remove_controller(1);
+        assert_eq!(gestures.tick(1400).iter().map(|(c, _)| *c).collect::<Vec<_>>(), [0]);
+    }
+}
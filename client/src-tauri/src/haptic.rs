@@ -1,12 +1,470 @@
-use std::sync::mpsc;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Clone)]
-pub struct HapticRequest {
-    pub strength: f32,
+use crate::controller::ControllerInfo;
+use crate::gamepad::GamepadSender;
+
+/// How often a ramp's strength is updated while it plays.
+const RAMP_FRAME_MS: u32 = 16;
+
+/// Patterns a controller holds back under `HapticPolicy::Queue`. Further
+/// requests are dropped until the queue drains.
+const MAX_QUEUE_DEPTH: usize = 4;
+
+/// One step of a haptic pattern.
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HapticStep {
+    /// Low-frequency (large) motor strength, 0..1.
+    pub low: f32,
+    /// High-frequency (small) motor strength, 0..1.
+    pub high: f32,
+    /// Trigger motors, on pads that have them (Xbox One and later).
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    /// Trackpad actuators on the Steam Deck. Steps that leave both at zero
+    /// play low-frequency on the left pad and high-frequency on the right.
+    pub left_pad: f32,
+    pub right_pad: f32,
     pub duration_ms: u32,
+    /// Silence after the step.
+    pub pause_ms: u32,
+    /// Slide from the previous step's strengths to this one's over
+    /// `duration_ms` instead of jumping straight to them.
+    pub ramp: bool,
+}
+
+impl HapticStep {
+    const fn new(low: f32, high: f32, duration_ms: u32, pause_ms: u32) -> Self {
+        Self {
+            low,
+            high,
+            left_trigger: 0.0,
+            right_trigger: 0.0,
+            left_pad: 0.0,
+            right_pad: 0.0,
+            duration_ms,
+            pause_ms,
+            ramp: false,
+        }
+    }
+
+    /// Both motors at the same strength, as `trigger_haptic` asks for.
+    pub fn pulse(strength: f32, duration_ms: u32) -> Self {
+        Self::new(strength, strength, duration_ms, 0)
+    }
+
+    pub fn motors(motors: RumbleMotors, duration_ms: u32) -> Self {
+        Self {
+            left_trigger: motors.left_trigger,
+            right_trigger: motors.right_trigger,
+            left_pad: motors.left_pad,
+            right_pad: motors.right_pad,
+            ..Self::new(motors.low, motors.high, duration_ms, 0)
+        }
+    }
+
+    fn level(&self) -> Level {
+        let channels = [self.low, self.high, self.left_trigger, self.right_trigger, self.left_pad, self.right_pad];
+        channels.map(|s| s.clamp(0.0, 1.0))
+    }
+}
+
+/// Strengths for `set_rumble`, 0..1 per motor.
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RumbleMotors {
+    pub low: f32,
+    pub high: f32,
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    pub left_pad: f32,
+    pub right_pad: f32,
+}
+
+/// A named preset, or an explicit list of steps.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(untagged)]
+pub enum HapticPattern {
+    Preset(String),
+    Steps(Vec<HapticStep>),
+}
+
+impl HapticPattern {
+    pub fn steps(self) -> Result<Vec<HapticStep>, HapticError> {
+        match self {
+            HapticPattern::Preset(name) => preset(&name).ok_or(HapticError::UnknownPreset(name)),
+            HapticPattern::Steps(steps) => Ok(steps),
+        }
+    }
+}
+
+/// Built-in presets: `tick`, `confirm`, `error`, `heartbeat` and `charge`.
+fn preset(name: &str) -> Option<Vec<HapticStep>> {
+    let steps = match name {
+        "tick" => vec![HapticStep::new(0.0, 0.4, 15, 0)],
+        "confirm" => vec![HapticStep::new(0.2, 0.5, 40, 40), HapticStep::new(0.3, 0.8, 60, 0)],
+        "error" => vec![
+            HapticStep::new(0.8, 0.2, 80, 50),
+            HapticStep::new(0.8, 0.2, 80, 50),
+            HapticStep::new(0.8, 0.2, 80, 0),
+        ],
+        "heartbeat" => vec![HapticStep::new(0.7, 0.1, 60, 90), HapticStep::new(0.4, 0.0, 80, 0)],
+        "charge" => vec![HapticStep {
+            ramp: true,
+            ..HapticStep::new(0.6, 1.0, 400, 0)
+        }],
+        _ => return None,
+    };
+    Some(steps)
+}
+
+/// How the Steam Deck plays haptics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeckHapticMode {
+    /// Its trackpad actuators, where the HID device can be opened.
+    #[default]
+    Trackpads,
+    /// SDL's rumble, like any other controller.
+    Rumble,
+}
+
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HapticConfig {
+    pub steam_deck: DeckHapticMode,
+}
+
+/// What to do with a pattern that arrives while another is playing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HapticPolicy {
+    /// Replace the playing pattern and anything queued behind it.
+    #[default]
+    LatestWins,
+    /// Ignore the new pattern; suits rapid feedback such as slider ticks.
+    DropIfBusy,
+    /// Play it once the current pattern and earlier queued ones finish.
+    Queue,
+}
+
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HapticOptions {
+    /// Instance id of the controller to play on; every controller if unset.
+    pub controller: Option<u32>,
+    pub policy: HapticPolicy,
+}
+
+#[derive(Debug, Clone)]
+pub enum HapticRequest {
+    Play { steps: Vec<HapticStep>, options: HapticOptions },
+    /// Cancels the playing and queued patterns and silences the motors.
+    Stop { controller: Option<u32> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HapticError {
+    UnknownPreset(String),
+    /// No connected controller can rumble.
+    NoDevice,
+    UnknownController(u32),
+    /// The targeted controller has no rumble motors or haptic device.
+    NoRumble(u32),
+    /// The gamepad thread has exited.
+    Stopped,
+}
+
+impl std::fmt::Display for HapticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HapticError::UnknownPreset(name) => write!(f, "Unknown haptic preset: {name}"),
+            HapticError::NoDevice => write!(f, "No connected controller supports haptics"),
+            HapticError::UnknownController(id) => write!(f, "Controller {id} is not connected"),
+            HapticError::NoRumble(id) => write!(f, "Controller {id} does not support haptics"),
+            HapticError::Stopped => write!(f, "Gamepad thread is not running"),
+        }
+    }
 }
 
 /// State managed by Tauri to bridge frontend commands to the gamepad thread.
 pub struct HapticState {
-    pub sender: mpsc::Sender<HapticRequest>,
+    pub sender: GamepadSender<HapticRequest>,
+    /// Connected controllers, as published by the gamepad thread.
+    pub controllers: Arc<Mutex<Vec<ControllerInfo>>>,
+}
+
+impl HapticState {
+    /// Hands a pattern to the gamepad thread, failing up front if no
+    /// connected controller it targets can play it.
+    pub fn play(&self, steps: Vec<HapticStep>, options: HapticOptions) -> Result<(), HapticError> {
+        let controllers = self.controllers.lock().map(|list| list.clone()).unwrap_or_default();
+        match options.controller {
+            Some(id) => match controllers.iter().find(|c| c.id == id) {
+                None => return Err(HapticError::UnknownController(id)),
+                Some(c) if !c.rumble => return Err(HapticError::NoRumble(id)),
+                Some(_) => {}
+            },
+            None if !controllers.iter().any(|c| c.rumble) => return Err(HapticError::NoDevice),
+            None => {}
+        }
+        self.sender
+            .send(HapticRequest::Play { steps, options })
+            .map_err(|_| HapticError::Stopped)
+    }
+
+    pub fn stop(&self, controller: Option<u32>) -> Result<(), HapticError> {
+        self.sender
+            .send(HapticRequest::Stop { controller })
+            .map_err(|_| HapticError::Stopped)
+    }
+}
+
+/// Strengths of the low, high, left trigger, right trigger, left pad and
+/// right pad channels.
+type Level = [f32; 6];
+
+const SILENT: Level = [0.0; 6];
+
+/// Motor strengths to apply, held for `duration_ms`. All zero means stop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rumble {
+    pub low: f32,
+    pub high: f32,
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    pub left_pad: f32,
+    pub right_pad: f32,
+    pub duration_ms: u32,
+}
+
+impl Rumble {
+    pub const OFF: Rumble = Rumble::new(SILENT, 0);
+
+    const fn new([low, high, left_trigger, right_trigger, left_pad, right_pad]: Level, duration_ms: u32) -> Self {
+        Self {
+            low,
+            high,
+            left_trigger,
+            right_trigger,
+            left_pad,
+            right_pad,
+            duration_ms,
+        }
+    }
+
+    /// Low- and high-frequency motor strengths. Patterns written only for
+    /// the Deck's trackpads play their left and right pads here instead.
+    pub fn motors(&self) -> (f32, f32) {
+        if self.low > 0.0 || self.high > 0.0 {
+            (self.low, self.high)
+        } else {
+            (self.left_pad, self.right_pad)
+        }
+    }
+
+    /// Left and right trackpad strengths, the mirror of `motors`.
+    pub fn pads(&self) -> (f32, f32) {
+        if self.left_pad > 0.0 || self.right_pad > 0.0 {
+            (self.left_pad, self.right_pad)
+        } else {
+            (self.low, self.high)
+        }
+    }
+
+    /// Single strength for devices with one rumble effect instead of separate
+    /// motors. Trigger motors have no equivalent there and are left out.
+    pub fn strength(&self) -> f32 {
+        let (low, high) = self.motors();
+        low.max(high)
+    }
+}
+
+/// Where playback is inside a pattern. Steps and the pauses after them are
+/// numbered as separate segments, `2 * step` and `2 * step + 1`.
+struct Frame {
+    segment: usize,
+    level: Level,
+    remaining: u32,
+    ramping: bool,
+}
+
+fn frame(steps: &[HapticStep], elapsed: u32) -> Option<Frame> {
+    let mut t = elapsed;
+    let mut previous = SILENT;
+    for (i, step) in steps.iter().enumerate() {
+        let target = step.level();
+        if t < step.duration_ms {
+            let level = if step.ramp {
+                let f = t as f32 / step.duration_ms as f32;
+                std::array::from_fn(|i| previous[i] + (target[i] - previous[i]) * f)
+            } else {
+                target
+            };
+            let remaining = step.duration_ms - t;
+            return Some(Frame { segment: 2 * i, level, remaining, ramping: step.ramp });
+        }
+        t -= step.duration_ms;
+        if t < step.pause_ms {
+            let remaining = step.pause_ms - t;
+            return Some(Frame { segment: 2 * i + 1, level: SILENT, remaining, ramping: false });
+        }
+        t -= step.pause_ms;
+        previous = target;
+    }
+    None
+}
+
+/// Plays one controller's patterns on the gamepad thread, so they keep their
+/// timing without the frontend scheduling timers.
+#[derive(Default)]
+pub struct HapticPlayer {
+    steps: Vec<HapticStep>,
+    started: u32,
+    playing: bool,
+    /// Segment and level last sent to the motors.
+    sent: Option<(usize, Level)>,
+    queue: VecDeque<Vec<HapticStep>>,
+}
+
+impl HapticPlayer {
+    /// Starts or queues `steps` according to `policy`.
+    pub fn play(&mut self, steps: Vec<HapticStep>, policy: HapticPolicy, now: u32) {
+        match policy {
+            _ if !self.playing => self.start(steps, now),
+            HapticPolicy::LatestWins => {
+                self.queue.clear();
+                self.start(steps, now);
+            }
+            HapticPolicy::DropIfBusy => {}
+            HapticPolicy::Queue => {
+                if self.queue.len() < MAX_QUEUE_DEPTH {
+                    self.queue.push_back(steps);
+                }
+            }
+        }
+    }
+
+    fn start(&mut self, steps: Vec<HapticStep>, now: u32) {
+        self.steps = steps;
+        self.started = now;
+        self.playing = true;
+        self.sent = None;
+    }
+
+    /// Cancels playback and drops anything queued. Returns whether anything
+    /// was playing.
+    pub fn stop(&mut self) -> bool {
+        let was_playing = self.playing;
+        self.steps.clear();
+        self.queue.clear();
+        self.playing = false;
+        self.sent = None;
+        was_playing
+    }
+
+    /// The motor change due at `now`, if any: on every segment boundary, on
+    /// each ramp frame, and once more to stop the motors when the last queued
+    /// pattern ends.
+    pub fn update(&mut self, now: u32) -> Option<Rumble> {
+        if !self.playing {
+            return None;
+        }
+        let Some(f) = frame(&self.steps, now.wrapping_sub(self.started)) else {
+            if let Some(next) = self.queue.pop_front() {
+                self.start(next, now);
+                return self.update(now);
+            }
+            self.stop();
+            return Some(Rumble::OFF);
+        };
+        let unchanged = self
+            .sent
+            .is_some_and(|(segment, level)| segment == f.segment && (!f.ramping || level == f.level));
+        if unchanged {
+            return None;
+        }
+        self.sent = Some((f.segment, f.level));
+        // Ramp frames are held a little past the next frame so a late update
+        // doesn't leave a gap.
+        let duration_ms = if f.ramping { f.remaining.min(RAMP_FRAME_MS * 2) } else { f.remaining };
+        Some(Rumble::new(f.level, duration_ms))
+    }
+
+    /// Milliseconds until `update` has something new, if a pattern is playing.
+    pub fn next_due(&self, now: u32) -> Option<u32> {
+        if !self.playing {
+            return None;
+        }
+        match frame(&self.steps, now.wrapping_sub(self.started)) {
+            Some(f) if f.ramping => Some(f.remaining.min(RAMP_FRAME_MS)),
+            Some(f) => Some(f.remaining),
+            None => Some(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::backend::Waker;
+    use crate::controller::ControllerKind;
+
+    fn state(rumble: &[bool]) -> (HapticState, mpsc::Receiver<HapticRequest>) {
+        let (tx, rx) = mpsc::channel();
+        let controllers = rumble
+            .iter()
+            .enumerate()
+            .map(|(id, &rumble)| ControllerInfo {
+                id: id as u32,
+                guid: format!("pad-{id}"),
+                name: format!("Pad {id}"),
+                kind: ControllerKind::Generic,
+                vendor_id: None,
+                product_id: None,
+                rumble,
+                led: false,
+                motion: false,
+                battery: Default::default(),
+            })
+            .collect();
+        let state = HapticState {
+            sender: GamepadSender::new(tx, Waker::default()),
+            controllers: Arc::new(Mutex::new(controllers)),
+        };
+        (state, rx)
+    }
+
+    fn on(controller: Option<u32>) -> HapticOptions {
+        HapticOptions { controller, ..Default::default() }
+    }
+
+    #[test]
+    fn requests_nobody_can_play_are_rejected() {
+        let tick = || preset("tick").unwrap();
+
+        let (none, _rx) = state(&[]);
+        assert_eq!(none.play(tick(), on(None)), Err(HapticError::NoDevice));
+
+        let (state, rx) = state(&[false, true]);
+        assert_eq!(state.play(tick(), on(Some(0))), Err(HapticError::NoRumble(0)));
+        assert_eq!(state.play(tick(), on(Some(7))), Err(HapticError::UnknownController(7)));
+        assert!(rx.try_recv().is_err());
+
+        assert_eq!(state.play(tick(), on(None)), Ok(()));
+        assert_eq!(state.play(tick(), on(Some(1))), Ok(()));
+        assert_eq!(rx.try_iter().count(), 2);
+
+        drop(rx);
+        assert_eq!(state.play(tick(), on(None)), Err(HapticError::Stopped));
+    }
+
+    #[test]
+    fn unknown_presets_are_an_error() {
+        let pattern = HapticPattern::Preset("fanfare".into());
+        assert_eq!(pattern.steps().unwrap_err(), HapticError::UnknownPreset("fanfare".into()));
+    }
 }
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+/// How often an animated LED is refreshed.
+const LED_FRAME_MS: u32 = 33;
+/// Lowest brightness of a pulse, so the LED never looks switched off.
+const PULSE_FLOOR: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct LedColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl LedColor {
+    fn scaled(self, brightness: f32) -> Self {
+        let scale = |c: u8| (f32::from(c) * brightness.clamp(0.0, 1.0)).round() as u8;
+        Self {
+            r: scale(self.r),
+            g: scale(self.g),
+            b: scale(self.b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedEffect {
+    #[default]
+    Solid,
+    /// Fades smoothly between dim and full brightness.
+    Pulse,
+    /// Switches fully on and off.
+    Blink,
+}
+
+/// A named state the frontend wants shown, such as Discord being muted.
+/// States stay active until cleared by `id`.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedState {
+    pub id: String,
+    pub color: LedColor,
+    #[serde(default)]
+    pub effect: LedEffect,
+    /// Length of one pulse or blink cycle (default 1500 ms).
+    #[serde(default)]
+    pub period_ms: Option<u32>,
+    /// When several states are active the highest priority is shown; ties go
+    /// to the most recent.
+    #[serde(default)]
+    pub priority: i32,
+    /// Instance id of the controller to show it on; every controller if unset.
+    #[serde(default)]
+    pub controller: Option<u32>,
+}
+
+impl LedState {
+    fn period(&self) -> u32 {
+        self.period_ms.unwrap_or(1500).max(1)
+    }
+
+    fn applies_to(&self, controller: u32) -> bool {
+        self.controller.is_none_or(|id| id == controller)
+    }
+
+    fn color_at(&self, elapsed: u32) -> LedColor {
+        let phase = (elapsed % self.period()) as f32 / self.period() as f32;
+        match self.effect {
+            LedEffect::Solid => self.color,
+            LedEffect::Pulse => {
+                let wave = 0.5 - 0.5 * (phase * TAU).cos();
+                self.color.scaled(PULSE_FLOOR + (1.0 - PULSE_FLOOR) * wave)
+            }
+            LedEffect::Blink if phase < 0.5 => self.color,
+            LedEffect::Blink => LedColor::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LedError {
+    /// No connected controller has an LED.
+    NoDevice,
+    UnknownController(u32),
+    NoLed(u32),
+    /// The gamepad thread has exited.
+    Stopped,
+}
+
+impl std::fmt::Display for LedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LedError::NoDevice => write!(f, "No connected controller has an LED"),
+            LedError::UnknownController(id) => write!(f, "Controller {id} is not connected"),
+            LedError::NoLed(id) => write!(f, "Controller {id} has no LED"),
+            LedError::Stopped => write!(f, "Gamepad thread is not running"),
+        }
+    }
+}
+
+/// Decides what each controller's LED shows: the winning active state, or
+/// else the colour last set with `set_controller_led`. Controllers nobody
+/// asked about are left alone; once their last state clears with no colour
+/// set, they're switched off.
+#[derive(Default)]
+pub struct LedManager {
+    /// Manual colours; the `None` key applies to every controller.
+    base: HashMap<Option<u32>, LedColor>,
+    /// Active states with the time each was set, oldest first.
+    states: Vec<(LedState, u32)>,
+}
+
+impl LedManager {
+    pub fn set_color(&mut self, controller: Option<u32>, color: Option<LedColor>) {
+        if controller.is_none() {
+            // A colour for everyone overrides earlier per-controller ones.
+            self.base.clear();
+        }
+        match color {
+            Some(color) => self.base.insert(controller, color),
+            None => self.base.remove(&controller),
+        };
+    }
+
+    /// Activates `state`, replacing an active state with the same id.
+    pub fn set_state(&mut self, state: LedState, now: u32) {
+        self.clear_state(&state.id);
+        self.states.push((state, now));
+    }
+
+    pub fn clear_state(&mut self, id: &str) {
+        self.states.retain(|(state, _)| state.id != id);
+    }
+
+    fn winner(&self, controller: u32) -> Option<&(LedState, u32)> {
+        // max_by_key keeps the last of equal priorities, i.e. the most recent.
+        self.states
+            .iter()
+            .filter(|(state, _)| state.applies_to(controller))
+            .max_by_key(|(state, _)| state.priority)
+    }
+
+    /// Colour `controller`'s LED should show at `now`, or `None` to leave it.
+    pub fn color(&self, controller: u32, now: u32) -> Option<LedColor> {
+        match self.winner(controller) {
+            Some((state, since)) => Some(state.color_at(now.wrapping_sub(*since))),
+            None => self.base.get(&Some(controller)).or(self.base.get(&None)).copied(),
+        }
+    }
+
+    /// Milliseconds until an animated LED among `controllers` needs another
+    /// frame, if any is animated.
+    pub fn next_due(&self, controllers: impl Iterator<Item = u32>) -> Option<u32> {
+        controllers
+            .filter_map(|id| self.winner(id))
+            .any(|(state, _)| state.effect != LedEffect::Solid)
+            .then_some(LED_FRAME_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: LedColor = LedColor { r: 255, g: 0, b: 0 };
+    const AMBER: LedColor = LedColor { r: 245, g: 158, b: 11 };
+    const BLUE: LedColor = LedColor { r: 0, g: 0, b: 255 };
+
+    fn state(id: &str, color: LedColor, priority: i32) -> LedState {
+        LedState {
+            id: id.to_string(),
+            color,
+            effect: LedEffect::Solid,
+            period_ms: None,
+            priority,
+            controller: None,
+        }
+    }
+
+    #[test]
+    fn highest_priority_then_latest_state_wins() {
+        let mut leds = LedManager::default();
+        leds.set_color(None, Some(BLUE));
+        assert_eq!(leds.color(0, 0), Some(BLUE));
+
+        leds.set_state(state("muted", RED, 10), 0);
+        leds.set_state(state("waiting", AMBER, 5), 10);
+        assert_eq!(leds.color(0, 10), Some(RED));
+
+        leds.set_state(state("other", BLUE, 10), 20);
+        assert_eq!(leds.color(0, 20), Some(BLUE));
+
+        leds.clear_state("other");
+        leds.clear_state("muted");
+        assert_eq!(leds.color(0, 30), Some(AMBER));
+        leds.clear_state("waiting");
+        assert_eq!(leds.color(0, 30), Some(BLUE));
+    }
+
+    #[test]
+    fn targeted_colours_and_states() {
+        let mut leds = LedManager::default();
+        leds.set_color(Some(1), Some(RED));
+        assert_eq!(leds.color(0, 0), None);
+        assert_eq!(leds.color(1, 0), Some(RED));
+
+        leds.set_state(LedState { controller: Some(0), ..state("p1", AMBER, 0) }, 0);
+        assert_eq!(leds.color(0, 0), Some(AMBER));
+        assert_eq!(leds.color(1, 0), Some(RED));
+
+        // A colour for everyone replaces the per-controller one.
+        leds.set_color(None, Some(BLUE));
+        assert_eq!(leds.color(1, 0), Some(BLUE));
+    }
+
+    #[test]
+    fn pulses_stay_visible_and_only_they_animate() {
+        let mut leds = LedManager::default();
+        leds.set_state(state("solid", RED, 0), 0);
+        assert_eq!(leds.next_due([0].into_iter()), None);
+
+        let pulse = LedState { effect: LedEffect::Pulse, period_ms: Some(1000), ..state("pulse", RED, 1) };
+        leds.set_state(pulse, 100);
+        assert_eq!(leds.next_due([0].into_iter()), Some(LED_FRAME_MS));
+        assert_eq!(leds.next_due(std::iter::empty()), None);
+
+        let dimmest = leds.color(0, 100).unwrap();
+        let brightest = leds.color(0, 600).unwrap();
+        assert_eq!(dimmest.r, (255.0 * PULSE_FLOOR).round() as u8);
+        assert_eq!(brightest, RED);
+    }
+}
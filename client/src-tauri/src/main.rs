@@ -1,31 +1,425 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod axis;
+mod backend;
+mod battery;
+mod combo;
+mod connection;
+mod controller;
+mod deck_haptics;
+mod discovery;
 mod gamepad;
+mod gesture;
 mod haptic;
+mod led;
+mod mapping;
+mod motion;
+mod pairing;
+mod protocol;
+mod registry;
+mod sdl_backend;
+mod touchpad;
+mod transport;
 
-use std::sync::mpsc;
-use haptic::{HapticRequest, HapticState};
+use std::sync::{mpsc, Arc, Mutex};
+use tauri::{Emitter, Manager};
+use axis::{AxisConfig, TriggerConfig};
+use backend::{BackendStatus, Waker};
+use combo::ComboDefinition;
+use connection::{ConnectionConfig, ConnectionEvent, ConnectionStatus, ServerConnection};
+use controller::ControllerInfo;
+use discovery::DiscoveredServer;
+use gamepad::{GamepadCommand, GamepadSender, GamepadState};
+use gesture::GestureConfig;
+use haptic::{HapticConfig, HapticOptions, HapticPattern, HapticRequest, HapticState, HapticStep, RumbleMotors};
+use led::{LedColor, LedState};
+use motion::MotionConfig;
+use pairing::{PairedDevice, PairingInfo, PairingRequest, PairingState, ProxiedResponse};
+use protocol::{ClientMessage, ServerMessage};
+use touchpad::TouchpadConfig;
 
+/// Rumbles every motor at `strength`. Fails if no targeted controller can
+/// rumble.
 #[tauri::command]
-fn trigger_haptic(state: tauri::State<HapticState>, strength: f32, duration_ms: u32) {
-    let _ = state.sender.send(HapticRequest {
-        strength: strength.clamp(0.0, 1.0),
-        duration_ms,
-    });
+fn trigger_haptic(
+    state: tauri::State<HapticState>,
+    strength: f32,
+    duration_ms: u32,
+    options: Option<HapticOptions>,
+) -> Result<(), String> {
+    let step = HapticStep::pulse(strength.clamp(0.0, 1.0), duration_ms);
+    state.play(vec![step], options.unwrap_or_default()).map_err(|e| e.to_string())
+}
+
+/// Drives the low/high-frequency and trigger motors separately. Pads without
+/// them get a single rumble at the stronger of `low` and `high`.
+#[tauri::command]
+fn set_rumble(
+    state: tauri::State<HapticState>,
+    motors: RumbleMotors,
+    duration_ms: u32,
+    options: Option<HapticOptions>,
+) -> Result<(), String> {
+    let step = HapticStep::motors(motors, duration_ms);
+    state.play(vec![step], options.unwrap_or_default()).map_err(|e| e.to_string())
+}
+
+/// Plays a preset by name (`"confirm"`, `"error"`, ...) or a list of steps.
+/// By default it replaces any pattern already playing.
+#[tauri::command]
+fn play_haptic(
+    state: tauri::State<HapticState>,
+    pattern: HapticPattern,
+    options: Option<HapticOptions>,
+) -> Result<(), String> {
+    let steps = pattern.steps().map_err(|e| e.to_string())?;
+    state.play(steps, options.unwrap_or_default()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn stop_haptic(state: tauri::State<HapticState>, controller: Option<u32>) -> Result<(), String> {
+    state.stop(controller).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_controllers(state: tauri::State<GamepadState>) -> Vec<ControllerInfo> {
+    state.controllers.lock().map(|list| list.clone()).unwrap_or_default()
+}
+
+#[tauri::command]
+fn gamepad_backend_status(state: tauri::State<GamepadState>) -> BackendStatus {
+    state.backend_status.lock().map(|status| status.clone()).unwrap_or_default()
+}
+
+#[tauri::command]
+fn restart_gamepad_backend(state: tauri::State<GamepadState>) {
+    let _ = state.sender.send(GamepadCommand::RestartBackend);
+}
+
+#[tauri::command]
+fn set_gesture_config(state: tauri::State<GamepadState>, config: GestureConfig) {
+    let _ = state.sender.send(GamepadCommand::SetGestureConfig(config));
+}
+
+#[tauri::command]
+fn set_gamepad_combos(state: tauri::State<GamepadState>, combos: Vec<ComboDefinition>) {
+    let _ = state.sender.send(GamepadCommand::SetCombos(combos));
+}
+
+#[tauri::command]
+fn set_axis_config(state: tauri::State<GamepadState>, config: AxisConfig) {
+    let _ = state.sender.send(GamepadCommand::SetAxisConfig(config));
+}
+
+#[tauri::command]
+fn set_trigger_config(state: tauri::State<GamepadState>, config: TriggerConfig) {
+    let _ = state.sender.send(GamepadCommand::SetTriggerConfig(config));
+}
+
+#[tauri::command]
+fn set_touchpad_config(state: tauri::State<GamepadState>, config: TouchpadConfig) {
+    let _ = state.sender.send(GamepadCommand::SetTouchpadConfig(config));
+}
+
+#[tauri::command]
+fn set_motion_config(state: tauri::State<GamepadState>, config: MotionConfig) {
+    let _ = state.sender.send(GamepadCommand::SetMotionConfig(config));
+}
+
+/// Sets a controller's LED or lightbar, or every controller's when
+/// `controller` is omitted. `color: null` drops the manual colour.
+#[tauri::command]
+fn set_controller_led(
+    state: tauri::State<GamepadState>,
+    color: Option<LedColor>,
+    controller: Option<u32>,
+) -> Result<(), String> {
+    state.set_led(controller, color).map_err(|e| e.to_string())
+}
+
+/// Shows a named state (e.g. `discord_muted`) on the LEDs until cleared. It
+/// takes precedence over the manual colour.
+#[tauri::command]
+fn set_led_state(state: tauri::State<GamepadState>, led: LedState) {
+    let _ = state.sender.send(GamepadCommand::SetLedState(led));
+}
+
+#[tauri::command]
+fn clear_led_state(state: tauri::State<GamepadState>, id: String) {
+    let _ = state.sender.send(GamepadCommand::ClearLedState(id));
+}
+
+/// Adds an SDL controller mapping (a `gamecontrollerdb.txt` line) and saves
+/// it to the user's mapping file, which is loaded at every start.
+#[tauri::command]
+fn add_controller_mapping(state: tauri::State<GamepadState>, mapping: String) -> Result<(), String> {
+    state.add_mapping(mapping).map_err(|e| e.to_string())
+}
+
+/// Opens every joystick, including ones SDL has no mapping for, and reports
+/// their buttons, axes and hats as `gamepad_raw_input` mapping sources.
+#[tauri::command]
+fn start_raw_capture(state: tauri::State<GamepadState>) {
+    let _ = state.sender.send(GamepadCommand::SetRawCapture(true));
+}
+
+#[tauri::command]
+fn stop_raw_capture(state: tauri::State<GamepadState>) {
+    let _ = state.sender.send(GamepadCommand::SetRawCapture(false));
+}
+
+#[tauri::command]
+fn set_haptic_config(state: tauri::State<GamepadState>, config: HapticConfig) {
+    let _ = state.sender.send(GamepadCommand::SetHapticConfig(config));
+}
+
+/// Hold the controller still while this runs; `gamepad_motion_calibrated`
+/// fires when it's done, or `gamepad_motion_calibration_failed` if no
+/// samples came in. Fails at once if no controller has motion sensors.
+#[tauri::command]
+fn calibrate_motion(state: tauri::State<GamepadState>, duration_ms: Option<u32>) -> Result<(), String> {
+    state.calibrate_motion(duration_ms.unwrap_or(1000)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn zero_motion(state: tauri::State<GamepadState>) {
+    let _ = state.sender.send(GamepadCommand::ZeroMotion);
+}
+
+/// Points the native server connection at `url` (`http://host:port`). Until
+/// then gamepad presses only reach the server through the webview.
+#[tauri::command]
+fn connect_server(state: tauri::State<ServerConnection>, url: String) -> Result<(), String> {
+    state.connect(&url).map_err(|e| e.to_string())
+}
+
+/// Sends a message over the native connection; dropped while it's down.
+#[tauri::command]
+fn send_server_message(state: tauri::State<ServerConnection>, message: ClientMessage) -> Result<(), String> {
+    state.send(message).map_err(|e| e.to_string())
+}
+
+/// Looks for DeckPilot servers on the LAN for `timeout_ms` (1.5s by default).
+#[tauri::command(async)]
+fn discover_servers(timeout_ms: Option<u64>) -> Result<Vec<DiscoveredServer>, String> {
+    let timeout = timeout_ms.map_or(discovery::DEFAULT_TIMEOUT, std::time::Duration::from_millis);
+    discovery::discover(timeout)
+}
+
+/// Asks the server at `url` to show a pairing PIN.
+#[tauri::command(async)]
+fn request_pairing(url: String, name: Option<String>) -> Result<PairingRequest, String> {
+    let name = name.unwrap_or_else(pairing::device_name);
+    pairing::request_pairing(&url, &name).map_err(|e| e.to_string())
+}
+
+/// Completes pairing with the PIN the server showed (or its pairing link),
+/// saves the key and reconnects with it. For a TLS server, `fingerprint` is
+/// the certificate the user was shown while entering the PIN.
+#[tauri::command(async)]
+fn confirm_pairing(
+    app: tauri::AppHandle,
+    state: tauri::State<PairingState>,
+    connection: tauri::State<ServerConnection>,
+    url: String,
+    request_id: String,
+    code: String,
+    fingerprint: Option<String>,
+) -> Result<PairingInfo, String> {
+    let paired =
+        pairing::confirm_pairing(&url, &request_id, &code, fingerprint.as_deref()).map_err(|e| e.to_string())?;
+    state.set(&app, Some(paired.clone())).map_err(|e| e.to_string())?;
+    connection.set_pairing(Some(paired.clone()));
+    Ok(PairingInfo::from(&paired))
+}
+
+/// Pins the paired server's new certificate after the user accepted the
+/// change, and reconnects with it.
+#[tauri::command]
+fn trust_server_certificate(
+    app: tauri::AppHandle,
+    state: tauri::State<PairingState>,
+    connection: tauri::State<ServerConnection>,
+    fingerprint: String,
+) -> Result<PairingInfo, String> {
+    let mut paired = state.current().ok_or(pairing::PairingError::NotPaired).map_err(|e| e.to_string())?;
+    paired.fingerprint = Some(fingerprint);
+    state.set(&app, Some(paired.clone())).map_err(|e| e.to_string())?;
+    connection.set_pairing(Some(paired.clone()));
+    Ok(PairingInfo::from(&paired))
+}
+
+#[tauri::command]
+fn pairing_status(state: tauri::State<PairingState>) -> Option<PairingInfo> {
+    state.current().as_ref().map(PairingInfo::from)
+}
+
+/// A token for one of the webview's own requests to `url`, if paired with
+/// it. It's only good for `method` and `path`, and only once.
+#[tauri::command]
+fn auth_token(state: tauri::State<PairingState>, url: String, method: String, path: String) -> Option<String> {
+    state
+        .current()
+        .filter(|pairing| pairing.is_for(&url))
+        .map(|pairing| pairing.token(&method, &path, std::time::SystemTime::now()))
+}
+
+/// Makes one of the webview's REST requests to the paired server at `url`,
+/// which the webview can't reach itself when it serves a self-signed
+/// certificate. `body` is sent as is, with `content_type`.
+#[tauri::command(async)]
+fn server_request(
+    state: tauri::State<PairingState>,
+    url: String,
+    method: String,
+    path: String,
+    content_type: Option<String>,
+    body: Option<Vec<u8>>,
+) -> Result<ProxiedResponse, String> {
+    let paired = state
+        .current()
+        .filter(|pairing| pairing.is_for(&url))
+        .ok_or(pairing::PairingError::NotPaired)
+        .map_err(|e| e.to_string())?;
+    let body = body.as_deref().map(|body| (content_type.as_deref().unwrap_or("application/octet-stream"), body));
+    pairing::proxy(&paired, &method, &path, body).map_err(|e| e.to_string())
+}
+
+#[tauri::command(async)]
+fn list_paired_devices(state: tauri::State<PairingState>) -> Result<Vec<PairedDevice>, String> {
+    let paired = state.current().ok_or(pairing::PairingError::NotPaired).map_err(|e| e.to_string())?;
+    pairing::paired_devices(&paired).map_err(|e| e.to_string())
+}
+
+/// Revokes `device_id` on the server, or this device when unset. The server
+/// only lets a device revoke itself; others are unpaired on its own machine.
+/// Revoking this device also forgets its key, even if the server can't be
+/// reached.
+#[tauri::command(async)]
+fn revoke_pairing(
+    app: tauri::AppHandle,
+    state: tauri::State<PairingState>,
+    connection: tauri::State<ServerConnection>,
+    device_id: Option<String>,
+) -> Result<(), String> {
+    let paired = state.current().ok_or(pairing::PairingError::NotPaired).map_err(|e| e.to_string())?;
+    let target = device_id.unwrap_or_else(|| paired.device_id.clone());
+    let revoked = pairing::revoke(&paired, &target);
+    if target == paired.device_id {
+        state.set(&app, None).map_err(|e| e.to_string())?;
+        connection.set_pairing(None);
+    }
+    revoked.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_connection_config(state: tauri::State<ServerConnection>, config: ConnectionConfig) {
+    state.set_config(config);
+}
+
+#[tauri::command]
+fn server_connection_status(state: tauri::State<ServerConnection>) -> ConnectionStatus {
+    state.status()
+}
+
+/// Called by the webview when it comes back online or into view, so a
+/// connection broken by sleep or a network switch recovers right away.
+#[tauri::command]
+fn notify_network_change(state: tauri::State<ServerConnection>) {
+    state.network_changed();
 }
 
 fn main() {
     let (haptic_tx, haptic_rx) = mpsc::channel::<HapticRequest>();
+    let (gamepad_tx, gamepad_rx) = mpsc::channel::<GamepadCommand>();
+    let controllers = Arc::new(Mutex::new(Vec::new()));
+    let backend_status = Arc::new(Mutex::new(BackendStatus::default()));
+    let waker = Waker::default();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_process::init())
-        .manage(HapticState { sender: haptic_tx })
-        .invoke_handler(tauri::generate_handler![trigger_haptic])
+        .manage(HapticState {
+            sender: GamepadSender::new(haptic_tx, waker.clone()),
+            controllers: controllers.clone(),
+        })
+        .manage(GamepadState {
+            sender: GamepadSender::new(gamepad_tx, waker.clone()),
+            controllers: controllers.clone(),
+            backend_status: backend_status.clone(),
+        })
+        .invoke_handler(tauri::generate_handler![
+            trigger_haptic,
+            set_rumble,
+            play_haptic,
+            stop_haptic,
+            list_controllers,
+            gamepad_backend_status,
+            restart_gamepad_backend,
+            set_gesture_config,
+            set_gamepad_combos,
+            set_axis_config,
+            set_trigger_config,
+            set_touchpad_config,
+            set_motion_config,
+            set_haptic_config,
+            set_controller_led,
+            set_led_state,
+            clear_led_state,
+            add_controller_mapping,
+            start_raw_capture,
+            stop_raw_capture,
+            calibrate_motion,
+            zero_motion,
+            connect_server,
+            send_server_message,
+            set_connection_config,
+            server_connection_status,
+            notify_network_change,
+            discover_servers,
+            request_pairing,
+            confirm_pairing,
+            trust_server_certificate,
+            pairing_status,
+            auth_token,
+            server_request,
+            list_paired_devices,
+            revoke_pairing,
+        ])
         .setup(|app| {
-            gamepad::spawn_gamepad_thread(app.handle().clone(), haptic_rx);
+            let handle = app.handle().clone();
+            // The webview can't open a socket to a self-signed TLS server, so
+            // it talks to those through this connection instead.
+            let mut bridged = false;
+            let connection = ServerConnection::spawn(move |event| match event {
+                // Results of presses sent natively never reach the webview's
+                // socket, so give the feedback it would have given here (and
+                // only here when bridged).
+                ConnectionEvent::Message(ServerMessage::ActionResult { payload }) => {
+                    let preset = if payload.success { "confirm" } else { "error" };
+                    if let (Some(haptics), Ok(steps)) =
+                        (handle.try_state::<HapticState>(), HapticPattern::Preset(preset.into()).steps())
+                    {
+                        let _ = haptics.play(steps, HapticOptions::default());
+                    }
+                }
+                ConnectionEvent::Message(message) => {
+                    if bridged {
+                        let _ = handle.emit("server_message", message);
+                    }
+                }
+                ConnectionEvent::Status(status) => {
+                    bridged = status.url.as_deref().is_some_and(|url| url.starts_with("wss://"));
+                    let _ = handle.emit("connection_status", status);
+                }
+            });
+            let pairing = PairingState::load(app.handle());
+            connection.set_pairing(pairing.current());
+            app.manage(connection);
+            app.manage(pairing);
+            gamepad::spawn_gamepad_thread(app.handle().clone(), haptic_rx, gamepad_rx, controllers, backend_status, waker);
             Ok(())
         })
         .run(tauri::generate_context!())
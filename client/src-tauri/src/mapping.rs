@@ -0,0 +1,253 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// User mapping database in the app config dir, in SDL's
+/// `gamecontrollerdb.txt` format. Loaded when the backend starts; mappings
+/// added at runtime are saved to it.
+pub const MAPPING_DB_FILE: &str = "gamecontrollerdb.txt";
+
+/// How far an axis has to move from rest to count as captured.
+const AXIS_CAPTURE_THRESHOLD: i32 = 16_384;
+/// How close to rest it has to come back before it can be captured again.
+const AXIS_RELEASE_THRESHOLD: i32 = 8_192;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MappingError {
+    /// Not a `guid,name,binding:source,...` line.
+    Invalid(String),
+    /// The gamepad thread has exited.
+    Stopped,
+}
+
+impl std::fmt::Display for MappingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MappingError::Invalid(reason) => write!(f, "Invalid controller mapping: {reason}"),
+            MappingError::Stopped => write!(f, "Gamepad thread is not running"),
+        }
+    }
+}
+
+/// Checks that `mapping` is a single SDL mapping line and returns its GUID.
+/// SDL itself only reports "invalid mapping", so this catches the common
+/// mistakes with a useful message first.
+pub fn mapping_guid(mapping: &str) -> Result<&str, MappingError> {
+    let invalid = |reason: &str| Err(MappingError::Invalid(reason.to_string()));
+    if mapping.contains('\n') {
+        return invalid("expected a single line");
+    }
+    let mut fields = mapping.trim().split(',');
+    let guid = fields.next().unwrap_or_default();
+    if guid.len() != 32 || !guid.chars().all(|c| c.is_ascii_hexdigit()) {
+        return invalid("the GUID must be 32 hex digits");
+    }
+    if fields.next().is_none_or(|name| name.is_empty()) {
+        return invalid("missing controller name");
+    }
+    let bindings: Vec<&str> = fields.filter(|f| !f.is_empty()).collect();
+    if bindings.is_empty() {
+        return invalid("no bindings");
+    }
+    if let Some(binding) = bindings.iter().find(|b| !b.contains(':')) {
+        return invalid(&format!("`{binding}` is not a `target:source` binding"));
+    }
+    Ok(guid)
+}
+
+/// The user's mapping file.
+pub struct MappingDb {
+    path: PathBuf,
+}
+
+impl MappingDb {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds `mapping` to the file, replacing any line for the same GUID.
+    pub fn save(&self, mapping: &str) -> io::Result<()> {
+        let guid = mapping_guid(mapping).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+        let existing = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut lines: Vec<&str> = existing
+            .lines()
+            .filter(|line| !line.trim_start().starts_with(guid))
+            .collect();
+        lines.push(mapping.trim());
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, lines.join("\n") + "\n")
+    }
+}
+
+/// A joystick opened for raw capture, reported in `gamepad_raw_capture`.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawJoystick {
+    /// SDL instance id, as in `gamepad_raw_input`.
+    pub id: u32,
+    /// The GUID a mapping for it has to start with.
+    pub guid: String,
+    pub name: String,
+    pub buttons: u32,
+    pub axes: u32,
+    pub hats: u32,
+    /// Axis positions when capture started, taken as their rest positions.
+    #[serde(skip)]
+    pub rest: Vec<i16>,
+}
+
+/// Unmapped joystick input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawInput {
+    Button { index: u8, pressed: bool },
+    Axis { index: u8, value: i16 },
+    /// `state` is SDL's hat bitmask: 1 up, 2 right, 4 down, 8 left.
+    Hat { index: u8, state: u8 },
+}
+
+/// Payload of `gamepad_raw_input`: one deliberate input, as the source it
+/// would be bound to in a mapping string.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawSource {
+    pub joystick: u32,
+    pub guid: String,
+    /// `b3`, `h0.4`, `a2` for a trigger-like axis, `+a1`/`-a1` for one half
+    /// of a centred axis.
+    pub source: String,
+}
+
+/// Turns raw joystick input into mapping sources while building a mapping.
+/// Releases, small axis wobble and repeats are filtered out, so each
+/// press or push reports once.
+pub struct RawCapture {
+    joysticks: Vec<RawJoystick>,
+    /// Axes currently pushed past the threshold.
+    pushed: HashSet<(u32, u8)>,
+    hats: HashMap<(u32, u8), u8>,
+}
+
+impl RawCapture {
+    pub fn new(joysticks: Vec<RawJoystick>) -> Self {
+        Self {
+            joysticks,
+            pushed: HashSet::new(),
+            hats: HashMap::new(),
+        }
+    }
+
+    pub fn input(&mut self, joystick: u32, input: RawInput) -> Option<RawSource> {
+        let info = self.joysticks.iter().find(|j| j.id == joystick)?;
+        let source = match input {
+            RawInput::Button { index, pressed: true } => format!("b{index}"),
+            RawInput::Button { pressed: false, .. } => return None,
+            RawInput::Axis { index, value } => {
+                let rest = info.rest.get(usize::from(index)).copied().unwrap_or(0);
+                let delta = i32::from(value) - i32::from(rest);
+                if delta.abs() < AXIS_RELEASE_THRESHOLD {
+                    self.pushed.remove(&(joystick, index));
+                }
+                if delta.abs() < AXIS_CAPTURE_THRESHOLD || !self.pushed.insert((joystick, index)) {
+                    return None;
+                }
+                // Triggers rest at one end and use the whole range.
+                if i32::from(rest) < -AXIS_CAPTURE_THRESHOLD {
+                    format!("a{index}")
+                } else if delta > 0 {
+                    format!("+a{index}")
+                } else {
+                    format!("-a{index}")
+                }
+            }
+            RawInput::Hat { index, state } => {
+                let previous = self.hats.insert((joystick, index), state);
+                if state == 0 || previous == Some(state) {
+                    return None;
+                }
+                format!("h{index}.{state}")
+            }
+        };
+        Some(RawSource {
+            joystick,
+            guid: info.guid.clone(),
+            source,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUID: &str = "030000005e0400008e02000010010000";
+
+    #[test]
+    fn mappings_are_validated() {
+        let mapping = format!("{GUID},Pedals,a:b0,b:b1,leftx:a0,platform:Linux,");
+        assert_eq!(mapping_guid(&mapping), Ok(GUID));
+        assert!(matches!(mapping_guid("xyz,Pedals,a:b0"), Err(MappingError::Invalid(_))));
+        assert!(matches!(mapping_guid(&format!("{GUID},Pedals,")), Err(MappingError::Invalid(_))));
+        assert!(matches!(mapping_guid(&format!("{GUID},Pedals,a:b0,b1")), Err(MappingError::Invalid(_))));
+        assert!(matches!(mapping_guid(&format!("{GUID},,a:b0")), Err(MappingError::Invalid(_))));
+    }
+
+    #[test]
+    fn saving_replaces_the_line_for_the_same_guid() {
+        let dir = std::env::temp_dir().join(format!("deckpilot-mapping-{}", std::process::id()));
+        let db = MappingDb::new(dir.join(MAPPING_DB_FILE));
+        let other = "03000000de2800000512000011010000,Steam Deck,a:b0,";
+        db.save(other).unwrap();
+        db.save(&format!("{GUID},Pedals,a:b0,")).unwrap();
+        db.save(&format!("{GUID},Pedals,a:b1,")).unwrap();
+
+        let contents = std::fs::read_to_string(db.path()).unwrap();
+        assert_eq!(contents, format!("{other}\n{GUID},Pedals,a:b1,\n"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn capture() -> RawCapture {
+        RawCapture::new(vec![RawJoystick {
+            id: 7,
+            guid: GUID.to_string(),
+            name: "Pedals".to_string(),
+            buttons: 4,
+            axes: 2,
+            hats: 1,
+            rest: vec![0, -32768],
+        }])
+    }
+
+    #[test]
+    fn inputs_become_mapping_sources_once() {
+        let mut capture = capture();
+        let source = |s: Option<RawSource>| s.map(|s| s.source);
+
+        assert_eq!(source(capture.input(7, RawInput::Button { index: 3, pressed: true })), Some("b3".into()));
+        assert_eq!(capture.input(7, RawInput::Button { index: 3, pressed: false }), None);
+        assert_eq!(capture.input(8, RawInput::Button { index: 0, pressed: true }), None);
+
+        // A centred axis reports its direction once per push.
+        assert_eq!(capture.input(7, RawInput::Axis { index: 0, value: 4000 }), None);
+        assert_eq!(source(capture.input(7, RawInput::Axis { index: 0, value: -30000 })), Some("-a0".into()));
+        assert_eq!(capture.input(7, RawInput::Axis { index: 0, value: -32000 }), None);
+        capture.input(7, RawInput::Axis { index: 0, value: 0 });
+        assert_eq!(source(capture.input(7, RawInput::Axis { index: 0, value: 30000 })), Some("+a0".into()));
+
+        // A pedal resting at one end is a whole axis.
+        assert_eq!(source(capture.input(7, RawInput::Axis { index: 1, value: 0 })), Some("a1".into()));
+
+        assert_eq!(source(capture.input(7, RawInput::Hat { index: 0, state: 4 })), Some("h0.4".into()));
+        assert_eq!(capture.input(7, RawInput::Hat { index: 0, state: 0 }), None);
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MotionConfig {
+    /// Turns the gyro and accelerometer on. Off by default: the sensors cost
+    /// battery and most setups don't use them.
+    pub enabled: bool,
+    /// Also stream raw `gamepad_sensor` samples, not just recognised gestures.
+    pub raw_stream: bool,
+    /// Minimum time between `gamepad_sensor` / `gamepad_tilt` events.
+    pub min_interval_ms: u32,
+    /// Yaw rate (rad/s) a turn has to exceed to count as a flick.
+    pub flick_threshold: f32,
+    pub flick_cooldown_ms: u32,
+    /// Roll angle (degrees) around neutral ignored by the tilt output.
+    pub tilt_deadzone_deg: f32,
+    /// Roll angle (degrees) at which the tilt output reaches ±1.
+    pub tilt_range_deg: f32,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            raw_stream: false,
+            min_interval_ms: 16,
+            flick_threshold: 4.0,
+            flick_cooldown_ms: 300,
+            tilt_deadzone_deg: 5.0,
+            tilt_range_deg: 30.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorKind {
+    Gyro,
+    Accel,
+}
+
+/// Raw sample in SDL's frame: +X right, +Y up, +Z toward the player. Gyro is
+/// in rad/s with calibration bias removed, accel in m/s².
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SensorEvent {
+    pub sensor: SensorKind,
+    pub data: [f32; 3],
+}
+
+/// Orientation relative to the zeroed pose, in degrees, plus `value`: roll
+/// mapped through the tilt deadzone and range onto -1..1 for slider control.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TiltEvent {
+    pub roll: f32,
+    pub pitch: f32,
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionGesture {
+    FlickLeft,
+    FlickRight,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MotionGestureEvent {
+    pub gesture: MotionGesture,
+    pub timestamp: u32,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibrationEvent {
+    pub gyro_bias: [f32; 3],
+    pub samples: u32,
+}
+
+#[derive(Debug, Clone)]
+pub enum MotionOutput {
+    Sensor(SensorEvent),
+    Tilt(TiltEvent),
+    Gesture(MotionGestureEvent),
+    Calibrated(CalibrationEvent),
+    /// No samples arrived during the calibration; the old bias is kept.
+    CalibrationFailed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MotionError {
+    /// No connected controller has a gyro.
+    NoSensors,
+    /// The gamepad thread has exited.
+    Stopped,
+}
+
+impl std::fmt::Display for MotionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MotionError::NoSensors => write!(f, "No connected controller has motion sensors"),
+            MotionError::Stopped => write!(f, "Gamepad thread is not running"),
+        }
+    }
+}
+
+struct Calibration {
+    until: u32,
+    gyro_sum: [f32; 3],
+    accel_sum: [f32; 3],
+    gyro_samples: u32,
+    accel_samples: u32,
+}
+
+#[derive(Default)]
+struct ControllerMotion {
+    gyro_bias: [f32; 3],
+    /// Gravity direction in the zeroed pose, as (roll, pitch) in degrees.
+    neutral: (f32, f32),
+    accel: Option<[f32; 3]>,
+    calibration: Option<Calibration>,
+    last_flick: Option<u32>,
+    last_raw: [Option<u32>; 2],
+    last_tilt: Option<(u32, f32)>,
+}
+
+/// Calibrates the gyro and accelerometer per controller and recognises
+/// flicks and tilt from their samples.
+#[derive(Default)]
+pub struct MotionProcessor {
+    config: MotionConfig,
+    controllers: HashMap<u32, ControllerMotion>,
+}
+
+impl MotionProcessor {
+    pub fn config(&self) -> &MotionConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: MotionConfig) {
+        self.config = config;
+    }
+
+    pub fn remove_controller(&mut self, controller: u32) {
+        self.controllers.remove(&controller);
+    }
+
+    /// Starts averaging samples for `duration_ms` on the given controllers;
+    /// the pads should be held still meanwhile. The averaged gyro becomes the
+    /// bias and the averaged gravity the neutral pose for tilt. Their sensors
+    /// have to be on until `calibrating` turns false.
+    pub fn calibrate(&mut self, controllers: impl IntoIterator<Item = u32>, now: u32, duration_ms: u32) {
+        for controller in controllers {
+            self.controllers.entry(controller).or_default().calibration = Some(Calibration {
+                until: now.wrapping_add(duration_ms),
+                gyro_sum: [0.0; 3],
+                accel_sum: [0.0; 3],
+                gyro_samples: 0,
+                accel_samples: 0,
+            });
+        }
+    }
+
+    pub fn calibrating(&self) -> bool {
+        self.controllers.values().any(|motion| motion.calibration.is_some())
+    }
+
+    /// Milliseconds until `tick` finishes a calibration, if one is running.
+    pub fn next_due(&self, now: u32) -> Option<u32> {
+        self.controllers
+            .values()
+            .filter_map(|motion| motion.calibration.as_ref())
+            .map(|cal| (cal.until.wrapping_sub(now) as i32).max(0) as u32)
+            .min()
+    }
+
+    /// Finishes calibrations whose time is up, whether or not samples came
+    /// in. Each output comes with its controller.
+    pub fn tick(&mut self, now: u32) -> Vec<(u32, MotionOutput)> {
+        self.controllers
+            .iter_mut()
+            .filter(|(_, motion)| {
+                motion.calibration.as_ref().is_some_and(|cal| now.wrapping_sub(cal.until) as i32 >= 0)
+            })
+            .filter_map(|(&controller, motion)| Some((controller, finish_calibration(motion)?)))
+            .collect()
+    }
+
+    /// Makes the current orientation the neutral pose for tilt.
+    pub fn zero(&mut self) {
+        for motion in self.controllers.values_mut() {
+            if let Some(accel) = motion.accel {
+                motion.neutral = orientation(accel);
+            }
+        }
+    }
+
+    pub fn sample(&mut self, controller: u32, sensor: SensorKind, data: [f32; 3], now: u32) -> Vec<MotionOutput> {
+        let config = &self.config;
+        let motion = self.controllers.entry(controller).or_default();
+        let mut out = Vec::new();
+
+        if let Some(cal) = motion.calibration.as_mut() {
+            match sensor {
+                SensorKind::Gyro => {
+                    add(&mut cal.gyro_sum, data);
+                    cal.gyro_samples += 1;
+                }
+                SensorKind::Accel => {
+                    add(&mut cal.accel_sum, data);
+                    cal.accel_samples += 1;
+                }
+            }
+            if now.wrapping_sub(cal.until) as i32 >= 0 {
+                out.extend(finish_calibration(motion));
+            }
+            return out;
+        }
+
+        let data = match sensor {
+            SensorKind::Gyro => sub(data, motion.gyro_bias),
+            SensorKind::Accel => data,
+        };
+
+        let slot = sensor as usize;
+        if config.raw_stream && due(motion.last_raw[slot], now, config.min_interval_ms) {
+            motion.last_raw[slot] = Some(now);
+            out.push(MotionOutput::Sensor(SensorEvent { sensor, data }));
+        }
+
+        match sensor {
+            SensorKind::Gyro => {
+                let yaw = data[1];
+                if yaw.abs() >= config.flick_threshold && due(motion.last_flick, now, config.flick_cooldown_ms) {
+                    motion.last_flick = Some(now);
+                    // Positive yaw is a counter-clockwise turn seen from above.
+                    let gesture = if yaw > 0.0 {
+                        MotionGesture::FlickLeft
+                    } else {
+                        MotionGesture::FlickRight
+                    };
+                    out.push(MotionOutput::Gesture(MotionGestureEvent { gesture, timestamp: now }));
+                }
+            }
+            SensorKind::Accel => {
+                motion.accel = Some(data);
+                let (roll, pitch) = orientation(data);
+                let roll = roll - motion.neutral.0;
+                let pitch = pitch - motion.neutral.1;
+                let value = tilt_value(roll, config.tilt_deadzone_deg, config.tilt_range_deg);
+
+                let changed = motion.last_tilt.is_none_or(|(t, last)| {
+                    (value - last).abs() >= 0.01 && now.wrapping_sub(t) >= config.min_interval_ms
+                });
+                if changed {
+                    motion.last_tilt = Some((now, value));
+                    out.push(MotionOutput::Tilt(TiltEvent { roll, pitch, value }));
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// Applies the averages of a running calibration and ends it.
+fn finish_calibration(motion: &mut ControllerMotion) -> Option<MotionOutput> {
+    let cal = motion.calibration.take()?;
+    if cal.gyro_samples == 0 && cal.accel_samples == 0 {
+        return Some(MotionOutput::CalibrationFailed);
+    }
+    if cal.gyro_samples > 0 {
+        motion.gyro_bias = cal.gyro_sum.map(|v| v / cal.gyro_samples as f32);
+    }
+    if cal.accel_samples > 0 {
+        motion.neutral = orientation(cal.accel_sum.map(|v| v / cal.accel_samples as f32));
+    }
+    Some(MotionOutput::Calibrated(CalibrationEvent {
+        gyro_bias: motion.gyro_bias,
+        samples: cal.gyro_samples,
+    }))
+}
+
+fn due(last: Option<u32>, now: u32, interval_ms: u32) -> bool {
+    last.is_none_or(|t| now.wrapping_sub(t) >= interval_ms)
+}
+
+fn add(sum: &mut [f32; 3], v: [f32; 3]) {
+    for (s, v) in sum.iter_mut().zip(v) {
+        *s += v;
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+/// Roll (left/right tilt) and pitch (forward/back tilt) in degrees from the
+/// gravity vector, 0 when the pad is held level.
+fn orientation(accel: [f32; 3]) -> (f32, f32) {
+    let [x, y, z] = accel;
+    (x.atan2(y).to_degrees(), z.atan2(y).to_degrees())
+}
+
+fn tilt_value(roll: f32, deadzone: f32, range: f32) -> f32 {
+    let magnitude = roll.abs();
+    if magnitude <= deadzone {
+        return 0.0;
+    }
+    let span = (range - deadzone).max(1.0);
+    roll.signum() * ((magnitude - deadzone) / span).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: [f32; 3] = [0.0, 9.81, 0.0];
+
+    fn calibrated(out: &[MotionOutput]) -> Option<&CalibrationEvent> {
+        out.iter().find_map(|output| match output {
+            MotionOutput::Calibrated(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn calibration_averages_the_gyro_into_its_bias() {
+        let mut motion = MotionProcessor::default();
+        motion.calibrate([0], 1000, 100);
+        assert!(motion.calibrating());
+        assert!(motion.sample(0, SensorKind::Gyro, [0.1, -0.2, 0.0], 1010).is_empty());
+        assert!(motion.sample(0, SensorKind::Gyro, [0.3, 0.0, 0.02], 1050).is_empty());
+        assert!(motion.sample(0, SensorKind::Accel, LEVEL, 1060).is_empty());
+        let out = motion.sample(0, SensorKind::Gyro, [0.2, -0.1, 0.01], 1100);
+        let event = calibrated(&out).unwrap();
+        assert_eq!(event.samples, 3);
+        let expected = [0.2, -0.1, 0.01];
+        assert!(event.gyro_bias.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-6));
+        assert!(!motion.calibrating());
+
+        // Later samples have the bias taken off
+        motion.set_config(MotionConfig { raw_stream: true, ..Default::default() });
+        let out = motion.sample(0, SensorKind::Gyro, [0.2, -0.1, 0.01], 1200);
+        let [MotionOutput::Sensor(SensorEvent { data, .. })] = out.as_slice() else { panic!("{out:?}") };
+        assert!(data.iter().all(|v| v.abs() < 1e-6));
+    }
+
+    #[test]
+    fn calibration_finishes_on_time_without_a_closing_sample() {
+        let mut motion = MotionProcessor::default();
+        motion.calibrate([0], 1000, 100);
+        motion.sample(0, SensorKind::Gyro, [0.4, 0.0, 0.0], 1020);
+        assert_eq!(motion.next_due(1020), Some(80));
+        assert!(motion.tick(1099).is_empty());
+        let out = motion.tick(1100);
+        assert_eq!(out.len(), 1);
+        assert_eq!(calibrated(&[out[0].1.clone()]).unwrap().gyro_bias, [0.4, 0.0, 0.0]);
+        assert_eq!(motion.next_due(1100), None);
+    }
+
+    #[test]
+    fn calibration_without_samples_fails_and_keeps_the_bias() {
+        let mut motion = MotionProcessor::default();
+        motion.calibrate([0], 1000, 100);
+        motion.sample(0, SensorKind::Gyro, [0.5, 0.0, 0.0], 1100);
+        motion.calibrate([0, 1], 2000, 100);
+        let mut out = motion.tick(2100);
+        out.sort_by_key(|(controller, _)| *controller);
+        assert!(matches!(out.as_slice(), [(0, MotionOutput::CalibrationFailed), (1, MotionOutput::CalibrationFailed)]));
+        assert!(!motion.calibrating());
+        assert_eq!(motion.controllers[&0].gyro_bias, [0.5, 0.0, 0.0]);
+    }
+}
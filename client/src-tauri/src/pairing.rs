@@ -0,0 +1,468 @@
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreExt;
+
+use crate::transport::{self, HttpResponse, TransportError};
+
+/// Tauri store the pairing is kept in, next to the server URL.
+pub const PAIRING_STORE: &str = "settings.json";
+const PAIRING_STORE_KEY: &str = "pairing";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PairingError {
+    /// Neither a PIN nor a `deckpilot://pair` link.
+    InvalidCode,
+    /// The server turned the request down: `wrong_pin`, `expired`, or
+    /// `busy`, `rate_limited` and `locked` while it limits pairing;
+    /// `not_allowed` when revoking another device.
+    Rejected(String),
+    NotPaired,
+    Http(String),
+    /// The server's certificate isn't the one pinned when pairing.
+    CertificateChanged { expected: String, found: String },
+    Store(String),
+}
+
+impl From<TransportError> for PairingError {
+    fn from(error: TransportError) -> Self {
+        match error {
+            TransportError::Io(error) => PairingError::Http(error),
+            TransportError::CertificateChanged { expected, found } => {
+                PairingError::CertificateChanged { expected, found }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for PairingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PairingError::InvalidCode => write!(f, "Enter the 6-digit PIN shown on the server"),
+            PairingError::Rejected(reason) if reason == "wrong_pin" => write!(f, "Wrong PIN"),
+            PairingError::Rejected(reason) if reason == "expired" => {
+                write!(f, "The PIN has expired, start pairing again")
+            }
+            PairingError::Rejected(reason) if reason == "busy" => {
+                write!(f, "Another device is pairing, try again in a couple of minutes")
+            }
+            PairingError::Rejected(reason) if reason == "rate_limited" || reason == "locked" => {
+                write!(f, "Too many pairing attempts, try again later")
+            }
+            PairingError::Rejected(reason) if reason == "not_allowed" => {
+                write!(f, "Other devices can only be unpaired on the server's machine")
+            }
+            PairingError::Rejected(reason) => write!(f, "Pairing refused: {reason}"),
+            PairingError::NotPaired => write!(f, "Not paired with a server"),
+            PairingError::Http(error) => write!(f, "Could not reach the server: {error}"),
+            PairingError::CertificateChanged { found, .. } => {
+                write!(f, "The server's certificate has changed (now {found})")
+            }
+            PairingError::Store(error) => write!(f, "Could not save the pairing: {error}"),
+        }
+    }
+}
+
+/// This device's credentials for one server, issued when pairing.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pairing {
+    /// Server URL as the user entered it (`http://host:port`).
+    pub server: String,
+    pub device_id: String,
+    /// Hex HMAC-SHA256 key, derived from the PIN on both sides when pairing;
+    /// it's never sent.
+    pub key: String,
+    /// Certificate fingerprint pinned for an `https` server.
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+}
+
+impl Pairing {
+    /// Token proving this device's identity for one `method` request to
+    /// `path` at `now`: `<deviceId>.<unix seconds>.<nonce>.<signature>`, the
+    /// signature being hex HMAC-SHA256(key, "<deviceId>.<unix seconds>.<nonce>.<METHOD>.<path>").
+    /// The server takes it once, within a minute of its clock, and only for
+    /// that request, so one seen in transit is no use to anyone else.
+    pub fn token(&self, method: &str, path: &str, now: SystemTime) -> String {
+        self.sign(method, path, now, &format!("{:016x}", fastrand::u64(..)))
+    }
+
+    fn sign(&self, method: &str, path: &str, now: SystemTime, nonce: &str) -> String {
+        let issued = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let claim = format!("{}.{issued}.{nonce}", self.device_id);
+        // The server signs the path without its query
+        let path = path.split('?').next().unwrap_or(path);
+        let key = decode_hex(&self.key).unwrap_or_default();
+        let signature = hmac_hex(&key, &format!("{claim}.{}.{path}", method.to_ascii_uppercase()));
+        format!("{claim}.{signature}")
+    }
+
+    /// Whether these credentials are for the server at `server_url`.
+    pub fn is_for(&self, server_url: &str) -> bool {
+        self.server.trim_end_matches('/') == server_url.trim_end_matches('/')
+    }
+}
+
+fn hmac_hex(key: &[u8], message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The key pairing request `request_id` ends in, worked out from the PIN
+/// by the server and the device alike: hex HMAC-SHA256(pin, "deckpilot-pair.<requestId>").
+/// Over plain `http` someone watching the pairing could still find it by
+/// trying every PIN; over `https` they see nothing.
+fn derive_key(pin: &str, request_id: &str) -> String {
+    hmac_hex(pin.as_bytes(), &format!("deckpilot-pair.{request_id}"))
+}
+
+/// Shows the server the device knows the PIN without sending it (or the key).
+fn pairing_proof(key: &str, request_id: &str) -> String {
+    hmac_hex(&decode_hex(key).unwrap_or_default(), &format!("confirm.{request_id}"))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// What the webview is told about the pairing; the key stays in Rust.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingInfo {
+    pub server: String,
+    pub device_id: String,
+    pub fingerprint: Option<String>,
+}
+
+impl From<&Pairing> for PairingInfo {
+    fn from(pairing: &Pairing) -> Self {
+        Self {
+            server: pairing.server.clone(),
+            device_id: pairing.device_id.clone(),
+            fingerprint: pairing.fingerprint.clone(),
+        }
+    }
+}
+
+/// Name the server lists this device under unless the user picks one.
+pub fn device_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "DeckPilot app".to_string())
+}
+
+/// Reply to `POST /api/pair/request`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingRequest {
+    pub request_id: String,
+    pub expires_in_ms: u64,
+    /// For `https` servers, the certificate seen, to check against the one
+    /// the server prints and pin when pairing completes.
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+}
+
+/// A device paired with the server, as `PairedDevice` in `shared/src/types`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairedDevice {
+    pub id: String,
+    pub name: String,
+    pub paired_at: String,
+    #[serde(default)]
+    pub last_seen: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Issued {
+    device_id: String,
+}
+
+#[derive(serde::Deserialize)]
+struct Refusal {
+    error: String,
+}
+
+/// What the user typed or scanned: a bare PIN for the pending request, or
+/// the `deckpilot://pair?request=...&pin=...` link the server prints, which
+/// names its own request. Returns `(request, pin)`.
+pub fn parse_code(code: &str) -> Result<(Option<&str>, &str), PairingError> {
+    let code = code.trim();
+    let (request, pin) = match code.strip_prefix("deckpilot://pair?") {
+        Some(query) => {
+            let param = |name: &str| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+            };
+            (param("request"), param("pin").ok_or(PairingError::InvalidCode)?)
+        }
+        None => (None, code),
+    };
+    if pin.len() != 6 || !pin.chars().all(|c| c.is_ascii_digit()) {
+        return Err(PairingError::InvalidCode);
+    }
+    Ok((request, pin))
+}
+
+fn send(
+    method: &str,
+    server_url: &str,
+    path: &str,
+    pairing: Option<&Pairing>,
+    body: Option<&serde_json::Value>,
+    pin: Option<&str>,
+) -> Result<HttpResponse, PairingError> {
+    let url = format!("{}{path}", server_url.trim_end_matches('/'));
+    let authorization = pairing.map(|pairing| format!("Bearer {}", pairing.token(method, path, SystemTime::now())));
+    let body = body.map(|body| body.to_string());
+    let body = body.as_deref().map(|body| ("application/json", body.as_bytes()));
+    Ok(transport::http_request(method, &url, authorization.as_deref(), body, pin, REQUEST_TIMEOUT)?)
+}
+
+/// Reads a JSON reply, turning an error status into `Rejected` with the
+/// server's reason.
+fn read_reply<T: serde::de::DeserializeOwned>(reply: &HttpResponse) -> Result<T, PairingError> {
+    if reply.status == 401 {
+        return Err(PairingError::NotPaired);
+    }
+    if !(200..300).contains(&reply.status) {
+        let reason = serde_json::from_slice::<Refusal>(&reply.body).map_or_else(|_| reply.status.to_string(), |r| r.error);
+        return Err(PairingError::Rejected(reason));
+    }
+    serde_json::from_slice(&reply.body).map_err(|e| PairingError::Http(e.to_string()))
+}
+
+/// Asks the server for a PIN; it shows the PIN on its own screen. Over
+/// `https` whatever certificate the server presents is accepted here, and
+/// reported for the user to compare.
+pub fn request_pairing(server_url: &str, name: &str) -> Result<PairingRequest, PairingError> {
+    let body = serde_json::json!({ "name": name });
+    let reply = send("POST", server_url, "/api/pair/request", None, Some(&body), None)?;
+    Ok(PairingRequest { fingerprint: reply.fingerprint.clone(), ..read_reply(&reply)? })
+}
+
+/// Completes pairing with the PIN (or pairing link): both sides derive the
+/// key from it, and the server only gets a proof and hands out the device
+/// id. Pins `fingerprint`, the certificate the user saw when pairing began.
+pub fn confirm_pairing(
+    server_url: &str,
+    request_id: &str,
+    code: &str,
+    fingerprint: Option<&str>,
+) -> Result<Pairing, PairingError> {
+    let (linked_request, pin) = parse_code(code)?;
+    let request_id = linked_request.unwrap_or(request_id);
+    let key = derive_key(pin, request_id);
+    let body = serde_json::json!({ "requestId": request_id, "proof": pairing_proof(&key, request_id) });
+    let reply = send("POST", server_url, "/api/pair/confirm", None, Some(&body), fingerprint)?;
+    let issued: Issued = read_reply(&reply)?;
+    Ok(Pairing {
+        server: server_url.trim_end_matches('/').to_string(),
+        device_id: issued.device_id,
+        key,
+        fingerprint: reply.fingerprint,
+    })
+}
+
+pub fn paired_devices(pairing: &Pairing) -> Result<Vec<PairedDevice>, PairingError> {
+    let pin = pairing.fingerprint.as_deref();
+    read_reply(&send("GET", &pairing.server, "/api/pair/devices", Some(pairing), None, pin)?)
+}
+
+/// Revokes `device_id`'s pairing on the server.
+pub fn revoke(pairing: &Pairing, device_id: &str) -> Result<(), PairingError> {
+    let path = format!("/api/pair/devices/{device_id}");
+    let reply = send("DELETE", &pairing.server, &path, Some(pairing), None, pairing.fingerprint.as_deref())?;
+    read_reply::<serde_json::Value>(&reply).map(|_| ())
+}
+
+/// The server's reply to a request the webview made through [`proxy`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxiedResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// Makes one of the webview's REST requests to the paired server. The
+/// webview can't verify an `https` server's self-signed certificate, so
+/// those requests go through here, against the pinned one.
+pub fn proxy(
+    pairing: &Pairing,
+    method: &str,
+    path: &str,
+    body: Option<(&str, &[u8])>,
+) -> Result<ProxiedResponse, PairingError> {
+    let authorization = format!("Bearer {}", pairing.token(method, path, SystemTime::now()));
+    let url = format!("{}{path}", pairing.server.trim_end_matches('/'));
+    let pin = pairing.fingerprint.as_deref();
+    let reply = transport::http_request(method, &url, Some(&authorization), body, pin, REQUEST_TIMEOUT)?;
+    Ok(ProxiedResponse { status: reply.status, content_type: reply.content_type, body: reply.body })
+}
+
+/// Managed state: the current pairing, mirrored to the Tauri store.
+#[derive(Default)]
+pub struct PairingState {
+    pairing: Mutex<Option<Pairing>>,
+}
+
+impl PairingState {
+    pub fn load<R: Runtime>(app: &AppHandle<R>) -> Self {
+        let pairing = app
+            .store(PAIRING_STORE)
+            .ok()
+            .and_then(|store| store.get(PAIRING_STORE_KEY))
+            .and_then(|value| serde_json::from_value(value).ok());
+        Self { pairing: Mutex::new(pairing) }
+    }
+
+    pub fn current(&self) -> Option<Pairing> {
+        self.pairing.lock().ok()?.clone()
+    }
+
+    /// Replaces the pairing, `None` forgetting it, and saves the change.
+    pub fn set<R: Runtime>(&self, app: &AppHandle<R>, pairing: Option<Pairing>) -> Result<(), PairingError> {
+        let store = app.store(PAIRING_STORE).map_err(|e| PairingError::Store(e.to_string()))?;
+        match &pairing {
+            Some(pairing) => store.set(PAIRING_STORE_KEY, serde_json::to_value(pairing).expect("pairing serializes")),
+            None => {
+                store.delete(PAIRING_STORE_KEY);
+            }
+        }
+        store.save().map_err(|e| PairingError::Store(e.to_string()))?;
+        if let Ok(mut current) = self.pairing.lock() {
+            *current = pairing;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn pairing() -> Pairing {
+        Pairing {
+            server: "http://127.0.0.1:9900".into(),
+            device_id: "a1b2c3d4e5f60718".into(),
+            key: "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff".into(),
+            fingerprint: None,
+        }
+    }
+
+    #[test]
+    fn tokens_are_signed_with_the_device_key() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            pairing().sign("get", "/ws?token=x", now, "0123456789abcdef"),
+            "a1b2c3d4e5f60718.1700000000.0123456789abcdef.\
+             2030ff600d96fc391d7efcb7b681a241f0b8abcc835bae43c206f7c0fe35f66e"
+        );
+        // Every token gets its own nonce, so none is ever accepted twice
+        assert_ne!(pairing().token("GET", "/ws", now), pairing().token("GET", "/ws", now));
+        assert!(pairing().is_for("http://127.0.0.1:9900/"));
+        assert!(!pairing().is_for("http://127.0.0.1:9901"));
+    }
+
+    #[test]
+    fn codes_are_pins_or_pairing_links() {
+        assert_eq!(parse_code(" 042917 "), Ok((None, "042917")));
+        assert_eq!(parse_code("deckpilot://pair?request=ab12&pin=042917"), Ok((Some("ab12"), "042917")));
+        assert_eq!(parse_code("42917"), Err(PairingError::InvalidCode));
+        assert_eq!(parse_code("deckpilot://pair?request=ab12"), Err(PairingError::InvalidCode));
+    }
+
+    /// Answers one HTTP request with `status` and `body`, returning the
+    /// request line and body it got.
+    fn respond_once(listener: TcpListener, status: &'static str, body: &'static str) -> thread::JoinHandle<(String, String)> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut request_body = vec![0; length];
+            reader.read_exact(&mut request_body).unwrap();
+            let reply = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            reader.get_mut().write_all(reply.as_bytes()).unwrap();
+            (request_line.trim().to_string(), String::from_utf8(request_body).unwrap())
+        })
+    }
+
+    #[test]
+    fn confirming_derives_the_key_from_the_pin() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        let responder = respond_once(listener, "200 OK", r#"{"deviceId":"d1"}"#);
+
+        let pairing = confirm_pairing(&format!("{server}/"), "req", "123456", None).unwrap();
+        let key = "36551bfd39c5de9da11fca2bed7c2a3c130474de5a479a59f1e1cccecc74c4a5";
+        let expected = Pairing { server: server.clone(), device_id: "d1".into(), key: key.into(), fingerprint: None };
+        assert_eq!(pairing, expected);
+        // Neither the PIN nor the key goes over the wire
+        let (request_line, body) = responder.join().unwrap();
+        assert_eq!(request_line, "POST /api/pair/confirm HTTP/1.1");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let proof = "c0d282b6fd522f69833b33b6fd0b18a65d20b18bdfc12b4e176930e265e87333";
+        assert_eq!(body, serde_json::json!({ "requestId": "req", "proof": proof }));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        let responder = respond_once(listener, "403 Forbidden", r#"{"error":"wrong_pin"}"#);
+        assert_eq!(confirm_pairing(&server, "req", "000000", None), Err(PairingError::Rejected("wrong_pin".into())));
+        responder.join().unwrap();
+    }
+
+    #[test]
+    fn proxied_requests_pass_bodies_through_as_they_are() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        let responder = respond_once(listener, "201 Created", r#"{"ok":true}"#);
+
+        let paired = Pairing { server, ..pairing() };
+        let reply = proxy(&paired, "POST", "/api/sounds", Some(("text/plain", b"beep"))).unwrap();
+        assert_eq!(reply, ProxiedResponse {
+            status: 201,
+            content_type: Some("application/json".into()),
+            body: br#"{"ok":true}"#.to_vec(),
+        });
+        assert_eq!(responder.join().unwrap(), ("POST /api/sounds HTTP/1.1".into(), "beep".into()));
+    }
+}
@@ -0,0 +1,194 @@
+use serde_json::Value;
+
+/// An action as configured on a widget or binding. Its params depend on the
+/// type and are only interpreted by the server.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Action {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingKind {
+    /// Handled in the webview (page navigation).
+    Client,
+    /// Runs `action` on the server. Legacy bindings without a kind are these.
+    #[default]
+    Server,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GamepadBinding {
+    pub button: u8,
+    /// Controller GUID this binding is limited to; unset matches any controller.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub controller: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub controller_name: Option<String>,
+    #[serde(default)]
+    pub kind: BindingKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<Action>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_action: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Send the press even if it was queued offline for longer than the
+    /// connection's `lateAfterMs`.
+    #[serde(default)]
+    pub replay_if_late: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ServerAddress {
+    pub port: u16,
+    pub host: String,
+    /// Pairing and TLS settings, kept so the config reaches the webview
+    /// intact when it's bridged through the native connection.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, Value>,
+}
+
+/// Mirrors `DeckPilotConfig` in `shared/src/types`, typing out only what the
+/// native connection acts on.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeckPilotConfig {
+    pub version: u32,
+    pub server: ServerAddress,
+    pub active_profile: String,
+    /// Pages and widgets; only the webview renders them.
+    pub profiles: Vec<Value>,
+    #[serde(default)]
+    pub gamepad_bindings: Vec<GamepadBinding>,
+}
+
+impl DeckPilotConfig {
+    /// The binding `button` triggers on `controller`: one limited to that
+    /// controller wins over a catch-all one, as on the server.
+    pub fn gamepad_binding(&self, button: u8, controller: Option<&str>) -> Option<&GamepadBinding> {
+        let bindings = || self.gamepad_bindings.iter().filter(move |b| b.button == button);
+        bindings()
+            .find(|b| controller.is_some() && b.controller.as_deref() == controller)
+            .or_else(|| bindings().find(|b| b.controller.is_none()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionResult {
+    pub success: bool,
+    pub action_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StateUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub muted: Option<bool>,
+    pub connected: bool,
+}
+
+/// Messages sent to the server over `/ws`, as `ClientMessage` in
+/// `shared/src/types`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    #[serde(rename_all = "camelCase")]
+    ButtonPress { action_id: String, page: String },
+    #[serde(rename_all = "camelCase")]
+    ButtonLongPress { action_id: String, page: String },
+    #[serde(rename_all = "camelCase")]
+    SliderChange { widget_id: String, page: String, value: f64 },
+    GamepadButton {
+        button: u8,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        controller: Option<String>,
+    },
+    RequestConfig,
+    Ping,
+}
+
+/// Messages received from the server, as `ServerMessage` in
+/// `shared/src/types`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Config { payload: DeckPilotConfig },
+    ConfigUpdated { payload: DeckPilotConfig },
+    ActionResult { payload: ActionResult },
+    StateUpdate { payload: StateUpdate },
+    LiveData { source: String, data: Value },
+    Pong,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn client_messages_match_the_server_protocol() {
+        let press = ClientMessage::ButtonPress { action_id: "mute".into(), page: "main".into() };
+        assert_eq!(
+            serde_json::to_value(press).unwrap(),
+            json!({ "type": "button_press", "actionId": "mute", "page": "main" })
+        );
+        let button = ClientMessage::GamepadButton { button: 3, controller: None };
+        assert_eq!(serde_json::to_value(button).unwrap(), json!({ "type": "gamepad_button", "button": 3 }));
+        assert_eq!(serde_json::to_value(ClientMessage::RequestConfig).unwrap(), json!({ "type": "request_config" }));
+    }
+
+    #[test]
+    fn server_messages_parse() {
+        let config = json!({
+            "type": "config",
+            "payload": {
+                "version": 2,
+                "server": { "port": 9900, "host": "0.0.0.0" },
+                "activeProfile": "default",
+                "profiles": [{ "id": "default", "name": "Default", "pages": [] }],
+                "gamepadBindings": [
+                    { "button": 0, "kind": "server", "action": { "type": "media.play_pause", "params": {} } },
+                    { "button": 0, "controller": "deck", "kind": "client", "clientAction": "nav.page.next" }
+                ]
+            }
+        });
+        let ServerMessage::Config { payload } = serde_json::from_value(config).unwrap() else { panic!() };
+        assert_eq!(payload.gamepad_binding(0, Some("deck")).unwrap().kind, BindingKind::Client);
+        assert_eq!(payload.gamepad_binding(0, Some("xbox")).unwrap().kind, BindingKind::Server);
+        assert_eq!(payload.gamepad_binding(0, None).unwrap().kind, BindingKind::Server);
+        assert!(payload.gamepad_binding(1, None).is_none());
+
+        let result = json!({ "type": "action_result", "payload": { "success": false, "actionType": "exec.shell", "error": "boom" } });
+        assert!(matches!(
+            serde_json::from_value(result).unwrap(),
+            ServerMessage::ActionResult { payload: ActionResult { success: false, .. } }
+        ));
+        assert_eq!(serde_json::from_value::<ServerMessage>(json!({ "type": "pong" })).unwrap(), ServerMessage::Pong);
+    }
+
+    #[test]
+    fn bridged_messages_leave_out_what_the_server_left_out() {
+        // Nulls would fail the server's schema when the webview saves them back
+        let binding = json!({ "button": 2, "kind": "server", "action": { "type": "media.play_pause" } });
+        let parsed: GamepadBinding = serde_json::from_value(binding).unwrap();
+        assert_eq!(
+            serde_json::to_value(parsed).unwrap(),
+            json!({ "button": 2, "kind": "server", "action": { "type": "media.play_pause" }, "replayIfLate": false })
+        );
+        let state = json!({ "type": "state_update", "payload": { "connected": true } });
+        let parsed: ServerMessage = serde_json::from_value(state.clone()).unwrap();
+        assert_eq!(serde_json::to_value(parsed).unwrap(), state);
+        let result = json!({ "type": "action_result", "payload": { "success": true, "actionType": "audio.mute" } });
+        let parsed: ServerMessage = serde_json::from_value(result.clone()).unwrap();
+        assert_eq!(serde_json::to_value(parsed).unwrap(), result);
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::controller::ControllerInfo;
+use crate::haptic::HapticPlayer;
+use crate::led::LedColor;
+
+/// One open controller plus everything tracked for it. Generic over the
+/// controller and haptic handles so the bookkeeping can be exercised without
+/// SDL devices.
+pub struct Device<C, H> {
+    pub info: ControllerInfo,
+    pub controller: C,
+    pub haptic: Option<H>,
+    /// Haptic patterns playing or queued on this controller.
+    pub player: HapticPlayer,
+    /// Colour last sent to the LED, to skip repeating it.
+    pub led: Option<LedColor>,
+    /// W3C indices currently held, including latched triggers (6 and 7).
+    held: BTreeSet<u8>,
+}
+
+impl<C, H> Device<C, H> {
+    /// Marks `button` held. Returns `false` if it already was, so repeated
+    /// down events (or a trigger hovering past its threshold) are dropped.
+    pub fn press(&mut self, button: u8) -> bool {
+        self.held.insert(button)
+    }
+
+    /// Marks `button` released. Returns `false` if it wasn't held.
+    pub fn release(&mut self, button: u8) -> bool {
+        self.held.remove(&button)
+    }
+
+    /// Buttons still held, so listeners can be sent the matching releases
+    /// when the device goes away.
+    pub fn held(&self) -> impl Iterator<Item = u8> + '_ {
+        self.held.iter().copied()
+    }
+}
+
+/// Open controllers keyed by SDL instance id, which replaces the old parallel
+/// controller and haptic vectors that could drift out of step.
+pub struct ControllerRegistry<C, H> {
+    devices: BTreeMap<u32, Device<C, H>>,
+}
+
+impl<C, H> Default for ControllerRegistry<C, H> {
+    fn default() -> Self {
+        Self {
+            devices: BTreeMap::new(),
+        }
+    }
+}
+
+impl<C, H> ControllerRegistry<C, H> {
+    pub fn contains(&self, id: u32) -> bool {
+        self.devices.contains_key(&id)
+    }
+
+    /// Registers a freshly opened controller. SDL sends `ControllerDeviceAdded`
+    /// for pads that were already present at startup, so a second open of the
+    /// same instance is rejected and the caller's handles are dropped.
+    pub fn insert(&mut self, info: ControllerInfo, controller: C, haptic: Option<H>) -> bool {
+        if self.devices.contains_key(&info.id) {
+            return false;
+        }
+        let device = Device {
+            info,
+            controller,
+            haptic,
+            player: HapticPlayer::default(),
+            led: None,
+            held: BTreeSet::new(),
+        };
+        self.devices.insert(device.info.id, device);
+        true
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Device<C, H>> {
+        self.devices.remove(&id)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Device<C, H>> {
+        self.devices.get_mut(&id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.devices.keys().copied()
+    }
+
+    pub fn devices(&self) -> impl Iterator<Item = &Device<C, H>> {
+        self.devices.values()
+    }
+
+    pub fn devices_mut(&mut self) -> impl Iterator<Item = &mut Device<C, H>> {
+        self.devices.values_mut()
+    }
+
+    pub fn infos(&self) -> Vec<ControllerInfo> {
+        self.devices.values().map(|d| d.info.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::ControllerKind;
+
+    fn info(id: u32, guid: &str) -> ControllerInfo {
+        ControllerInfo {
+            id,
+            guid: guid.to_string(),
+            name: format!("Pad {id}"),
+            kind: ControllerKind::Generic,
+            vendor_id: None,
+            product_id: None,
+            rumble: false,
+            led: false,
+            motion: false,
+            battery: Default::default(),
+        }
+    }
+
+    /// Haptic handle stand-in that records which pad it belongs to.
+    struct Rumble(u32);
+
+    #[test]
+    fn rejects_duplicate_instance() {
+        let mut registry = ControllerRegistry::<(), Rumble>::default();
+        assert!(registry.insert(info(0, "a"), (), Some(Rumble(0))));
+        assert!(!registry.insert(info(0, "a"), (), None));
+        assert_eq!(registry.ids().collect::<Vec<_>>(), vec![0]);
+        // The original haptic handle survives the rejected re-open.
+        assert_eq!(registry.devices().filter(|d| d.haptic.is_some()).count(), 1);
+    }
+
+    #[test]
+    fn removal_keeps_haptics_with_their_pad() {
+        let mut registry = ControllerRegistry::<(), Rumble>::default();
+        registry.insert(info(0, "a"), (), Some(Rumble(0)));
+        registry.insert(info(1, "b"), (), None);
+        registry.insert(info(2, "c"), (), Some(Rumble(2)));
+
+        let removed = registry.remove(0).unwrap();
+        assert_eq!(removed.haptic.map(|h| h.0), Some(0));
+        assert!(registry.remove(0).is_none());
+
+        let pads: Vec<u32> = registry.devices_mut().filter_map(|d| d.haptic.as_mut()).map(|h| h.0).collect();
+        assert_eq!(pads, vec![2]);
+        assert_eq!(registry.ids().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn reconnect_gets_fresh_state() {
+        let mut registry = ControllerRegistry::<(), ()>::default();
+        registry.insert(info(0, "deck"), (), None);
+        let device = registry.get_mut(0).unwrap();
+        assert!(device.press(6));
+        assert!(!device.press(6));
+        assert!(device.press(0));
+
+        let removed = registry.remove(0).unwrap();
+        assert_eq!(removed.held().collect::<Vec<_>>(), vec![0, 6]);
+
+        // Same physical pad comes back with a new instance id.
+        assert!(registry.insert(info(1, "deck"), (), None));
+        assert!(registry.get_mut(1).unwrap().press(6));
+        assert_eq!(registry.infos()[0].guid, "deck");
+    }
+
+    #[test]
+    fn identical_pads_are_kept_apart() {
+        // Two pads of the same model share a GUID but not an instance id.
+        let mut registry = ControllerRegistry::<(), ()>::default();
+        assert!(registry.insert(info(3, "xbox"), (), None));
+        assert!(registry.insert(info(4, "xbox"), (), None));
+        registry.get_mut(3).unwrap().press(0);
+        assert!(!registry.get_mut(4).unwrap().release(0));
+        assert_eq!(registry.infos().len(), 2);
+    }
+}
@@ -0,0 +1,399 @@
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::haptic::Haptic;
+use sdl2::joystick::{Joystick, PowerLevel};
+use sdl2::sensor::SensorType;
+use sdl2::event::EventSender;
+use sdl2::sys;
+use std::path::Path;
+use std::ptr::NonNull;
+use sdl2::{EventPump, EventSubsystem, GameControllerSubsystem, HapticSubsystem, JoystickSubsystem, TimerSubsystem};
+
+use crate::axis::AxisId;
+use crate::backend::{BackendEvent, InputBackend, Subsystem, SubsystemError, WakeFn};
+use crate::battery::{BatteryLevel, BatteryStatus};
+use crate::controller::{ControllerInfo, ControllerKind};
+use crate::deck_haptics::{DeckHaptics, HidDevice};
+use crate::haptic::Rumble;
+use crate::led::LedColor;
+use crate::mapping::{RawInput, RawJoystick};
+use crate::motion::SensorKind;
+use crate::touchpad::{TouchPhase, TouchpadEvent};
+
+/// Maps SDL2 GameController buttons to W3C Gamepad API indices. Buttons the
+/// standard layout doesn't cover get stable extended indices from 17 up; keep
+/// these in sync with `GAMEPAD_LABELS` in the client.
+fn button_to_w3c(button: Button) -> u8 {
+    match button {
+        Button::A => 0,
+        Button::B => 1,
+        Button::X => 2,
+        Button::Y => 3,
+        Button::LeftShoulder => 4,
+        Button::RightShoulder => 5,
+        Button::Back => 8,
+        Button::Start => 9,
+        Button::LeftStick => 10,
+        Button::RightStick => 11,
+        Button::DPadUp => 12,
+        Button::DPadDown => 13,
+        Button::DPadLeft => 14,
+        Button::DPadRight => 15,
+        Button::Guide => 16,
+        Button::Misc1 => 17,
+        Button::Touchpad => 18,
+        // Steam Deck back grips: R4, L4, R5, L5
+        Button::Paddle1 => 19,
+        Button::Paddle2 => 20,
+        Button::Paddle3 => 21,
+        Button::Paddle4 => 22,
+    }
+}
+
+fn axis_id(axis: Axis) -> AxisId {
+    match axis {
+        Axis::LeftX => AxisId::LeftX,
+        Axis::LeftY => AxisId::LeftY,
+        Axis::RightX => AxisId::RightX,
+        Axis::RightY => AxisId::RightY,
+        Axis::TriggerLeft => AxisId::TriggerLeft,
+        Axis::TriggerRight => AxisId::TriggerRight,
+    }
+}
+
+const STEAM_DECK_VID: u16 = 0x28de;
+const STEAM_DECK_PID: u16 = 0x1205;
+/// USB interface of the Deck's controller; the others are keyboard and mouse
+/// emulation.
+const STEAM_DECK_CONTROLLER_INTERFACE: i32 = 2;
+
+/// A HID device opened through SDL's bundled HIDAPI.
+pub struct SdlHid(NonNull<sys::SDL_hid_device>);
+
+impl SdlHid {
+    /// Opens the Steam Deck's controller interface, if this is a Deck and the
+    /// hidraw node is accessible.
+    fn open_steam_deck() -> Option<Self> {
+        // SAFETY: the enumeration list is only walked before it is freed, and
+        // init/exit are reference counted by SDL.
+        unsafe {
+            if sys::SDL_hid_init() != 0 {
+                return None;
+            }
+            let devices = sys::SDL_hid_enumerate(STEAM_DECK_VID, STEAM_DECK_PID);
+            let mut device = std::ptr::null_mut();
+            let mut info = devices;
+            while let Some(current) = info.as_ref() {
+                if current.interface_number == STEAM_DECK_CONTROLLER_INTERFACE {
+                    device = sys::SDL_hid_open_path(current.path, 0);
+                    break;
+                }
+                info = current.next;
+            }
+            sys::SDL_hid_free_enumeration(devices);
+            let opened = NonNull::new(device).map(SdlHid);
+            if opened.is_none() {
+                sys::SDL_hid_exit();
+            }
+            opened
+        }
+    }
+}
+
+impl HidDevice for SdlHid {
+    fn send_feature_report(&mut self, data: &[u8]) -> Result<(), String> {
+        // SAFETY: the device stays open until drop.
+        let sent = unsafe { sys::SDL_hid_send_feature_report(self.0.as_ptr(), data.as_ptr(), data.len()) };
+        if sent < 0 {
+            Err(sdl2::get_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for SdlHid {
+    fn drop(&mut self) {
+        // SAFETY: opened in `open_steam_deck`, closed exactly once here.
+        unsafe {
+            sys::SDL_hid_close(self.0.as_ptr());
+            sys::SDL_hid_exit();
+        }
+    }
+}
+
+/// An opened controller, plus the trackpad actuators if it's a Steam Deck.
+pub struct SdlController {
+    pad: GameController,
+    /// Second handle on the same device, for what GameController doesn't
+    /// expose (power level).
+    joystick: Option<Joystick>,
+    trackpads: Option<DeckHaptics<SdlHid>>,
+}
+
+/// Input from SDL2's GameController API.
+pub struct SdlBackend {
+    timer: TimerSubsystem,
+    joystick: JoystickSubsystem,
+    game_controller: GameControllerSubsystem,
+    /// `None` where haptics can't be initialised; input still works.
+    haptic: Option<HapticSubsystem>,
+    events: EventSubsystem,
+    event_pump: EventPump,
+    /// User event pushed to interrupt `wait_event_timeout`.
+    wake_event: u32,
+    /// Every joystick while raw capture is on, so its events come through.
+    captured: Vec<Joystick>,
+}
+
+impl SdlBackend {
+    /// Initialises the SDL subsystems. Haptics are optional: if they fail,
+    /// the backend still starts and the failure is returned alongside it.
+    /// Mappings in `mapping_db` are added on top of SDL's built-in ones.
+    pub fn new(mapping_db: Option<&Path>) -> Result<(Self, Vec<SubsystemError>), SubsystemError> {
+        let sdl = sdl2::init().map_err(|e| SubsystemError::new(Subsystem::Core, e))?;
+        let timer = sdl.timer().map_err(|e| SubsystemError::new(Subsystem::Timer, e))?;
+        let joystick = sdl.joystick().map_err(|e| SubsystemError::new(Subsystem::Joystick, e))?;
+        let game_controller = sdl
+            .game_controller()
+            .map_err(|e| SubsystemError::new(Subsystem::GameController, e))?;
+        let mut errors = Vec::new();
+        if let Some(path) = mapping_db.filter(|path| path.exists()) {
+            // A broken user file shouldn't stop the built-in mappings working.
+            if let Err(error) = game_controller.load_mappings(path) {
+                errors.push(SubsystemError::new(Subsystem::Mappings, format!("{}: {error}", path.display())));
+            }
+        }
+        let events = sdl.event().map_err(|e| SubsystemError::new(Subsystem::Events, e))?;
+        let event_pump = sdl.event_pump().map_err(|e| SubsystemError::new(Subsystem::Events, e))?;
+        // SAFETY: only ever pushed as a plain user event with null data.
+        let wake_event = unsafe { events.register_event() }.map_err(|e| SubsystemError::new(Subsystem::Events, e))?;
+
+        let haptic = sdl
+            .haptic()
+            .map_err(|e| errors.push(SubsystemError::new(Subsystem::Haptic, e)))
+            .ok();
+
+        let backend = Self {
+            timer,
+            joystick,
+            game_controller,
+            haptic,
+            events,
+            event_pump,
+            wake_event,
+            captured: Vec::new(),
+        };
+        Ok((backend, errors))
+    }
+}
+
+impl InputBackend for SdlBackend {
+    type Controller = SdlController;
+    type Haptic = Haptic;
+
+    fn ticks(&self) -> u32 {
+        self.timer.ticks()
+    }
+
+    fn device_count(&self) -> u32 {
+        self.game_controller.num_joysticks().unwrap_or(0)
+    }
+
+    fn wait(&mut self, timeout_ms: u32) -> Vec<BackendEvent> {
+        let Some(first) = self.event_pump.wait_event_timeout(timeout_ms) else { return Vec::new() };
+        let capturing = !self.captured.is_empty();
+        std::iter::once(first)
+            .chain(self.event_pump.poll_iter())
+            .filter_map(translate)
+            // Opened controllers produce joystick events too
+            .filter(|event| capturing || !matches!(event, BackendEvent::Raw { .. }))
+            .collect()
+    }
+
+    fn waker(&self) -> WakeFn {
+        let sender: EventSender = self.events.event_sender();
+        let type_ = self.wake_event;
+        Box::new(move || {
+            let _ = sender.push_event(Event::User {
+                timestamp: 0,
+                window_id: 0,
+                type_,
+                code: 0,
+                data1: std::ptr::null_mut(),
+                data2: std::ptr::null_mut(),
+            });
+        })
+    }
+
+    fn instance_id(&self, index: u32) -> Option<u32> {
+        // SAFETY: only reads SDL's device list, which it locks internally.
+        let id = unsafe { sys::SDL_JoystickGetDeviceInstanceID(index as i32) };
+        u32::try_from(id).ok()
+    }
+
+    fn open(&mut self, index: u32) -> Option<(ControllerInfo, SdlController, Option<Haptic>)> {
+        if !self.game_controller.is_game_controller(index) {
+            return None;
+        }
+        let pad = self.game_controller.open(index).ok()?;
+        let guid = self.joystick.device_guid(index).ok()?;
+        let mut info = ControllerInfo::new(&pad, guid);
+        let haptic = self.haptic.as_ref().and_then(|h| h.open_from_joystick_id(index).ok());
+        let trackpads = match info.kind {
+            ControllerKind::SteamDeck => SdlHid::open_steam_deck().map(DeckHaptics::new),
+            _ => None,
+        };
+        info.rumble = pad.has_rumble() || haptic.is_some() || trackpads.is_some();
+        let joystick = self.joystick.open(index).ok();
+        Some((info, SdlController { pad, joystick, trackpads }, haptic))
+    }
+
+    /// Turns the gyro and accelerometer on or off where the controller has them.
+    fn set_motion_sensors(&mut self, controller: &SdlController, enabled: bool) {
+        for sensor in [SensorType::Gyroscope, SensorType::Accelerometer] {
+            if controller.pad.has_sensor(sensor) {
+                let _ = controller.pad.sensor_set_enabled(sensor, enabled);
+            }
+        }
+    }
+
+    fn set_rumble(&mut self, controller: &mut SdlController, rumble: &Rumble) -> bool {
+        let pad = &mut controller.pad;
+        if !pad.has_rumble() {
+            return false;
+        }
+        let motor = |strength: f32| (strength.clamp(0.0, 1.0) * f32::from(u16::MAX)) as u16;
+        let ms = rumble.duration_ms;
+        let (low, high) = rumble.motors();
+        let _ = pad.set_rumble(motor(low), motor(high), ms);
+        if pad.has_rumble_triggers() {
+            let _ = pad.set_rumble_triggers(motor(rumble.left_trigger), motor(rumble.right_trigger), ms);
+        }
+        true
+    }
+
+    fn pulse_trackpads(&mut self, controller: &mut SdlController, rumble: &Rumble) -> bool {
+        let Some(trackpads) = controller.trackpads.as_mut() else { return false };
+        if trackpads.play(rumble).is_err() {
+            // Usually the hidraw node going away; SDL rumble takes over, and
+            // `false` tells the caller to fall back to it.
+            controller.trackpads = None;
+            return false;
+        }
+        true
+    }
+
+    fn set_led(&mut self, controller: &mut SdlController, color: LedColor) {
+        let _ = controller.pad.set_led(color.r, color.g, color.b);
+    }
+
+    fn rumble(&mut self, haptic: &mut Haptic, strength: f32, duration_ms: u32) {
+        haptic.rumble_play(strength, duration_ms);
+    }
+
+    fn stop_rumble(&mut self, haptic: &mut Haptic) {
+        haptic.rumble_stop();
+    }
+
+    fn battery(&self, controller: &SdlController) -> BatteryStatus {
+        let level = match controller.joystick.as_ref().map(|j| j.power_level()) {
+            Some(Ok(level)) => level,
+            _ => return BatteryStatus::default(),
+        };
+        let level = match level {
+            PowerLevel::Wired => return BatteryStatus { level: None, wired: true },
+            PowerLevel::Unknown => None,
+            PowerLevel::Empty => Some(BatteryLevel::Empty),
+            PowerLevel::Low => Some(BatteryLevel::Low),
+            PowerLevel::Medium => Some(BatteryLevel::Medium),
+            PowerLevel::Full => Some(BatteryLevel::Full),
+        };
+        BatteryStatus { level, wired: false }
+    }
+
+    fn add_mapping(&mut self, mapping: &str) -> Result<(), String> {
+        self.game_controller.add_mapping(mapping).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn set_raw_capture(&mut self, enabled: bool) -> Vec<RawJoystick> {
+        self.captured.clear();
+        if !enabled {
+            return Vec::new();
+        }
+        let count = self.joystick.num_joysticks().unwrap_or(0);
+        self.captured = (0..count).filter_map(|index| self.joystick.open(index).ok()).collect();
+        self.captured
+            .iter()
+            .map(|joystick| RawJoystick {
+                id: joystick.instance_id(),
+                guid: joystick.guid().string(),
+                name: joystick.name(),
+                buttons: joystick.num_buttons(),
+                axes: joystick.num_axes(),
+                hats: joystick.num_hats(),
+                rest: (0..joystick.num_axes()).map(|axis| joystick.axis(axis).unwrap_or(0)).collect(),
+            })
+            .collect()
+    }
+}
+
+fn translate(event: Event) -> Option<BackendEvent> {
+    let event = match event {
+        Event::ControllerDeviceAdded { which, .. } => BackendEvent::DeviceAdded { index: which },
+        Event::ControllerDeviceRemoved { timestamp, which } => BackendEvent::DeviceRemoved { id: which, timestamp },
+        Event::ControllerButtonDown { timestamp, which, button } => BackendEvent::Button {
+            id: which,
+            button: button_to_w3c(button),
+            pressed: true,
+            timestamp,
+        },
+        Event::ControllerButtonUp { timestamp, which, button } => BackendEvent::Button {
+            id: which,
+            button: button_to_w3c(button),
+            pressed: false,
+            timestamp,
+        },
+        Event::ControllerAxisMotion { timestamp, which, axis, value } => BackendEvent::Axis {
+            id: which,
+            axis: axis_id(axis),
+            value,
+            timestamp,
+        },
+        Event::ControllerTouchpadDown { which, touchpad, finger, x, y, pressure, .. } => {
+            let phase = TouchPhase::Down;
+            BackendEvent::Touchpad { id: which, event: TouchpadEvent { touchpad, finger, phase, x, y, pressure } }
+        }
+        Event::ControllerTouchpadMotion { which, touchpad, finger, x, y, pressure, .. } => {
+            let phase = TouchPhase::Motion;
+            BackendEvent::Touchpad { id: which, event: TouchpadEvent { touchpad, finger, phase, x, y, pressure } }
+        }
+        Event::ControllerTouchpadUp { which, touchpad, finger, x, y, pressure, .. } => {
+            let phase = TouchPhase::Up;
+            BackendEvent::Touchpad { id: which, event: TouchpadEvent { touchpad, finger, phase, x, y, pressure } }
+        }
+        Event::ControllerSensorUpdated { timestamp, which, sensor, data } => {
+            let sensor = match sensor {
+                SensorType::Gyroscope => SensorKind::Gyro,
+                SensorType::Accelerometer => SensorKind::Accel,
+                // Per-half sensors of combined Joy-Cons aren't used
+                _ => return None,
+            };
+            BackendEvent::Sensor { id: which, sensor, data, timestamp }
+        }
+        Event::JoyButtonDown { which, button_idx, .. } => {
+            BackendEvent::Raw { id: which, input: RawInput::Button { index: button_idx, pressed: true } }
+        }
+        Event::JoyButtonUp { which, button_idx, .. } => {
+            BackendEvent::Raw { id: which, input: RawInput::Button { index: button_idx, pressed: false } }
+        }
+        Event::JoyAxisMotion { which, axis_idx, value, .. } => {
+            BackendEvent::Raw { id: which, input: RawInput::Axis { index: axis_idx, value } }
+        }
+        Event::JoyHatMotion { which, hat_idx, state, .. } => {
+            BackendEvent::Raw { id: which, input: RawInput::Hat { index: hat_idx, state: state.to_raw() } }
+        }
+        _ => return None,
+    };
+    Some(event)
+}
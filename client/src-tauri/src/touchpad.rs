@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TouchpadMode {
+    /// Only the raw `gamepad_touchpad` stream.
+    #[default]
+    Raw,
+    /// Relative motion drives a virtual cursor (`gamepad_cursor`).
+    Cursor,
+    /// Swipes become discrete scroll steps (`gamepad_scroll`).
+    Scroll,
+    /// Finger angle around the pad centre picks a sector (`gamepad_radial`),
+    /// committed when the finger lifts.
+    Radial,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TouchpadConfig {
+    /// Mode per touchpad index; on the Steam Deck 0 is the left pad and 1 the right.
+    pub pads: Vec<TouchpadMode>,
+    /// Cursor travel per unit of finger travel.
+    pub cursor_sensitivity: f32,
+    /// Finger travel (in normalised pad units) per scroll step.
+    pub scroll_step: f32,
+    pub radial_sectors: u32,
+    /// Distance from the pad centre (0..0.5) below which no sector is selected.
+    pub radial_deadzone: f32,
+}
+
+impl Default for TouchpadConfig {
+    fn default() -> Self {
+        Self {
+            pads: Vec::new(),
+            cursor_sensitivity: 1.0,
+            scroll_step: 0.1,
+            radial_sectors: 8,
+            radial_deadzone: 0.15,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TouchPhase {
+    Down,
+    Motion,
+    Up,
+}
+
+/// One raw touchpad sample; `x`/`y` are 0..1 from the top-left corner.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TouchpadEvent {
+    pub touchpad: u32,
+    pub finger: u32,
+    pub phase: TouchPhase,
+    pub x: f32,
+    pub y: f32,
+    pub pressure: f32,
+}
+
+/// Absolute position of the virtual cursor, 0..1 on both axes.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorEvent {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Whole scroll steps; positive `dy` means the finger moved down.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrollEvent {
+    pub touchpad: u32,
+    pub dx: i32,
+    pub dy: i32,
+}
+
+/// Sector under the finger, clockwise from 12 o'clock. `selected` is set on
+/// the event sent when the finger lifts.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RadialEvent {
+    pub touchpad: u32,
+    pub sector: Option<u32>,
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum TouchpadOutput {
+    Raw(TouchpadEvent),
+    Cursor(CursorEvent),
+    Scroll(ScrollEvent),
+    Radial(RadialEvent),
+}
+
+type PadKey = (u32, u32);
+
+/// Turns SDL touchpad samples into raw events plus the derived cursor, scroll
+/// or radial stream configured for each pad.
+pub struct TouchpadProcessor {
+    config: TouchpadConfig,
+    /// Last position per (controller, touchpad, finger).
+    fingers: HashMap<(u32, u32, u32), (f32, f32)>,
+    cursor: (f32, f32),
+    scroll: HashMap<PadKey, (f32, f32)>,
+    radial: HashMap<PadKey, Option<u32>>,
+}
+
+impl Default for TouchpadProcessor {
+    fn default() -> Self {
+        Self {
+            config: TouchpadConfig::default(),
+            fingers: HashMap::new(),
+            cursor: (0.5, 0.5),
+            scroll: HashMap::new(),
+            radial: HashMap::new(),
+        }
+    }
+}
+
+impl TouchpadProcessor {
+    pub fn set_config(&mut self, config: TouchpadConfig) {
+        self.config = config;
+        self.scroll.clear();
+        self.radial.clear();
+    }
+
+    pub fn remove_controller(&mut self, controller: u32) {
+        self.fingers.retain(|&(c, _, _), _| c != controller);
+        self.scroll.retain(|&(c, _), _| c != controller);
+        self.radial.retain(|&(c, _), _| c != controller);
+    }
+
+    pub fn touch(&mut self, controller: u32, event: TouchpadEvent) -> Vec<TouchpadOutput> {
+        let pad = (controller, event.touchpad);
+        let finger = (controller, event.touchpad, event.finger);
+        let (x, y) = (event.x, event.y);
+        let phase = event.phase;
+        let mut out = Vec::new();
+
+        let delta = match phase {
+            TouchPhase::Down => {
+                self.fingers.insert(finger, (x, y));
+                (0.0, 0.0)
+            }
+            TouchPhase::Motion => match self.fingers.insert(finger, (x, y)) {
+                Some((px, py)) => (x - px, y - py),
+                None => (0.0, 0.0),
+            },
+            TouchPhase::Up => {
+                self.fingers.remove(&finger);
+                (0.0, 0.0)
+            }
+        };
+
+        let mode = self.config.pads.get(event.touchpad as usize).copied().unwrap_or_default();
+        out.push(TouchpadOutput::Raw(event));
+
+        match mode {
+            TouchpadMode::Raw => {}
+            TouchpadMode::Cursor => {
+                if delta != (0.0, 0.0) {
+                    let s = self.config.cursor_sensitivity;
+                    self.cursor.0 = (self.cursor.0 + delta.0 * s).clamp(0.0, 1.0);
+                    self.cursor.1 = (self.cursor.1 + delta.1 * s).clamp(0.0, 1.0);
+                    out.push(TouchpadOutput::Cursor(CursorEvent {
+                        x: self.cursor.0,
+                        y: self.cursor.1,
+                    }));
+                }
+            }
+            TouchpadMode::Scroll => {
+                if phase == TouchPhase::Up {
+                    self.scroll.remove(&pad);
+                    return out;
+                }
+                let step = self.config.scroll_step.max(0.01);
+                let acc = self.scroll.entry(pad).or_default();
+                acc.0 += delta.0;
+                acc.1 += delta.1;
+                let dx = (acc.0 / step).trunc() as i32;
+                let dy = (acc.1 / step).trunc() as i32;
+                if dx != 0 || dy != 0 {
+                    acc.0 -= dx as f32 * step;
+                    acc.1 -= dy as f32 * step;
+                    out.push(TouchpadOutput::Scroll(ScrollEvent {
+                        touchpad: pad.1,
+                        dx,
+                        dy,
+                    }));
+                }
+            }
+            TouchpadMode::Radial => {
+                let sector = self.sector(x, y);
+                let previous = self.radial.insert(pad, sector).flatten();
+                if phase == TouchPhase::Up {
+                    self.radial.remove(&pad);
+                    out.push(TouchpadOutput::Radial(RadialEvent {
+                        touchpad: pad.1,
+                        sector: sector.or(previous),
+                        selected: true,
+                    }));
+                } else if sector != previous {
+                    out.push(TouchpadOutput::Radial(RadialEvent {
+                        touchpad: pad.1,
+                        sector,
+                        selected: false,
+                    }));
+                }
+            }
+        }
+
+        out
+    }
+
+    fn sector(&self, x: f32, y: f32) -> Option<u32> {
+        let (dx, dy) = (x - 0.5, y - 0.5);
+        if dx.hypot(dy) < self.config.radial_deadzone {
+            return None;
+        }
+        let sectors = self.config.radial_sectors.max(1);
+        // Clockwise from straight up, centred on each sector.
+        let angle = dx.atan2(-dy).rem_euclid(TAU);
+        let width = TAU / sectors as f32;
+        Some(((angle + width / 2.0) / width) as u32 % sectors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(touchpad: u32, phase: TouchPhase, x: f32, y: f32) -> TouchpadEvent {
+        TouchpadEvent { touchpad, finger: 0, phase, x, y, pressure: 1.0 }
+    }
+
+    fn processor(mode: TouchpadMode) -> TouchpadProcessor {
+        let mut touchpads = TouchpadProcessor::default();
+        touchpads.set_config(TouchpadConfig { pads: vec![mode], ..Default::default() });
+        touchpads
+    }
+
+    /// The derived outputs only; every sample also comes back raw.
+    fn derived(touchpads: &mut TouchpadProcessor, event: TouchpadEvent) -> Vec<TouchpadOutput> {
+        let mut out = touchpads.touch(0, event);
+        assert!(matches!(out.remove(0), TouchpadOutput::Raw(_)));
+        out
+    }
+
+    fn cursor(out: &[TouchpadOutput]) -> Option<(f32, f32)> {
+        match out {
+            [TouchpadOutput::Cursor(CursorEvent { x, y })] => {
+                Some(((x * 100.0).round() / 100.0, (y * 100.0).round() / 100.0))
+            }
+            _ => None,
+        }
+    }
+
+    fn scroll(out: &[TouchpadOutput]) -> Option<(i32, i32)> {
+        match out {
+            [TouchpadOutput::Scroll(ScrollEvent { dx, dy, .. })] => Some((*dx, *dy)),
+            _ => None,
+        }
+    }
+
+    fn radial(out: &[TouchpadOutput]) -> Option<(Option<u32>, bool)> {
+        match out {
+            [TouchpadOutput::Radial(RadialEvent { sector, selected, .. })] => Some((*sector, *selected)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn raw_pads_only_pass_samples_through() {
+        let mut touchpads = TouchpadProcessor::default();
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Down, 0.2, 0.2)).is_empty());
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.8, 0.8)).is_empty());
+    }
+
+    #[test]
+    fn cursor_moves_by_scaled_finger_travel_and_stays_on_screen() {
+        let mut touchpads = processor(TouchpadMode::Cursor);
+        touchpads.config.cursor_sensitivity = 2.0;
+        // Putting the finger down doesn't move the cursor
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Down, 0.9, 0.9)).is_empty());
+        let moved = derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.95, 0.8));
+        assert_eq!(cursor(&moved), Some((0.6, 0.3)));
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Up, 0.95, 0.8)).is_empty());
+
+        // Lifting and landing elsewhere is relative, like a laptop trackpad
+        derived(&mut touchpads, sample(0, TouchPhase::Down, 0.1, 0.5));
+        let moved = derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.5, 0.0));
+        assert_eq!(cursor(&moved), Some((1.0, 0.0)));
+    }
+
+    #[test]
+    fn scroll_accumulates_into_whole_steps() {
+        let mut touchpads = processor(TouchpadMode::Scroll);
+        derived(&mut touchpads, sample(0, TouchPhase::Down, 0.5, 0.5));
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.5, 0.56)).is_empty());
+        assert_eq!(scroll(&derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.5, 0.62))), Some((0, 1)));
+        assert_eq!(scroll(&derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.75, 0.38))), Some((2, -2)));
+        // The remainder is dropped when the finger lifts
+        derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.8, 0.38));
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Up, 0.8, 0.38)).is_empty());
+        derived(&mut touchpads, sample(0, TouchPhase::Down, 0.5, 0.5));
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.57, 0.5)).is_empty());
+    }
+
+    #[test]
+    fn radial_selects_the_sector_under_the_finger_on_lift() {
+        let mut touchpads = processor(TouchpadMode::Radial);
+        // The centre is a deadzone
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Down, 0.5, 0.55)).is_empty());
+        // Straight up is sector 0, then clockwise
+        assert_eq!(radial(&derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.5, 0.1))), Some((Some(0), false)));
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.52, 0.1)).is_empty());
+        assert_eq!(radial(&derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.9, 0.5))), Some((Some(2), false)));
+        assert_eq!(radial(&derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.1, 0.5))), Some((Some(6), false)));
+        assert_eq!(radial(&derived(&mut touchpads, sample(0, TouchPhase::Up, 0.1, 0.5))), Some((Some(6), true)));
+    }
+
+    #[test]
+    fn radial_lift_in_the_centre_keeps_the_last_sector() {
+        let mut touchpads = processor(TouchpadMode::Radial);
+        derived(&mut touchpads, sample(0, TouchPhase::Down, 0.5, 0.9));
+        assert_eq!(radial(&derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.5, 0.5))), Some((None, false)));
+        // Only the sector seen on this move counts, not earlier ones
+        assert_eq!(radial(&derived(&mut touchpads, sample(0, TouchPhase::Up, 0.5, 0.5))), Some((None, true)));
+
+        derived(&mut touchpads, sample(0, TouchPhase::Down, 0.5, 0.9));
+        assert_eq!(radial(&derived(&mut touchpads, sample(0, TouchPhase::Up, 0.5, 0.5))), Some((Some(4), true)));
+    }
+
+    #[test]
+    fn modes_are_chosen_per_pad() {
+        let mut touchpads = TouchpadProcessor::default();
+        touchpads.set_config(TouchpadConfig {
+            pads: vec![TouchpadMode::Raw, TouchpadMode::Scroll],
+            ..Default::default()
+        });
+        derived(&mut touchpads, sample(0, TouchPhase::Down, 0.5, 0.5));
+        assert!(derived(&mut touchpads, sample(0, TouchPhase::Motion, 0.5, 0.9)).is_empty());
+        derived(&mut touchpads, sample(1, TouchPhase::Down, 0.5, 0.5));
+        assert_eq!(scroll(&derived(&mut touchpads, sample(1, TouchPhase::Motion, 0.5, 0.95))), Some((0, 4)));
+    }
+}
@@ -0,0 +1,409 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme, StreamOwned};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransportError {
+    Io(String),
+    /// The server's certificate isn't the one pinned for it.
+    CertificateChanged { expected: String, found: String },
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransportError::Io(error) => write!(f, "{error}"),
+            TransportError::CertificateChanged { .. } => write!(f, "The server's certificate has changed"),
+        }
+    }
+}
+
+impl From<io::Error> for TransportError {
+    fn from(error: io::Error) -> Self {
+        TransportError::Io(error.to_string())
+    }
+}
+
+/// SHA-256 of a DER certificate as colon-separated upper-case hex, the way
+/// `openssl x509 -fingerprint -sha256` and the server's log print it.
+pub fn fingerprint(certificate: &[u8]) -> String {
+    let digest = Sha256::digest(certificate);
+    digest.iter().map(|b| format!("{b:02X}")).collect::<Vec<_>>().join(":")
+}
+
+/// Where a server URL points: `http`/`ws` in the clear, `https`/`wss` over
+/// TLS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub tls: bool,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Endpoint {
+    pub fn parse(url: &str) -> Result<Self, TransportError> {
+        let invalid = || TransportError::Io(format!("invalid server URL: {url}"));
+        let (scheme, rest) = url.trim().split_once("://").ok_or_else(invalid)?;
+        let tls = match scheme {
+            "http" | "ws" => false,
+            "https" | "wss" => true,
+            _ => return Err(invalid()),
+        };
+        let authority = rest.split(['/', '?']).next().unwrap_or_default();
+        let default_port = if tls { 443 } else { 80 };
+        let (host, port) = match authority.rsplit_once(':') {
+            // A bare IPv6 address has colons but no port
+            Some((host, port)) if !port.contains(']') => (host, port.parse().map_err(|_| invalid())?),
+            _ => (authority, default_port),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self { tls, host: host.to_string(), port })
+    }
+}
+
+/// A connection to the server, in the clear or over TLS.
+pub enum Stream {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Stream {
+    pub fn tcp(&self) -> &TcpStream {
+        match self {
+            Stream::Plain(stream) => stream,
+            Stream::Tls(stream) => &stream.sock,
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.read(buf),
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buf),
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+pub struct Connected {
+    pub stream: Stream,
+    /// Fingerprint of the server's certificate, for TLS connections.
+    pub fingerprint: Option<String>,
+}
+
+/// Opens a connection to `endpoint`. Over TLS the server's certificate is
+/// accepted if its fingerprint is `pin`, or whatever it is when there's no
+/// pin yet, so the caller can show it and pin it (trust on first use). It is
+/// self-signed, so there's no CA or host name to check.
+pub fn connect(endpoint: &Endpoint, pin: Option<&str>, timeout: Duration) -> Result<Connected, TransportError> {
+    let address = (endpoint.host.as_str(), endpoint.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| TransportError::Io(format!("host not found: {}", endpoint.host)))?;
+    let mut tcp = TcpStream::connect_timeout(&address, timeout)?;
+    tcp.set_read_timeout(Some(timeout))?;
+    tcp.set_write_timeout(Some(timeout))?;
+    let _ = tcp.set_nodelay(true);
+    if !endpoint.tls {
+        return Ok(Connected { stream: Stream::Plain(tcp), fingerprint: None });
+    }
+
+    let verifier = Arc::new(PinnedCertificate::new(pin));
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| TransportError::Io(e.to_string()))?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    let name = ServerName::try_from(endpoint.host.clone()).map_err(|e| TransportError::Io(e.to_string()))?;
+    let mut tls = ClientConnection::new(Arc::new(config), name).map_err(|e| TransportError::Io(e.to_string()))?;
+    while tls.is_handshaking() {
+        if let Err(error) = tls.complete_io(&mut tcp) {
+            return Err(match (pin, verifier.seen()) {
+                (Some(expected), Some(found)) if expected != found => TransportError::CertificateChanged {
+                    expected: expected.to_string(),
+                    found,
+                },
+                _ => error.into(),
+            });
+        }
+    }
+    Ok(Connected {
+        fingerprint: verifier.seen(),
+        stream: Stream::Tls(Box::new(StreamOwned::new(tls, tcp))),
+    })
+}
+
+/// Accepts the one certificate whose fingerprint is pinned, or any when
+/// nothing is pinned yet. Handshake signatures are still checked, so the
+/// server has to hold the certificate's key.
+#[derive(Debug)]
+struct PinnedCertificate {
+    pin: Option<String>,
+    seen: Mutex<Option<String>>,
+    provider: CryptoProvider,
+}
+
+impl PinnedCertificate {
+    fn new(pin: Option<&str>) -> Self {
+        Self {
+            pin: pin.map(str::to_string),
+            seen: Mutex::new(None),
+            provider: rustls::crypto::ring::default_provider(),
+        }
+    }
+
+    fn seen(&self) -> Option<String> {
+        self.seen.lock().ok()?.clone()
+    }
+}
+
+impl ServerCertVerifier for PinnedCertificate {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let found = fingerprint(end_entity);
+        if let Ok(mut seen) = self.seen.lock() {
+            *seen = Some(found.clone());
+        }
+        match &self.pin {
+            Some(pin) if *pin != found => Err(rustls::Error::General("certificate fingerprint mismatch".into())),
+            _ => Ok(ServerCertVerified::assertion()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+    /// Fingerprint of the server's certificate, for `https` URLs.
+    pub fingerprint: Option<String>,
+}
+
+/// Sends one HTTP/1.1 request to `url` with an optional body, given as its
+/// content type and bytes. Only what the server's REST API needs: no
+/// redirects, one request per connection.
+pub fn http_request(
+    method: &str,
+    url: &str,
+    authorization: Option<&str>,
+    body: Option<(&str, &[u8])>,
+    pin: Option<&str>,
+    timeout: Duration,
+) -> Result<HttpResponse, TransportError> {
+    let endpoint = Endpoint::parse(url)?;
+    let path = url
+        .split_once("://")
+        .and_then(|(_, rest)| rest.find('/').map(|i| &rest[i..]))
+        .unwrap_or("/");
+    let Connected { mut stream, fingerprint, .. } = connect(&endpoint, pin, timeout)?;
+
+    let mut request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {}:{}\r\nAccept: */*\r\nConnection: close\r\n",
+        endpoint.host, endpoint.port
+    );
+    if let Some(authorization) = authorization {
+        request += &format!("Authorization: {authorization}\r\n");
+    }
+    if let Some((content_type, bytes)) = body {
+        request += &format!("Content-Type: {content_type}\r\nContent-Length: {}\r\n", bytes.len());
+    }
+    request += "\r\n";
+    stream.write_all(request.as_bytes())?;
+    if let Some((_, bytes)) = body {
+        stream.write_all(bytes)?;
+    }
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| TransportError::Io(format!("bad HTTP response: {}", status_line.trim())))?;
+
+    let mut length = None;
+    let mut chunked = false;
+    let mut content_type = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else { continue };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            length = value.parse().ok();
+        } else if name.eq_ignore_ascii_case("transfer-encoding") && value.eq_ignore_ascii_case("chunked") {
+            chunked = true;
+        } else if name.eq_ignore_ascii_case("content-type") {
+            content_type = Some(value.to_string());
+        }
+    }
+
+    let body = if chunked {
+        read_chunked(&mut reader)?
+    } else if let Some(length) = length {
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        body
+    } else {
+        let mut body = Vec::new();
+        match reader.read_to_end(&mut body) {
+            // Servers often drop TLS without a close_notify
+            Err(e) if e.kind() != io::ErrorKind::UnexpectedEof => return Err(e.into()),
+            _ => body,
+        }
+    };
+    Ok(HttpResponse { status, content_type, body, fingerprint })
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut size = String::new();
+        reader.read_line(&mut size)?;
+        let size = usize::from_str_radix(size.trim().split(';').next().unwrap_or_default(), 16)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        let mut end = String::new();
+        reader.read_line(&mut end)?;
+        if size == 0 {
+            return Ok(body);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::{ServerConfig, ServerConnection};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn endpoints_parse() {
+        let endpoint = |tls, host: &str, port| Endpoint { tls, host: host.into(), port };
+        assert_eq!(Endpoint::parse("http://192.168.1.5:9900").unwrap(), endpoint(false, "192.168.1.5", 9900));
+        assert_eq!(Endpoint::parse("wss://deck.local/ws").unwrap(), endpoint(true, "deck.local", 443));
+        assert_eq!(Endpoint::parse("https://[::1]:9443/api").unwrap(), endpoint(true, "::1", 9443));
+        assert!(Endpoint::parse("ftp://deck.local").is_err());
+    }
+
+    /// A TLS server with a fresh self-signed certificate that answers one
+    /// HTTP request per connection. Returns its URL and certificate
+    /// fingerprint.
+    fn tls_server(connections: usize) -> (String, String) {
+        let certified = rcgen::generate_simple_self_signed(vec!["DeckPilot".into()]).unwrap();
+        let der = certified.cert.der().clone();
+        let pinned = fingerprint(&der);
+        let key = PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into());
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![der], key)
+            .unwrap();
+        let config = Arc::new(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("https://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for _ in 0..connections {
+                let (tcp, _) = listener.accept().unwrap();
+                let tls = ServerConnection::new(config.clone()).unwrap();
+                let mut stream = StreamOwned::new(tls, tcp);
+                let mut request = [0u8; 1024];
+                if stream.read(&mut request).is_err() {
+                    continue;
+                }
+                let body = r#"{"status":"ok"}"#;
+                let reply = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}", body.len());
+                let _ = stream.write_all(reply.as_bytes());
+                let _ = stream.flush();
+            }
+        });
+        (url, pinned)
+    }
+
+    #[test]
+    fn certificates_are_trusted_on_first_use_then_pinned() {
+        let (url, pinned) = tls_server(3);
+        let health = format!("{url}/api/health");
+        let timeout = Duration::from_secs(5);
+
+        // First contact: nothing pinned, the fingerprint is reported
+        let first = http_request("GET", &health, None, None, None, timeout).unwrap();
+        assert_eq!((first.status, first.fingerprint.as_deref()), (200, Some(pinned.as_str())));
+        assert_eq!(first.body, br#"{"status":"ok"}"#);
+
+        let pinned_request = http_request("GET", &health, None, None, Some(&pinned), timeout).unwrap();
+        assert_eq!(pinned_request.status, 200);
+
+        let other = "AA:".repeat(31) + "AA";
+        let changed = http_request("GET", &health, None, None, Some(&other), timeout).unwrap_err();
+        assert_eq!(changed, TransportError::CertificateChanged { expected: other, found: pinned });
+    }
+}
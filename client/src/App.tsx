@@ -1,7 +1,7 @@
 import { useCallback, useEffect, useState } from "react";
 import { isTauri } from "./lib/platform";
 import { getServerUrl, setServerUrl } from "./lib/serverUrl";
-import { setApiBase } from "./lib/api";
+import { apiFetch, setApiBase } from "./lib/api";
 import { checkForUpdates } from "./lib/updater";
 import { ConnectionScreen } from "./components/ConnectionScreen";
 import { AppMain } from "./AppMain";
@@ -9,6 +9,8 @@ import { AppMain } from "./AppMain";
 export default function App() {
   const [serverUrl, setServerUrlState] = useState<string | null>(null);
   const [loading, setLoading] = useState(true);
+  // Stored server that turned us away: pair again from the connection screen
+  const [unpairedUrl, setUnpairedUrl] = useState<string | null>(null);
 
   // Check for updates on mount (Tauri only, no-op in browser)
   useEffect(() => {
@@ -16,12 +18,20 @@ export default function App() {
   }, []);
 
   useEffect(() => {
-    getServerUrl().then((url) => {
+    getServerUrl().then(async (url) => {
       if (url) {
         // In browser mode, API base stays "" (same-origin via proxy)
         // In Tauri mode, set the full server URL as base
         if (isTauri()) {
           setApiBase(url);
+          const resp = await apiFetch("/api/config", {
+            signal: AbortSignal.timeout(5000),
+          }).catch(() => null);
+          if (resp?.status === 401) {
+            setUnpairedUrl(url);
+            setLoading(false);
+            return;
+          }
         }
         setServerUrlState(url);
       }
@@ -32,6 +42,7 @@ export default function App() {
   const handleConnect = useCallback(async (url: string) => {
     await setServerUrl(url);
     setApiBase(url);
+    setUnpairedUrl(null);
     setServerUrlState(url);
   }, []);
 
@@ -39,7 +50,7 @@ export default function App() {
 
   // In Tauri, show connection screen if no server URL configured
   if (isTauri() && !serverUrl) {
-    return <ConnectionScreen onConnect={handleConnect} />;
+    return <ConnectionScreen onConnect={handleConnect} initialUrl={unpairedUrl ?? undefined} />;
   }
 
   // Pass serverUrl only in Tauri mode (browser uses same-origin)
@@ -1,16 +1,22 @@
-import { useCallback, useEffect, useState } from "react";
+import { useCallback, useEffect, useRef, useState } from "react";
 import { useWebSocket } from "./hooks/useWebSocket";
 import { useConfig } from "./hooks/useConfig";
 import { useGamepad } from "./hooks/useGamepad";
 import { useLiveData } from "./hooks/useLiveData";
+import { useControllerBattery } from "./hooks/useControllerBattery";
+import { useConnectionStatus } from "./hooks/useConnectionStatus";
+import { useControllerLed } from "./hooks/useControllerLed";
 import { useEditMode } from "./hooks/useEditMode";
 import { usePageNavigation } from "./hooks/usePageNavigation";
 import { useGestures } from "./hooks/useGestures";
 import { usePageTransition } from "./hooks/usePageTransition";
 import { useToast } from "./hooks/useToast";
 import { copyToClipboard } from "./lib/clipboard";
-import { apiUrl } from "./lib/api";
-import { triggerHaptic } from "./lib/haptics";
+import { apiFetch } from "./lib/api";
+import { playHaptic, triggerHaptic } from "./lib/haptics";
+import { findGamepadBinding } from "./lib/gamepad";
+import { connectNativeServer } from "./lib/serverUrl";
+import { confirmCertificateChange } from "./lib/pairing";
 import { StatusBar } from "./components/StatusBar";
 import { WidgetGrid } from "./components/WidgetGrid";
 import { GamepadIndicator } from "./components/GamepadIndicator";
@@ -30,8 +36,42 @@ export function AppMain({ serverUrl }: AppMainProps) {
   const { connected, lastMessage, send } = useWebSocket(serverUrl);
   const { config, setConfig, grid, pages, pageGridSize } = useConfig(lastMessage);
   const liveData = useLiveData(lastMessage);
+  useControllerLed(liveData);
   const [lastGamepadButton, setLastGamepadButton] = useState<number | null>(null);
+  // Set once the app's own connection sends gamepad presses to the server
+  const [nativeServer, setNativeServer] = useState(false);
+
+  useEffect(() => {
+    if (!serverUrl) return;
+    let cancelled = false;
+    connectNativeServer(serverUrl).then((ok) => {
+      if (!cancelled) setNativeServer(ok);
+    });
+    return () => {
+      cancelled = true;
+    };
+  }, [serverUrl]);
+  const nativeStatus = useConnectionStatus();
+  // Ask once per certificate the server changed to
+  const askedCertificate = useRef<string | null>(null);
+
+  useEffect(() => {
+    const change = nativeStatus?.certificate;
+    if (!change || askedCertificate.current === change.found) return;
+    askedCertificate.current = change.found;
+    confirmCertificateChange(change);
+  }, [nativeStatus?.certificate]);
   const { toasts, showToast, dismissToast } = useToast();
+  const controllers = useControllerBattery((pad) =>
+    showToast(`${pad.name} battery is ${pad.battery.level === "empty" ? "empty" : "low"}`, "error")
+  );
+
+  // Let the controller confirm whether the server ran the action
+  useEffect(() => {
+    if (lastMessage?.type === "action_result") {
+      playHaptic(lastMessage.payload.success ? "confirm" : "error");
+    }
+  }, [lastMessage]);
 
   // 2D page navigation
   const nav = usePageNavigation({ pages, gridSize: pageGridSize });
@@ -118,20 +158,24 @@ export function AppMain({ serverUrl }: AppMainProps) {
   const handleSliderChange = useCallback(
     (widgetId: string, value: number) => {
       if (!displayPage) return;
+      // Slider drags fire rapidly; skip ticks while one is still playing
+      playHaptic("tick", { policy: "drop_if_busy" });
       send({ type: "slider_change", widgetId, page: displayPage.id, value });
     },
     [displayPage, send]
   );
 
   const handleGamepadButton = useCallback(
-    (button: number) => {
+    (button: number, controller?: string) => {
       triggerHaptic(0.2, 40);
       setLastGamepadButton(button);
       setTimeout(() => setLastGamepadButton(null), 1500);
 
-      const binding = config?.gamepadBindings.find((b) => b.button === button);
+      const binding = config ? findGamepadBinding(config.gamepadBindings, button, controller) : undefined;
+      // The native connection forwards presses itself; sending them here too
+      // would run server bindings twice
       if (!binding) {
-        send({ type: "gamepad_button", button });
+        if (!nativeServer) send({ type: "gamepad_button", button, controller });
         return;
       }
 
@@ -153,11 +197,11 @@ export function AppMain({ serverUrl }: AppMainProps) {
             nav.toggleOverview();
             break;
         }
-      } else {
-        send({ type: "gamepad_button", button });
+      } else if (!nativeServer) {
+        send({ type: "gamepad_button", button, controller });
       }
     },
-    [config, send, nav]
+    [config, send, nav, nativeServer]
   );
 
   useGamepad(handleGamepadButton);
@@ -168,7 +212,7 @@ export function AppMain({ serverUrl }: AppMainProps) {
       if (!config) return;
       const newConfig = structuredClone(config);
       newConfig.activeProfile = profileId;
-      fetch(apiUrl("/api/config"), {
+      apiFetch("/api/config", {
         method: "PUT",
         headers: { "Content-Type": "application/json" },
         body: JSON.stringify(newConfig),
@@ -257,6 +301,8 @@ export function AppMain({ serverUrl }: AppMainProps) {
           profiles={config?.profiles}
           activeProfileId={config?.activeProfile}
           onSwitchProfile={handleSwitchProfile}
+          controllers={controllers}
+          nativeStatus={nativeStatus}
         />
       )}
 
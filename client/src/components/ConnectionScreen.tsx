@@ -1,16 +1,37 @@
-import { useState } from "react";
+import { useCallback, useEffect, useState } from "react";
+import { isTauri } from "../lib/platform";
+import { authToken } from "../lib/api";
+import { confirmPairing, getPairingStatus, requestPairing } from "../lib/pairing";
+import { discoverServers, type DiscoveredServer } from "../lib/serverUrl";
 
 interface ConnectionScreenProps {
   onConnect: (url: string) => void;
+  /** Server to pre-fill, e.g. one that no longer accepts this device. */
+  initialUrl?: string;
 }
 
-export function ConnectionScreen({ onConnect }: ConnectionScreenProps) {
-  const [url, setUrl] = useState("http://");
+export function ConnectionScreen({ onConnect, initialUrl }: ConnectionScreenProps) {
+  const [url, setUrl] = useState(initialUrl ?? "http://");
   const [testing, setTesting] = useState(false);
   const [error, setError] = useState<string | null>(null);
+  const [servers, setServers] = useState<DiscoveredServer[]>([]);
+  const [searching, setSearching] = useState(false);
+  // Set while waiting for the PIN the server shows
+  const [pairing, setPairing] = useState<{ url: string; requestId: string; fingerprint: string | null } | null>(null);
+  const [pin, setPin] = useState("");
 
-  const handleConnect = async () => {
-    const trimmed = url.replace(/\/$/, "");
+  const search = useCallback(async () => {
+    setSearching(true);
+    setServers(await discoverServers());
+    setSearching(false);
+  }, []);
+
+  useEffect(() => {
+    if (isTauri()) search();
+  }, [search]);
+
+  const handleConnect = async (target = url) => {
+    const trimmed = target.replace(/\/$/, "");
     if (!trimmed || trimmed === "http://" || trimmed === "https://") {
       setError("Enter a server URL");
       return;
@@ -19,21 +40,118 @@ export function ConnectionScreen({ onConnect }: ConnectionScreenProps) {
     setTesting(true);
     setError(null);
 
+    const startPairing = async () => {
+      const request = await requestPairing(trimmed);
+      setPin("");
+      setPairing({ url: trimmed, requestId: request.requestId, fingerprint: request.fingerprint });
+    };
+
     try {
+      if (isTauri() && trimmed.startsWith("https://")) {
+        // The webview won't trust a self-signed certificate; the native
+        // connection pins it when pairing instead
+        const status = await getPairingStatus();
+        const paired = status?.server === trimmed.replace(/\/+$/, "") && status.fingerprint;
+        if (paired) onConnect(trimmed);
+        else await startPairing();
+        return;
+      }
+
+      // Already paired with this server: prove it
+      const token = await authToken("GET", "/api/config", trimmed);
       const resp = await fetch(`${trimmed}/api/config`, {
         signal: AbortSignal.timeout(5000),
+        headers: token ? { Authorization: `Bearer ${token}` } : undefined,
       });
+      if (resp.status === 401 && isTauri()) {
+        await startPairing();
+        return;
+      }
       if (!resp.ok) throw new Error(`HTTP ${resp.status}`);
       onConnect(trimmed);
     } catch (e) {
       setError(
-        `Can't reach server: ${e instanceof Error ? e.message : "Unknown error"}`
+        `Can't reach server: ${typeof e === "string" ? e : e instanceof Error ? e.message : "Unknown error"}`
       );
     } finally {
       setTesting(false);
     }
   };
 
+  const handlePair = async () => {
+    if (!pairing) return;
+    setTesting(true);
+    setError(null);
+    try {
+      await confirmPairing(pairing.url, pairing.requestId, pin, pairing.fingerprint);
+      onConnect(pairing.url);
+    } catch (e) {
+      setError(typeof e === "string" ? e : e instanceof Error ? e.message : "Pairing failed");
+    } finally {
+      setTesting(false);
+    }
+  };
+
+  if (pairing) {
+    return (
+      <div className="flex flex-col items-center justify-center h-full gap-6 p-8">
+        <div className="flex flex-col items-center gap-2">
+          <h1 className="text-2xl font-bold text-[var(--text-primary)]">
+            Pair with server
+          </h1>
+          <p className="text-sm text-[var(--text-secondary)] text-center">
+            Enter the PIN shown on the computer running DeckPilot
+          </p>
+          {pairing.fingerprint && (
+            <p className="text-xs text-[var(--text-secondary)] text-center">
+              Check it also shows this certificate:
+              <span className="block mt-1 font-mono break-all text-[var(--text-primary)]">
+                {pairing.fingerprint}
+              </span>
+            </p>
+          )}
+        </div>
+
+        <div className="flex flex-col gap-3 w-full max-w-sm">
+          <input
+            value={pin}
+            onChange={(e) => setPin(e.target.value)}
+            onKeyDown={(e) => e.key === "Enter" && handlePair()}
+            inputMode="numeric"
+            placeholder="123456"
+            className="w-full px-4 py-3 rounded-xl bg-[var(--bg-button)] text-[var(--text-primary)] text-center text-lg tracking-widest border border-[var(--bg-button)] focus:border-[var(--accent)] outline-none"
+            autoFocus
+          />
+
+          {error && (
+            <p className="text-xs text-red-400 text-center">{error}</p>
+          )}
+
+          <button
+            onClick={handlePair}
+            disabled={testing}
+            className="w-full py-3 rounded-xl text-sm font-semibold text-white transition-opacity"
+            style={{
+              backgroundColor: "var(--accent)",
+              opacity: testing ? 0.6 : 1,
+            }}
+          >
+            {testing ? "Pairing..." : "Pair"}
+          </button>
+          <button
+            onClick={() => {
+              setPairing(null);
+              setError(null);
+            }}
+            className="text-xs text-[var(--text-secondary)]"
+          >
+            Cancel
+          </button>
+        </div>
+      </div>
+    );
+  }
+
   return (
     <div className="flex flex-col items-center justify-center h-full gap-6 p-8">
       <div className="flex flex-col items-center gap-2">
@@ -61,7 +179,7 @@ export function ConnectionScreen({ onConnect }: ConnectionScreenProps) {
         )}
 
         <button
-          onClick={handleConnect}
+          onClick={() => handleConnect()}
           disabled={testing}
           className="w-full py-3 rounded-xl text-sm font-semibold text-white transition-opacity"
           style={{
@@ -73,6 +191,48 @@ export function ConnectionScreen({ onConnect }: ConnectionScreenProps) {
         </button>
       </div>
 
+      {isTauri() && (
+        <div className="flex flex-col gap-2 w-full max-w-sm">
+          <div className="flex items-center justify-between">
+            <span className="text-xs text-[var(--text-secondary)]">
+              {searching ? "Looking for servers..." : "Servers on this network"}
+            </span>
+            <button
+              onClick={search}
+              disabled={searching}
+              className="text-xs font-medium text-[var(--accent)] disabled:opacity-50"
+            >
+              Search again
+            </button>
+          </div>
+          {servers.map((server) => {
+            const serverUrl = `${server.tls ? "https" : "http"}://${server.host}:${server.port}`;
+            return (
+              <button
+                key={serverUrl}
+                onClick={() => {
+                  setUrl(serverUrl);
+                  handleConnect(serverUrl);
+                }}
+                disabled={testing}
+                className="flex items-center justify-between w-full px-4 py-3 rounded-xl bg-[var(--bg-button)] hover:bg-[var(--bg-button-hover)] text-left transition-colors"
+              >
+                <span className="text-sm text-[var(--text-primary)] truncate">{server.name}</span>
+                <span className="text-xs text-[var(--text-secondary)] shrink-0 ml-2">
+                  {server.host}:{server.port}
+                  {server.version && ` · v${server.version}`}
+                </span>
+              </button>
+            );
+          })}
+          {!searching && servers.length === 0 && (
+            <p className="text-xs text-[var(--text-secondary)] opacity-50 text-center">
+              None found
+            </p>
+          )}
+        </div>
+      )}
+
       <p className="text-xs text-[var(--text-secondary)] opacity-50 text-center mt-4">
         Make sure the DeckPilot server is running on your desktop
       </p>
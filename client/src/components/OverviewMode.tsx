@@ -1,7 +1,7 @@
 import { useCallback, useEffect, useRef, useState } from "react";
 import { animate } from "animejs";
 import type { DeckPilotConfig, PageConfig } from "shared";
-import { apiUrl } from "../lib/api";
+import { apiFetch } from "../lib/api";
 
 interface OverviewModeProps {
   open: boolean;
@@ -143,7 +143,7 @@ export function OverviewMode({
 
   const saveConfig = useCallback(
     (newConfig: DeckPilotConfig) => {
-      fetch(apiUrl("/api/config"), {
+      apiFetch("/api/config", {
         method: "PUT",
         headers: { "Content-Type": "application/json" },
         body: JSON.stringify(newConfig),
@@ -1,6 +1,8 @@
 import { useCallback, useEffect, useState } from "react";
 import type { PageConfig, ProfileConfig } from "shared";
+import type { ControllerInfo } from "../lib/gamepad";
 import { isTauri } from "../lib/platform";
+import type { NativeConnectionStatus } from "../lib/serverUrl";
 
 interface StatusBarProps {
   connected: boolean;
@@ -12,6 +14,10 @@ interface StatusBarProps {
   profiles?: ProfileConfig[];
   activeProfileId?: string;
   onSwitchProfile?: (profileId: string) => void;
+  /** Connected controllers; wireless ones get a battery indicator. */
+  controllers?: ControllerInfo[];
+  /** The app's own server connection, which carries gamepad presses. */
+  nativeStatus?: NativeConnectionStatus | null;
 }
 
 export function StatusBar({
@@ -24,6 +30,8 @@ export function StatusBar({
   profiles,
   activeProfileId,
   onSwitchProfile,
+  controllers,
+  nativeStatus,
 }: StatusBarProps) {
   const [isFullscreen, setIsFullscreen] = useState(!!document.fullscreenElement);
   const [showProfileMenu, setShowProfileMenu] = useState(false);
@@ -43,6 +51,7 @@ export function StatusBar({
   }, []);
 
   const activeProfile = profiles?.find((p) => p.id === activeProfileId);
+  const reconnecting = nativeStatus?.state === "connecting" || nativeStatus?.state === "disconnected";
 
   // Build 2D dot grid
   const dots: React.ReactNode[] = [];
@@ -156,6 +165,11 @@ export function StatusBar({
             App
           </a>
         )}
+        {controllers
+          ?.filter((c) => !c.battery.wired && c.battery.level)
+          .map((c) => (
+            <BatteryIndicator key={c.id} controller={c} />
+          ))}
         <IconButton
           onClick={toggleFullscreen}
           label={isFullscreen ? "Exit fullscreen" : "Enter fullscreen"}
@@ -168,9 +182,13 @@ export function StatusBar({
         <div
           className="w-2 h-2 rounded-full shrink-0"
           style={{
-            backgroundColor: connected ? "var(--success)" : "var(--danger)",
+            backgroundColor: !connected
+              ? "var(--danger)"
+              : reconnecting
+                ? "var(--warning)"
+                : "var(--success)",
           }}
-          title={connected ? "Connected" : "Disconnected"}
+          title={connectionTitle(connected, nativeStatus)}
         />
       </div>
     </div>
@@ -189,6 +207,33 @@ function IconButton({ onClick, label, children }: { onClick: () => void; label:
   );
 }
 
+function connectionTitle(connected: boolean, native?: NativeConnectionStatus | null) {
+  if (!connected) return "Disconnected";
+  if (!native || native.state === "connected" || native.state === "idle") return "Connected";
+  const queued = native.queued > 0 ? `, ${native.queued} gamepad press${native.queued === 1 ? "" : "es"} queued` : "";
+  return `Gamepad link reconnecting${queued}`;
+}
+
+const BATTERY_FILL = { empty: 0.1, low: 0.3, medium: 0.6, full: 1 } as const;
+
+function BatteryIndicator({ controller }: { controller: ControllerInfo }) {
+  const level = controller.battery.level ?? "full";
+  const low = level === "empty" || level === "low";
+  return (
+    <div
+      className="flex items-center"
+      style={{ color: low ? "var(--danger)" : "var(--text-secondary)" }}
+      title={`${controller.name}: battery ${level}`}
+    >
+      <svg width="16" height="10" viewBox="0 0 16 10" fill="none">
+        <rect x="0.5" y="0.5" width="13" height="9" rx="1.5" stroke="currentColor" />
+        <rect x="14" y="3" width="1.5" height="4" rx="0.5" fill="currentColor" />
+        <rect x="2" y="2" width={10 * BATTERY_FILL[level]} height="6" rx="0.5" fill="currentColor" />
+      </svg>
+    </div>
+  );
+}
+
 function FullscreenIcon() {
   return (
     <svg width="13" height="13" viewBox="0 0 24 24" fill="currentColor">
@@ -1,6 +1,6 @@
 import { useState, useEffect } from "react";
 import type { Action, ActionType } from "shared";
-import { apiUrl } from "../../lib/api";
+import { apiFetch } from "../../lib/api";
 
 interface ActionPickerProps {
   value?: Action;
@@ -93,7 +93,7 @@ function SoundPicker({
   const selected = (params.soundId as string) ?? "";
 
   useEffect(() => {
-    fetch(apiUrl("/api/sounds"))
+    apiFetch("/api/sounds")
       .then((r) => (r.ok ? r.json() : []))
       .then(setSounds)
       .catch(() => {});
@@ -1,6 +1,7 @@
 import { useEffect, useRef, useState } from "react";
 import type { GamepadBinding, ClientActionType, Action } from "shared";
-import { GAMEPAD_LABELS } from "../../lib/gamepad";
+import { GAMEPAD_LABELS, type ControllerInfo } from "../../lib/gamepad";
+import { isTauri } from "../../lib/platform";
 import { BottomSheet } from "./BottomSheet";
 import { ActionPicker } from "./ActionPicker";
 
@@ -28,6 +29,15 @@ export function GamepadBindingEditor({
 }: GamepadBindingEditorProps) {
   const [capturing, setCapturing] = useState(false);
   const [editingIndex, setEditingIndex] = useState<number | null>(null);
+  const [controllers, setControllers] = useState<ControllerInfo[]>([]);
+
+  useEffect(() => {
+    if (!open || !isTauri()) return;
+    import("@tauri-apps/api/core")
+      .then(({ invoke }) => invoke<ControllerInfo[]>("list_controllers"))
+      .then(setControllers)
+      .catch(() => setControllers([]));
+  }, [open]);
 
   return (
     <BottomSheet open={open} onClose={onClose} title="Gamepad Bindings">
@@ -36,6 +46,7 @@ export function GamepadBindingEditor({
           <BindingRow
             key={index}
             binding={binding}
+            controllers={controllers}
             expanded={editingIndex === index}
             onToggle={() =>
               setEditingIndex(editingIndex === index ? null : index)
@@ -83,12 +94,14 @@ export function GamepadBindingEditor({
 
 function BindingRow({
   binding,
+  controllers,
   expanded,
   onToggle,
   onChange,
   onDelete,
 }: {
   binding: GamepadBinding;
+  controllers: ControllerInfo[];
   expanded: boolean;
   onToggle: () => void;
   onChange: (binding: GamepadBinding) => void;
@@ -157,6 +170,38 @@ function BindingRow({
             />
           </label>
 
+          {/* Controller filter (native backend only) */}
+          {(controllers.length > 0 || binding.controller) && (
+            <label className="flex items-center gap-2 text-sm">
+              <span className="text-[var(--text-secondary)] shrink-0">Controller:</span>
+              <select
+                value={binding.controller ?? ""}
+                onChange={(e) => {
+                  const match = controllers.find((c) => c.guid === e.target.value);
+                  onChange({
+                    ...binding,
+                    controller: e.target.value || undefined,
+                    controllerName: match?.name,
+                  });
+                }}
+                className="flex-1 px-2 py-1 rounded bg-[var(--bg-secondary)] text-[var(--text-primary)] text-sm border border-[var(--bg-button)]"
+              >
+                <option value="">Any</option>
+                {controllers.map((c) => (
+                  <option key={c.id} value={c.guid}>
+                    {c.name}
+                  </option>
+                ))}
+                {binding.controller &&
+                  !controllers.some((c) => c.guid === binding.controller) && (
+                    <option value={binding.controller}>
+                      {binding.controllerName ?? binding.controller} (disconnected)
+                    </option>
+                  )}
+              </select>
+            </label>
+          )}
+
           {/* Kind toggle */}
           <div className="flex items-center gap-2">
             <span className="text-sm text-[var(--text-secondary)]">Type:</span>
@@ -235,6 +280,16 @@ function BindingRow({
                 value={binding.action}
                 onChange={(action: Action) => onChange({ ...binding, action })}
               />
+              <label className="flex items-center gap-2 text-xs text-[var(--text-secondary)] mt-1">
+                <input
+                  type="checkbox"
+                  checked={binding.replayIfLate ?? false}
+                  onChange={(e) =>
+                    onChange({ ...binding, replayIfLate: e.target.checked || undefined })
+                  }
+                />
+                Run even if pressed while offline
+              </label>
             </div>
           )}
 
@@ -261,6 +316,27 @@ function CaptureOverlay({
   const prevPressed = useRef<Set<number>>(new Set());
 
   useEffect(() => {
+    if (isTauri()) {
+      // Tauri mode: the SDL2 backend reports extended buttons (paddles, QAM)
+      // that the browser Gamepad API never sees
+      let unlisten: (() => void) | undefined;
+      let cancelled = false;
+
+      import("@tauri-apps/api/event").then(({ listen }) => {
+        listen<{ button: number }>("gamepad_button", (event) => {
+          onCapture(event.payload.button);
+        }).then((fn) => {
+          if (cancelled) fn();
+          else unlisten = fn;
+        });
+      });
+
+      return () => {
+        cancelled = true;
+        unlisten?.();
+      };
+    }
+
     let rafId: number;
 
     const poll = () => {
@@ -3,7 +3,7 @@ import type { WidgetConfig, WidgetType, Action } from "shared";
 import { BottomSheet } from "./BottomSheet";
 import { ActionPicker } from "./ActionPicker";
 import { renderIcon, getAvailableIcons } from "../../lib/icons";
-import { apiUrl } from "../../lib/api";
+import { apiFetch } from "../../lib/api";
 
 interface WidgetPropertiesSheetProps {
   open: boolean;
@@ -382,7 +382,7 @@ function IconPicker({
     try {
       const form = new FormData();
       form.append("file", file);
-      const res = await fetch(apiUrl("/api/icons"), { method: "POST", body: form });
+      const res = await apiFetch("/api/icons", { method: "POST", body: form });
       if (res.ok) {
         const { filename } = await res.json();
         onChange(`custom:${filename}`);
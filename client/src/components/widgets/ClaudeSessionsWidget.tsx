@@ -1,7 +1,7 @@
 import { useState, useEffect } from "react";
 import type { ClaudeSession, ClaudeSessionsData } from "shared";
 import type { LiveWidgetProps } from "./liveWidgetRegistry";
-import { apiUrl } from "../../lib/api";
+import { apiFetch } from "../../lib/api";
 
 function isClaudeSessionsData(data: unknown): data is ClaudeSessionsData {
   return (
@@ -91,7 +91,7 @@ function SessionCard({ session }: { session: ClaudeSession }) {
   const config = STATUS_CONFIG[session.status];
 
   const handleFocus = () => {
-    fetch(apiUrl("/api/hooks/claude/focus"), {
+    apiFetch("/api/hooks/claude/focus", {
       method: "POST",
       headers: { "Content-Type": "application/json" },
       body: JSON.stringify({ sessionId: session.id }),
@@ -2,7 +2,7 @@ import { useState, useEffect, useCallback, useRef } from "react";
 import { animate } from "animejs";
 import type { SoundboardStatusData } from "shared";
 import type { LiveWidgetProps } from "./liveWidgetRegistry";
-import { apiUrl } from "../../lib/api";
+import { apiFetch } from "../../lib/api";
 
 interface SoundFile {
   name: string;
@@ -146,7 +146,7 @@ export function SoundboardWidget({ widget, data, onPress }: LiveWidgetProps) {
 
   const fetchSounds = useCallback(async () => {
     try {
-      const resp = await fetch(apiUrl("/api/sounds"));
+      const resp = await apiFetch("/api/sounds");
       if (resp.ok) setSounds(await resp.json());
     } catch {
       // Ignore
@@ -188,7 +188,7 @@ export function SoundboardWidget({ widget, data, onPress }: LiveWidgetProps) {
     try {
       const form = new FormData();
       form.append("file", file);
-      await fetch(apiUrl("/api/sounds"), { method: "POST", body: form });
+      await apiFetch("/api/sounds", { method: "POST", body: form });
       await fetchSounds();
     } catch {
       // Ignore
@@ -1,13 +1,13 @@
 import { useEffect, useState } from "react";
 import type { DeckPilotConfig, ServerMessage } from "shared";
-import { apiUrl } from "../lib/api";
+import { apiFetch } from "../lib/api";
 
 export function useConfig(lastMessage: ServerMessage | null) {
   const [config, setConfig] = useState<DeckPilotConfig | null>(null);
 
   // Fetch config from REST API on mount (reliable, not subject to WS race conditions)
   useEffect(() => {
-    fetch(apiUrl("/api/config"))
+    apiFetch("/api/config")
       .then((r) => r.json())
       .then((c: DeckPilotConfig) => setConfig(c))
       .catch(() => {});
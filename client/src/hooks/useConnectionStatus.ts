@@ -0,0 +1,44 @@
+import { useEffect, useState } from "react";
+import { isTauri } from "../lib/platform";
+import { notifyNetworkChange, type NativeConnectionStatus } from "../lib/serverUrl";
+
+/** Follows the native server connection, and nudges it to reconnect when
+ *  the device comes back online or the app back into view. */
+export function useConnectionStatus() {
+  const [status, setStatus] = useState<NativeConnectionStatus | null>(null);
+
+  useEffect(() => {
+    if (!isTauri()) return;
+    let cancelled = false;
+    let unlisten: (() => void) | undefined;
+
+    import("@tauri-apps/api/core")
+      .then(({ invoke }) => invoke<NativeConnectionStatus>("server_connection_status"))
+      .then((current) => {
+        if (!cancelled) setStatus(current);
+      })
+      .catch(() => {});
+
+    import("@tauri-apps/api/event").then(({ listen }) =>
+      listen<NativeConnectionStatus>("connection_status", (event) => setStatus(event.payload)).then((fn) => {
+        if (cancelled) fn();
+        else unlisten = fn;
+      })
+    );
+
+    const onVisible = () => {
+      if (document.visibilityState === "visible") notifyNetworkChange();
+    };
+    window.addEventListener("online", notifyNetworkChange);
+    document.addEventListener("visibilitychange", onVisible);
+
+    return () => {
+      cancelled = true;
+      unlisten?.();
+      window.removeEventListener("online", notifyNetworkChange);
+      document.removeEventListener("visibilitychange", onVisible);
+    };
+  }, []);
+
+  return status;
+}
@@ -0,0 +1,61 @@
+import { useEffect, useRef, useState } from "react";
+import type { BatteryStatus, ControllerInfo, GamepadBatteryEvent } from "../lib/gamepad";
+import { isTauri } from "../lib/platform";
+
+/** Tracks the connected controllers and their battery, and calls `onLow`
+ *  whenever one reports it is about to run out. */
+export function useControllerBattery(onLow: (controller: ControllerInfo) => void) {
+  const [controllers, setControllers] = useState<ControllerInfo[]>([]);
+  const controllersRef = useRef(controllers);
+  controllersRef.current = controllers;
+  const onLowRef = useRef(onLow);
+  onLowRef.current = onLow;
+
+  useEffect(() => {
+    if (!isTauri()) return;
+    let cancelled = false;
+    const unlisteners: (() => void)[] = [];
+
+    const setBattery = (id: number, battery: BatteryStatus) =>
+      setControllers((prev) => prev.map((c) => (c.id === id ? { ...c, battery } : c)));
+
+    import("@tauri-apps/api/core")
+      .then(({ invoke }) => invoke<ControllerInfo[]>("list_controllers"))
+      .then((list) => {
+        if (!cancelled) setControllers(list);
+      })
+      .catch(() => {});
+
+    import("@tauri-apps/api/event").then(({ listen }) => {
+      const subscriptions = [
+        listen<ControllerInfo & { connected: boolean }>("gamepad_status", (event) => {
+          const { connected, ...info } = event.payload;
+          setControllers((prev) => [...prev.filter((c) => c.id !== info.id), ...(connected ? [info] : [])]);
+        }),
+        listen<GamepadBatteryEvent>("gamepad_battery", (event) => {
+          const { controller, level, wired } = event.payload;
+          setBattery(controller, { level, wired });
+        }),
+        listen<GamepadBatteryEvent>("gamepad_battery_low", (event) => {
+          const { controller, level, wired } = event.payload;
+          setBattery(controller, { level, wired });
+          const pad = controllersRef.current.find((c) => c.id === controller);
+          if (pad) onLowRef.current({ ...pad, battery: { level, wired } });
+        }),
+      ];
+      for (const subscription of subscriptions) {
+        subscription.then((fn) => {
+          if (cancelled) fn();
+          else unlisteners.push(fn);
+        });
+      }
+    });
+
+    return () => {
+      cancelled = true;
+      unlisteners.forEach((fn) => fn());
+    };
+  }, []);
+
+  return controllers;
+}
@@ -0,0 +1,29 @@
+import { useEffect } from "react";
+import type { ClaudeSessionsData, DiscordPresenceData } from "shared";
+import { clearLedState, hexToLed, setLedState } from "../lib/led";
+
+/** Shows live state on the controller LEDs: red while Discord is muted,
+ *  a pulse while a Claude session waits for input. */
+export function useControllerLed(liveData: Record<string, unknown>) {
+  const discord = liveData.discord as DiscordPresenceData | undefined;
+  const sessions = liveData.claude_sessions as ClaudeSessionsData | undefined;
+
+  const muted = discord?.voiceSettings?.mute ?? false;
+  const waiting = sessions?.sessions?.some((s) => s.status === "waiting_for_input") ?? false;
+
+  useEffect(() => {
+    if (muted) {
+      setLedState({ id: "discord_muted", color: hexToLed("#ef4444"), priority: 10 });
+    } else {
+      clearLedState("discord_muted");
+    }
+  }, [muted]);
+
+  useEffect(() => {
+    if (waiting) {
+      setLedState({ id: "claude_waiting", color: hexToLed("#f59e0b"), effect: "pulse", priority: 5 });
+    } else {
+      clearLedState("claude_waiting");
+    }
+  }, [waiting]);
+}
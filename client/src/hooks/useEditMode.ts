@@ -1,6 +1,6 @@
 import { useCallback, useState } from "react";
 import type { DeckPilotConfig, GamepadBinding, PageGridPosition, WidgetConfig } from "shared";
-import { apiUrl } from "../lib/api";
+import { apiFetch } from "../lib/api";
 
 export interface EditModeState {
   active: boolean;
@@ -41,7 +41,7 @@ export function useEditMode(
 
   const saveEditMode = useCallback(async () => {
     if (!editMode.draft) return;
-    const res = await fetch(apiUrl("/api/config"), {
+    const res = await apiFetch("/api/config", {
       method: "PUT",
       headers: { "Content-Type": "application/json" },
       body: JSON.stringify(editMode.draft),
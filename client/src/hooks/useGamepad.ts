@@ -3,7 +3,7 @@ import { isTauri } from "../lib/platform";
 
 const DEADZONE = 0.5;
 
-export function useGamepad(onButton: (button: number) => void) {
+export function useGamepad(onButton: (button: number, controller?: string) => void) {
   const prevPressed = useRef<Set<number>>(new Set());
   const loggedGamepads = useRef<Set<string>>(new Set());
   const onButtonRef = useRef(onButton);
@@ -15,8 +15,8 @@ export function useGamepad(onButton: (button: number) => void) {
       let unlisten: (() => void) | undefined;
 
       import("@tauri-apps/api/event").then(({ listen }) => {
-        listen<{ button: number }>("gamepad_button", (event) => {
-          onButtonRef.current(event.payload.button);
+        listen<{ button: number; guid: string }>("gamepad_button", (event) => {
+          onButtonRef.current(event.payload.button, event.payload.guid || undefined);
         }).then((fn) => {
           unlisten = fn;
         });
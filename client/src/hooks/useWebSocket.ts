@@ -1,5 +1,8 @@
 import { useCallback, useEffect, useRef, useState } from "react";
 import type { ClientMessage, ServerMessage } from "shared";
+import { authToken } from "../lib/api";
+import { isTauri } from "../lib/platform";
+import type { NativeConnectionStatus } from "../lib/serverUrl";
 
 const RECONNECT_DELAY = 2000;
 
@@ -8,9 +11,14 @@ export function useWebSocket(serverUrl?: string) {
   const [connected, setConnected] = useState(false);
   const [lastMessage, setLastMessage] = useState<ServerMessage | null>(null);
   const reconnectTimer = useRef<ReturnType<typeof setTimeout> | undefined>(undefined);
+  // Set once the hook stops connecting, for attempts still awaiting a token
+  const stopped = useRef(false);
+  // The webview can't open a socket to an https server's self-signed
+  // certificate, so its messages go through the app's native connection
+  const bridged = isTauri() && !!serverUrl?.startsWith("https://");
 
-  const connect = useCallback(() => {
-    if (wsRef.current?.readyState === WebSocket.OPEN) return;
+  const connect = useCallback(async () => {
+    if (bridged || wsRef.current?.readyState === WebSocket.OPEN) return;
 
     let url: string;
     if (serverUrl) {
@@ -24,6 +32,11 @@ export function useWebSocket(serverUrl?: string) {
       url = `${protocol}//${window.location.host}/ws`;
     }
 
+    // Tokens are good for one connection, so each attempt gets its own
+    const token = serverUrl ? await authToken("GET", "/ws") : null;
+    if (token) url += `?token=${encodeURIComponent(token)}`;
+    if (stopped.current) return;
+
     const ws = new WebSocket(url);
 
     ws.onopen = () => {
@@ -50,21 +63,67 @@ export function useWebSocket(serverUrl?: string) {
     };
 
     wsRef.current = ws;
-  }, [serverUrl]);
+  }, [serverUrl, bridged]);
 
   useEffect(() => {
+    stopped.current = false;
     connect();
     return () => {
+      stopped.current = true;
       clearTimeout(reconnectTimer.current);
       wsRef.current?.close();
     };
   }, [connect]);
 
+  useEffect(() => {
+    if (!bridged) return;
+    let cancelled = false;
+    const unlisteners: (() => void)[] = [];
+    let wasConnected = false;
+
+    const onStatus = (status: NativeConnectionStatus) => {
+      const isConnected = status.state === "connected";
+      setConnected(isConnected);
+      // The server sent its config before we were listening
+      if (isConnected && !wasConnected) {
+        import("@tauri-apps/api/core").then(({ invoke }) =>
+          invoke("send_server_message", { message: { type: "request_config" } }).catch(() => {})
+        );
+      }
+      wasConnected = isConnected;
+    };
+
+    import("@tauri-apps/api/event").then(async ({ listen }) => {
+      const fns = await Promise.all([
+        listen<ServerMessage>("server_message", (event) => setLastMessage(event.payload)),
+        listen<NativeConnectionStatus>("connection_status", (event) => onStatus(event.payload)),
+      ]);
+      if (cancelled) {
+        fns.forEach((fn) => fn());
+        return;
+      }
+      unlisteners.push(...fns);
+      const { invoke } = await import("@tauri-apps/api/core");
+      onStatus(await invoke<NativeConnectionStatus>("server_connection_status"));
+    }).catch(() => {});
+
+    return () => {
+      cancelled = true;
+      unlisteners.forEach((fn) => fn());
+    };
+  }, [bridged]);
+
   const send = useCallback((msg: ClientMessage) => {
+    if (bridged) {
+      import("@tauri-apps/api/core").then(({ invoke }) =>
+        invoke("send_server_message", { message: msg }).catch(() => {})
+      );
+      return;
+    }
     if (wsRef.current?.readyState === WebSocket.OPEN) {
       wsRef.current.send(JSON.stringify(msg));
     }
-  }, []);
+  }, [bridged]);
 
   return { connected, lastMessage, send };
 }
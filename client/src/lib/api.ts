@@ -1,3 +1,5 @@
+import { isTauri } from "./platform";
+
 /** Base URL for all API calls. Empty string = same-origin (browser mode). */
 let base = "";
 
@@ -12,7 +14,64 @@ export function getApiBase(): string {
   return base;
 }
 
+/** A token for one `method` request to `path` on the server at `url`, from
+ *  the Rust shell, which holds the pairing key. Tokens only work for that
+ *  request and only once. Resolves null if this device isn't paired with
+ *  that server. */
+export async function authToken(method: string, path: string, url = base): Promise<string | null> {
+  if (!isTauri() || !url) return null;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<string | null>("auth_token", { url, method, path: path.split("?")[0] });
+  } catch {
+    return null;
+  }
+}
+
 /** Build a full API URL from a path like "/api/config" */
 export function apiUrl(path: string): string {
   return `${base}${path}`;
 }
+
+/** Whether requests go through the Rust shell: the webview won't trust an
+ *  `https` server's self-signed certificate, the shell checks it against
+ *  the one pinned when pairing. */
+export function isProxied(): boolean {
+  return isTauri() && base.startsWith("https://");
+}
+
+interface ProxiedResponse {
+  status: number;
+  contentType: string | null;
+  body: number[];
+}
+
+async function proxyFetch(path: string, init: RequestInit): Promise<Response> {
+  // A Request encodes any kind of body (JSON text, FormData) and its type
+  const request = new Request(apiUrl(path), init);
+  const body = init.body == null ? null : Array.from(new Uint8Array(await request.arrayBuffer()));
+  const { invoke } = await import("@tauri-apps/api/core");
+  const reply = await invoke<ProxiedResponse>("server_request", {
+    url: base,
+    method: request.method,
+    path,
+    contentType: body ? request.headers.get("Content-Type") : null,
+    body,
+  });
+  const empty = reply.status === 204 || reply.status === 304;
+  return new Response(empty ? null : new Uint8Array(reply.body), {
+    status: reply.status,
+    headers: reply.contentType ? { "Content-Type": reply.contentType } : undefined,
+  });
+}
+
+/** `fetch` an API path, sending a pairing token as a header so it never
+ *  ends up in a URL. */
+export async function apiFetch(path: string, init: RequestInit = {}): Promise<Response> {
+  if (isProxied()) return proxyFetch(path, init);
+  const headers = new Headers(init.headers);
+  const token = await authToken(init.method ?? "GET", path);
+  if (token) headers.set("Authorization", `Bearer ${token}`);
+  return fetch(apiUrl(path), { ...init, headers });
+}
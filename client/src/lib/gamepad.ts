@@ -1,3 +1,5 @@
+import type { GamepadBinding } from "shared";
+
 export const GAMEPAD_LABELS: Record<number, string> = {
   0: "A",
   1: "B",
@@ -15,6 +17,60 @@ export const GAMEPAD_LABELS: Record<number, string> = {
   13: "Down",
   14: "Left",
   15: "Right",
+  16: "Guide",
+  // Extended indices from the native SDL backend (see gamepad.rs)
+  17: "QAM",
+  18: "Pad",
+  19: "R4",
+  20: "L4",
+  21: "R5",
+  22: "L5",
 };
 
 export const DEADZONE = 0.5;
+
+/** Identity of a connected controller, as reported by the native backend. */
+export interface ControllerInfo {
+  id: number;
+  guid: string;
+  name: string;
+  kind: "steam_deck" | "xbox" | "play_station" | "nintendo" | "generic";
+  vendorId?: number;
+  productId?: number;
+  /** Whether it can play haptics. */
+  rumble: boolean;
+  /** Whether it has an LED or lightbar. */
+  led: boolean;
+  /** Whether it has a gyro for motion controls. */
+  motion: boolean;
+  battery: BatteryStatus;
+}
+
+export type BatteryLevel = "empty" | "low" | "medium" | "full";
+
+export interface BatteryStatus {
+  /** Null while wired or when the controller doesn't report it. */
+  level: BatteryLevel | null;
+  wired: boolean;
+}
+
+/** Payload of `gamepad_battery` and `gamepad_battery_low`. */
+export interface GamepadBatteryEvent extends BatteryStatus {
+  controller: number;
+  guid: string;
+}
+
+/**
+ * Finds the binding for a button, preferring one limited to the given
+ * controller over one that applies to any controller.
+ */
+export function findGamepadBinding(
+  bindings: GamepadBinding[],
+  button: number,
+  controller?: string
+): GamepadBinding | undefined {
+  return (
+    bindings.find((b) => b.button === button && controller && b.controller === controller) ??
+    bindings.find((b) => b.button === button && !b.controller)
+  );
+}
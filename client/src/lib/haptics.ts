@@ -1,14 +1,89 @@
 import { isTauri } from "./platform";
 
+/** What happens to a pattern requested while another is still playing:
+ *  replace it (default), be dropped, or wait its turn. */
+export type HapticPolicy = "latest_wins" | "drop_if_busy" | "queue";
+
+export interface HapticOptions {
+  /** Instance id from `list_controllers`; every controller if omitted. */
+  controller?: number;
+  policy?: HapticPolicy;
+}
+
 /** Trigger haptic feedback on the gamepad controller.
  *  No-op in browser mode. */
-export async function triggerHaptic(strength = 0.2, durationMs = 40): Promise<void> {
+export async function triggerHaptic(strength = 0.2, durationMs = 40, options?: HapticOptions): Promise<void> {
+  if (!isTauri()) return;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("trigger_haptic", { strength, durationMs, options });
+  } catch {
+    // ignore — no controller connected or not in Tauri
+  }
+}
+
+/** One step of a haptic pattern; strengths are 0..1 per motor. `ramp` slides
+ *  from the previous step's strengths instead of jumping. */
+export interface HapticStep {
+  low: number;
+  high: number;
+  leftTrigger?: number;
+  rightTrigger?: number;
+  /** Steam Deck trackpad actuators; without them `low` plays on the left
+   *  pad and `high` on the right. */
+  leftPad?: number;
+  rightPad?: number;
+  durationMs: number;
+  pauseMs?: number;
+  ramp?: boolean;
+}
+
+export type HapticPreset = "tick" | "confirm" | "error" | "heartbeat" | "charge";
+
+export interface RumbleMotors {
+  low: number;
+  high: number;
+  leftTrigger?: number;
+  rightTrigger?: number;
+  leftPad?: number;
+  rightPad?: number;
+}
+
+/** Drive the low/high-frequency and trigger motors independently. Controllers
+ *  without them get a single rumble at the stronger of `low` and `high`. */
+export async function setRumble(motors: RumbleMotors, durationMs: number, options?: HapticOptions): Promise<void> {
   if (!isTauri()) return;
 
   try {
     const { invoke } = await import("@tauri-apps/api/core");
-    await invoke("trigger_haptic", { strength, durationMs });
+    await invoke("set_rumble", { motors, durationMs, options });
   } catch {
     // ignore — no controller connected or not in Tauri
   }
 }
+
+/** Play a preset or a custom pattern on the controller. By default it
+ *  replaces any pattern already playing. No-op in browser mode. */
+export async function playHaptic(pattern: HapticPreset | HapticStep[], options?: HapticOptions): Promise<void> {
+  if (!isTauri()) return;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("play_haptic", { pattern, options });
+  } catch {
+    // ignore — no controller connected or not in Tauri
+  }
+}
+
+/** Cancel the playing and queued patterns, on one controller or all. */
+export async function stopHaptic(controller?: number): Promise<void> {
+  if (!isTauri()) return;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("stop_haptic", { controller });
+  } catch {
+    // ignore
+  }
+}
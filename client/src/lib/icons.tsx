@@ -1,4 +1,5 @@
-import { apiUrl } from "./api";
+import { useEffect, useState } from "react";
+import { apiFetch, apiUrl, isProxied } from "./api";
 import type { LucideIcon } from "lucide-react";
 import {
   Play,
@@ -150,6 +151,37 @@ const ICON_MAP: Record<string, LucideIcon> = {
   film: Film,
 };
 
+/** An uploaded image. The webview can't load one from a server it only
+ *  reaches through the Rust shell, so those are fetched into a blob. */
+function CustomIcon({ path, size }: { path: string; size: number }) {
+  const proxied = isProxied();
+  const [src, setSrc] = useState<string | undefined>(proxied ? undefined : apiUrl(path));
+
+  useEffect(() => {
+    if (!proxied) {
+      setSrc(apiUrl(path));
+      return;
+    }
+    let cancelled = false;
+    let objectUrl: string | undefined;
+    apiFetch(path)
+      .then((resp) => (resp.ok ? resp.blob() : null))
+      .then((blob) => {
+        if (!blob || cancelled) return;
+        objectUrl = URL.createObjectURL(blob);
+        setSrc(objectUrl);
+      })
+      .catch(() => {});
+    return () => {
+      cancelled = true;
+      if (objectUrl) URL.revokeObjectURL(objectUrl);
+    };
+  }, [path, proxied]);
+
+  if (!src) return <span style={{ width: size, height: size, display: "inline-block" }} />;
+  return <img src={src} alt="" width={size} height={size} style={{ objectFit: "contain" }} />;
+}
+
 // Emoji detection: codepoint in common emoji ranges
 const EMOJI_RE = /^[\p{Emoji_Presentation}\p{Extended_Pictographic}]/u;
 
@@ -161,16 +193,7 @@ export function renderIcon(
 
   // Custom uploaded image
   if (icon.startsWith("custom:")) {
-    const filename = icon.slice(7);
-    return (
-      <img
-        src={apiUrl(`/api/icons/${filename}`)}
-        alt=""
-        width={size}
-        height={size}
-        style={{ objectFit: "contain" }}
-      />
-    );
+    return <CustomIcon path={`/api/icons/${icon.slice(7)}`} size={size} />;
   }
 
   // Emoji
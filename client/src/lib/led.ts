@@ -0,0 +1,60 @@
+import { isTauri } from "./platform";
+
+export interface LedColor {
+  r: number;
+  g: number;
+  b: number;
+}
+
+/** A named state shown on the controller LEDs until cleared. The highest
+ *  `priority` wins; ties go to the most recent. */
+export interface LedState {
+  id: string;
+  color: LedColor;
+  effect?: "solid" | "pulse" | "blink";
+  periodMs?: number;
+  priority?: number;
+  /** Instance id from `list_controllers`; every controller if omitted. */
+  controller?: number;
+}
+
+/** Parses `#rrggbb` into an LED colour. */
+export function hexToLed(hex: string): LedColor {
+  const value = parseInt(hex.replace("#", ""), 16);
+  return { r: (value >> 16) & 0xff, g: (value >> 8) & 0xff, b: value & 0xff };
+}
+
+/** Set the LED or lightbar colour; `null` drops the manual colour.
+ *  No-op in browser mode. */
+export async function setControllerLed(color: LedColor | null, controller?: number): Promise<void> {
+  if (!isTauri()) return;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("set_controller_led", { color, controller });
+  } catch {
+    // ignore — no controller with an LED connected
+  }
+}
+
+export async function setLedState(led: LedState): Promise<void> {
+  if (!isTauri()) return;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("set_led_state", { led });
+  } catch {
+    // ignore
+  }
+}
+
+export async function clearLedState(id: string): Promise<void> {
+  if (!isTauri()) return;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("clear_led_state", { id });
+  } catch {
+    // ignore
+  }
+}
@@ -0,0 +1,75 @@
+import { isTauri } from "./platform";
+
+/** A joystick opened for raw capture (payload of `gamepad_raw_capture`). */
+export interface RawJoystick {
+  id: number;
+  /** The GUID a mapping for this joystick has to start with. */
+  guid: string;
+  name: string;
+  buttons: number;
+  axes: number;
+  hats: number;
+}
+
+export interface RawCaptureEvent {
+  active: boolean;
+  joysticks: RawJoystick[];
+}
+
+/** Payload of `gamepad_raw_input`: one press or push, as a mapping source
+ *  (`b3`, `h0.4`, `a2`, `+a1`/`-a1`). */
+export interface RawInputEvent {
+  joystick: number;
+  guid: string;
+  source: string;
+}
+
+/** Payload of `gamepad_mapping_error`: a well-formed mapping that SDL
+ *  rejected or that couldn't be saved. */
+export interface MappingErrorEvent {
+  mapping: string;
+  error: string;
+}
+
+/** Builds an SDL mapping line from captured sources, keyed by SDL target
+ *  name (`a`, `dpup`, `leftx`, `lefttrigger`, ...). Stick axes take the
+ *  whole axis, so the direction captured for them is dropped. */
+export function buildMapping(joystick: RawJoystick, bindings: Record<string, string>): string {
+  const fullAxis = /^(left|right)[xy]$/;
+  const entries = Object.entries(bindings).map(([target, source]) =>
+    fullAxis.test(target) ? `${target}:${source.replace(/^[+-]/, "")}` : `${target}:${source}`
+  );
+  return [joystick.guid, joystick.name.replace(/,/g, " "), ...entries, ""].join(",");
+}
+
+/** Add an SDL controller mapping and save it for future launches. Rejects
+ *  with the reason if the mapping is malformed; later failures arrive as
+ *  `gamepad_mapping_error`. */
+export async function addControllerMapping(mapping: string): Promise<void> {
+  if (!isTauri()) return;
+
+  const { invoke } = await import("@tauri-apps/api/core");
+  await invoke("add_controller_mapping", { mapping });
+}
+
+export async function startRawCapture(): Promise<void> {
+  if (!isTauri()) return;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("start_raw_capture");
+  } catch {
+    // ignore
+  }
+}
+
+export async function stopRawCapture(): Promise<void> {
+  if (!isTauri()) return;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("stop_raw_capture");
+  } catch {
+    // ignore
+  }
+}
@@ -0,0 +1,83 @@
+import type { PairedDevice } from "shared";
+import { isTauri } from "./platform";
+
+export interface PairingRequest {
+  requestId: string;
+  expiresInMs: number;
+  /** SHA-256 fingerprint of an `https` server's certificate, for the user to
+   *  compare with the one the server prints. */
+  fingerprint: string | null;
+}
+
+export interface PairingInfo {
+  server: string;
+  deviceId: string;
+  /** The pinned certificate, for `https` servers. */
+  fingerprint: string | null;
+}
+
+export async function getPairingStatus(): Promise<PairingInfo | null> {
+  if (!isTauri()) return null;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<PairingInfo | null>("pairing_status");
+  } catch {
+    return null;
+  }
+}
+
+/** Ask the server at `url` to show a PIN. Rejects with the reason. */
+export async function requestPairing(url: string, name?: string): Promise<PairingRequest> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<PairingRequest>("request_pairing", { url, name });
+}
+
+/** Finish pairing with the PIN shown on the server (or its
+ *  `deckpilot://pair` link), pinning the certificate `fingerprint` the user
+ *  was shown. Rejects with the reason, e.g. a wrong PIN. */
+export async function confirmPairing(
+  url: string,
+  requestId: string,
+  code: string,
+  fingerprint?: string | null
+): Promise<PairingInfo> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<PairingInfo>("confirm_pairing", { url, requestId, code, fingerprint });
+}
+
+/** Pin the paired server's new certificate once the user accepted it. */
+export async function trustServerCertificate(fingerprint: string): Promise<PairingInfo> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<PairingInfo>("trust_server_certificate", { fingerprint });
+}
+
+/** Ask whether to trust a paired server's new certificate, and pin it if so.
+ *  Resolves whether it was trusted. */
+export async function confirmCertificateChange(change: { expected: string; found: string }): Promise<boolean> {
+  try {
+    const { ask } = await import("@tauri-apps/plugin-dialog");
+    const yes = await ask(
+      `The server presented a different certificate than when you paired.\n\nExpected:\n${change.expected}\n\nFound:\n${change.found}\n\nOnly trust it if the server shows the new fingerprint, e.g. after reinstalling.`,
+      { title: "Server Certificate Changed", kind: "warning", okLabel: "Trust", cancelLabel: "Cancel" }
+    );
+    if (!yes) return false;
+    await trustServerCertificate(change.found);
+    return true;
+  } catch (e) {
+    console.warn("[Pairing] Could not trust certificate:", e);
+    return false;
+  }
+}
+
+export async function listPairedDevices(): Promise<PairedDevice[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<PairedDevice[]>("list_paired_devices");
+}
+
+/** Revoke `deviceId`, or this device (forgetting its key) when omitted. The
+ *  server refuses to revoke other devices unless asked from its own machine. */
+export async function revokePairing(deviceId?: string): Promise<void> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  await invoke("revoke_pairing", { deviceId });
+}
@@ -1,7 +1,31 @@
 import { isTauri } from "./platform";
 
+/** Payload of `connection_status`: the native server connection's state. */
+export interface NativeConnectionStatus {
+  state: "idle" | "connecting" | "connected" | "disconnected";
+  url: string | null;
+  /** Failed attempts since the last successful connection. */
+  attempts: number;
+  retryInMs: number | null;
+  /** Gamepad presses waiting to be sent. */
+  queued: number;
+  error: string | null;
+  /** The server presented a different certificate than the pinned one. */
+  certificate: { expected: string; found: string } | null;
+}
+
 const STORE_KEY = "server_url";
 
+/** A DeckPilot server found on the LAN by `discover_servers`. */
+export interface DiscoveredServer {
+  host: string;
+  port: number;
+  name: string;
+  version: string | null;
+  /** Serves `https`; the app connects to it natively. */
+  tls: boolean;
+}
+
 /** Get the stored server URL (Tauri) or derive from window.location (browser) */
 export async function getServerUrl(): Promise<string | null> {
   if (!isTauri()) {
@@ -33,3 +57,44 @@ export async function setServerUrl(url: string): Promise<void> {
     // ignore
   }
 }
+
+/** Point the app's native server connection at `url`, so gamepad presses
+ *  reach the server without going through the webview. Resolves false where
+ *  there is none (browser mode, or a URL it can't handle). */
+export async function connectNativeServer(url: string): Promise<boolean> {
+  if (!isTauri()) return false;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("connect_server", { url });
+    return true;
+  } catch {
+    return false;
+  }
+}
+
+/** Tell the native connection the network may have changed, so it retries
+ *  (or checks its socket) now instead of waiting out its backoff. */
+export async function notifyNetworkChange(): Promise<void> {
+  if (!isTauri()) return;
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("notify_network_change");
+  } catch {
+    // ignore
+  }
+}
+
+/** Look for DeckPilot servers on the LAN (mDNS, then a UDP broadcast probe).
+ *  Resolves empty in browser mode, or if neither method can run. */
+export async function discoverServers(): Promise<DiscoveredServer[]> {
+  if (!isTauri()) return [];
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<DiscoveredServer[]>("discover_servers");
+  } catch {
+    return [];
+  }
+}
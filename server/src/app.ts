@@ -3,16 +3,21 @@ import { Hono } from "hono";
 import { cors } from "hono/cors";
 import { configRouter } from "./routes/config";
 import { actionsRouter } from "./routes/actions";
+import { pairingRouter, type RequestAuth } from "./routes/pairing";
+import { isAppOrigin } from "./services/pairing";
 
 const clientDist = resolve(import.meta.dir, "../../client/dist");
 
-const app = new Hono();
+const app = new Hono<{ Bindings: RequestAuth }>();
 
-app.use(cors());
+// Only the app's own pages may read responses; any other site could
+// otherwise drive this machine's actions through a browser
+app.use(cors({ origin: (origin, c) => (isAppOrigin(origin, new URL(c.req.url)) ? origin : null) }));
 
 app.get("/api/health", (c) => c.json({ status: "ok" }));
 app.route("/api/config", configRouter);
 app.route("/api/actions", actionsRouter);
+app.route("/api/pair", pairingRouter);
 
 /** Register the static file catch-all AFTER all API routes */
 export function registerStaticFallback(): void {
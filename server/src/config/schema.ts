@@ -60,10 +60,13 @@ const profileSchema = z.object({
 
 const gamepadBindingSchema = z.object({
   button: z.number(),
+  controller: z.string().optional(),
+  controllerName: z.string().optional(),
   kind: z.enum(["client", "server"]),
   action: actionSchema.optional(),
   clientAction: z.string().optional(),
   label: z.string().optional(),
+  replayIfLate: z.boolean().optional(),
 });
 
 export const configSchema = z.object({
@@ -71,6 +74,8 @@ export const configSchema = z.object({
   server: z.object({
     port: z.number(),
     host: z.string(),
+    requirePairing: z.boolean().optional(),
+    tls: z.boolean().optional(),
   }),
   activeProfile: z.string(),
   profiles: z.array(profileSchema).min(1),
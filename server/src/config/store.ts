@@ -5,7 +5,7 @@ import type { DeckPilotConfig } from "shared";
 import { configSchema } from "./schema";
 import { defaultConfig } from "./defaults";
 
-function getConfigDir(): string {
+export function getConfigDir(): string {
   switch (process.platform) {
     case "darwin":
       return join(homedir(), "Library", "Application Support", "deckpilot");
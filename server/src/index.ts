@@ -16,6 +16,9 @@ import { iconsRouter } from "./routes/icons";
 import { ClaudeSessionsSource } from "./services/sources/claude-sessions";
 import { createClaudeHooksRouter } from "./routes/claude-hooks";
 import { setupClaudeHooks } from "./services/claude-hooks-setup";
+import { startDiscovery } from "./services/discovery";
+import { authorize, setCertificateFingerprint, setOnDeviceRevoked } from "./services/pairing";
+import { loadTlsIdentity } from "./services/tls";
 
 // Initialize
 const config = loadConfig();
@@ -26,6 +29,8 @@ const soundboard = new SoundboardManager();
 const claudeSessionsSource = new ClaudeSessionsSource();
 const engine = new ActionEngine({ platform, discordSource, nowPlayingSource, soundboard });
 const wsHandler = createWebSocketHandler(engine);
+const tls = config.server.tls ? loadTlsIdentity() : null;
+if (tls) setCertificateFingerprint(tls.fingerprint);
 
 // Discord OAuth routes
 app.route("/api/discord", createDiscordRouter(discordSource));
@@ -53,6 +58,13 @@ const providers = [
 ];
 const liveData = new LiveDataManager(providers, clients, getConfig);
 
+// Drop the connections of devices whose pairing was revoked
+setOnDeviceRevoked((deviceId) => {
+  for (const ws of clients) {
+    if ((ws.data as { deviceId?: string } | undefined)?.deviceId === deviceId) ws.close(4001, "revoked");
+  }
+});
+
 // Refresh pollers when config changes (e.g. new data sources added)
 setOnConfigChanged(() => liveData.refreshPollers());
 
@@ -74,23 +86,37 @@ setupClaudeHooks(config.server.port).catch((err) =>
   console.warn("Failed to configure Claude Code hooks:", err)
 );
 
-console.log(`DeckPilot server starting on port ${config.server.port}`);
+// Let the app find this server on the LAN
+startDiscovery(config.server.port, !!tls);
+
+console.log(`DeckPilot server starting on port ${config.server.port}${tls ? " (https)" : ""}`);
+if (tls) {
+  console.log(`Certificate fingerprint: ${tls.fingerprint}`);
+}
+if (config.server.requirePairing !== false) {
+  console.log("Devices on other machines have to pair before connecting");
+}
 
 export default {
   port: config.server.port,
   hostname: config.server.host,
+  tls: tls ? { cert: tls.cert, key: tls.key } : undefined,
 
   fetch(req: Request, server: import("bun").Server<unknown>): Response | Promise<Response> {
     const url = new URL(req.url);
 
+    // Everything but the pairing flow needs a paired device off this machine
+    const auth = authorize(req, url, server.requestIP(req)?.address);
+    if (auth instanceof Response) return auth;
+
     // WebSocket upgrade
     if (url.pathname === "/ws") {
-      const upgraded = server.upgrade(req, { data: {} });
+      const upgraded = server.upgrade(req, { data: { deviceId: auth.deviceId } });
       if (upgraded) return undefined as unknown as Response;
       return new Response("WebSocket upgrade failed", { status: 400 });
     }
 
-    return app.fetch(req, { env: {} } as never);
+    return app.fetch(req, auth);
   },
 
   websocket: wrappedWsHandler,
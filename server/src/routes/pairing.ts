@@ -0,0 +1,52 @@
+import { Hono } from "hono";
+import {
+  confirmPairing,
+  getPairedDevices,
+  getPendingPairings,
+  requestPairing,
+  revokeDevice,
+} from "../services/pairing";
+
+/** What `authorize` found out about the request, passed in as Hono's env. */
+export interface RequestAuth {
+  deviceId?: string;
+  local: boolean;
+  /** Where the request came from, if Bun could tell. */
+  address?: string;
+}
+
+const pairingRouter = new Hono<{ Bindings: RequestAuth }>();
+
+pairingRouter.post("/request", async (c) => {
+  const { name } = await c.req.json<{ name?: string }>().catch(() => ({ name: undefined }));
+  const result = requestPairing(name?.trim().slice(0, 64) || "Unnamed device", c.env.address);
+  if (!result.ok) return c.json({ error: result.error, retryInMs: result.retryInMs }, 429);
+  return c.json({ requestId: result.requestId, expiresInMs: result.expiresInMs });
+});
+
+pairingRouter.post("/confirm", async (c) => {
+  const body = await c.req.json<{ requestId?: string; proof?: string }>().catch(() => null);
+  if (!body?.requestId || !body.proof) return c.json({ error: "invalid_request" }, 400);
+  const result = confirmPairing(body.requestId, body.proof);
+  if (!result.ok) return c.json({ error: result.error }, result.error === "locked" ? 429 : 403);
+  // The device derives the key from the PIN itself
+  return c.json({ deviceId: result.deviceId });
+});
+
+// PINs are only for the screen of the machine the server runs on
+pairingRouter.get("/pending", (c) => {
+  if (!c.env.local) return c.json({ error: "local_only" }, 403);
+  return c.json(getPendingPairings());
+});
+
+pairingRouter.get("/devices", (c) => c.json(getPairedDevices()));
+
+// A device may unpair itself; unpairing others is up to this machine
+pairingRouter.delete("/devices/:id", (c) => {
+  const id = c.req.param("id");
+  if (!c.env.local && c.env.deviceId !== id) return c.json({ error: "not_allowed" }, 403);
+  if (!revokeDevice(id)) return c.json({ error: "not_found" }, 404);
+  return c.json({ success: true });
+});
+
+export { pairingRouter };
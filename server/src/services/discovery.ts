@@ -0,0 +1,61 @@
+import { createSocket } from "dgram";
+import { readFileSync } from "fs";
+import { hostname } from "os";
+import { join } from "path";
+
+/** DNS-SD service type the Tauri app browses for. */
+const SERVICE_TYPE = "_deckpilot._tcp";
+/** UDP port the app's broadcast probe goes to, whatever the HTTP port. */
+const DISCOVERY_PORT = 9900;
+const PROBE = "DECKPILOT_DISCOVER";
+
+const version: string = JSON.parse(readFileSync(join(import.meta.dir, "../../package.json"), "utf8")).version;
+
+/** Makes the server findable from the app's "find servers" list: advertised
+ *  over mDNS where the OS has a responder, and answering UDP probes. */
+export function startDiscovery(port: number, tls = false): () => void {
+  const name = hostname().replace(/\.local$/, "");
+  const stopAdvertising = advertise(name, port, tls);
+
+  const socket = createSocket({ type: "udp4", reuseAddr: true });
+  socket.on("message", (msg, from) => {
+    if (msg.toString() !== PROBE) return;
+    const reply = JSON.stringify({ name, port, version, tls });
+    socket.send(reply, from.port, from.address);
+  });
+  socket.on("error", (err) => {
+    console.warn(`Discovery probe responder unavailable: ${err.message}`);
+    socket.close();
+  });
+  socket.bind(DISCOVERY_PORT);
+
+  return () => {
+    stopAdvertising();
+    try {
+      socket.close();
+    } catch {
+      // already closed
+    }
+  };
+}
+
+/** Registers the service with the system's mDNS responder (Bonjour on
+ *  macOS, Avahi on Linux). */
+function advertise(name: string, port: number, tls: boolean): () => void {
+  const txt = [`version=${version}`, `tls=${tls ? 1 : 0}`];
+  const command =
+    process.platform === "darwin"
+      ? ["dns-sd", "-R", name, SERVICE_TYPE, "local", String(port), ...txt]
+      : process.platform === "linux"
+        ? ["avahi-publish-service", name, SERVICE_TYPE, String(port), ...txt]
+        : null;
+  if (!command) return () => {};
+
+  try {
+    const proc = Bun.spawn(command, { stdout: "ignore", stderr: "ignore" });
+    return () => proc.kill();
+  } catch (err) {
+    console.warn(`mDNS advertising unavailable: ${err instanceof Error ? err.message : err}`);
+    return () => {};
+  }
+}
@@ -0,0 +1,305 @@
+import { createHmac, randomBytes, randomInt, timingSafeEqual } from "crypto";
+import { existsSync, mkdirSync, readFileSync, writeFileSync } from "fs";
+import { join } from "path";
+import type { PairedDevice } from "shared";
+import { getConfig, getConfigDir } from "../config/store";
+
+/** How long a pairing PIN stays valid. */
+const PIN_TTL_MS = 2 * 60 * 1000;
+/** Wrong PINs allowed before a pairing request is thrown away. */
+const MAX_PIN_ATTEMPTS = 5;
+/** Wrong PINs allowed across all requests before pairing is locked, so a
+ *  PIN can't be guessed by opening a new request every few tries. */
+const MAX_FAILED_PINS = 10;
+const PAIRING_LOCKOUT_MS = 15 * 60 * 1000;
+/** Pairing requests one address may open per `REQUEST_WINDOW_MS`. */
+const MAX_REQUESTS_PER_ADDRESS = 5;
+const REQUEST_WINDOW_MS = 10 * 60 * 1000;
+/** How far a token's timestamp may be from the server clock. Tokens are
+ *  remembered for this long so none is accepted twice; devices sign a new
+ *  one for every connection and request. */
+const TOKEN_MAX_AGE_S = 60;
+
+interface StoredDevice extends PairedDevice {
+  /** Hex HMAC-SHA256 key the device signs its tokens with, derived from the
+   *  PIN on both sides when pairing. */
+  key: string;
+}
+
+interface PendingPairing {
+  name: string;
+  pin: string;
+  expiresAt: number;
+  attempts: number;
+}
+
+const devicesPath = join(getConfigDir(), "devices.json");
+const pending = new Map<string, PendingPairing>();
+/** When each address opened its recent pairing requests. */
+const requestTimes = new Map<string, number[]>();
+let failedPins = 0;
+let lockedUntil = 0;
+let devices: StoredDevice[] = loadDevices();
+/** Tokens already used, as `<deviceId>.<ts>.<nonce>`, with when they expire. */
+const usedTokens = new Map<string, number>();
+let onRevoked: ((deviceId: string) => void) | null = null;
+let certificateFingerprint: string | null = null;
+
+function loadDevices(): StoredDevice[] {
+  if (!existsSync(devicesPath)) return [];
+  try {
+    return JSON.parse(readFileSync(devicesPath, "utf-8"));
+  } catch (err) {
+    console.warn("Failed to read paired devices:", err);
+    return [];
+  }
+}
+
+function saveDevices(): void {
+  mkdirSync(getConfigDir(), { recursive: true });
+  writeFileSync(devicesPath, JSON.stringify(devices, null, 2), { mode: 0o600 });
+}
+
+function publicDevice({ key: _key, ...device }: StoredDevice): PairedDevice {
+  return device;
+}
+
+export type PairingRequestResult =
+  | { ok: true; requestId: string; expiresInMs: number }
+  | { ok: false; error: "locked" | "busy" | "rate_limited"; retryInMs: number };
+
+function dropExpired(now: number): void {
+  for (const [requestId, request] of pending) {
+    if (request.expiresAt < now) pending.delete(requestId);
+  }
+  for (const [address, times] of requestTimes) {
+    const recent = times.filter((t) => t > now - REQUEST_WINDOW_MS);
+    if (recent.length) requestTimes.set(address, recent);
+    else requestTimes.delete(address);
+  }
+  if (lockedUntil && lockedUntil <= now) {
+    lockedUntil = 0;
+    failedPins = 0;
+  }
+}
+
+/** Starts pairing a device called `name`. The PIN is only shown on this
+ *  machine, so entering it on the device proves the user can see both. Only
+ *  one request is open at a time, and `address` may only open a few. */
+export function requestPairing(name: string, address = "unknown"): PairingRequestResult {
+  const now = Date.now();
+  dropExpired(now);
+  if (lockedUntil) return { ok: false, error: "locked", retryInMs: lockedUntil - now };
+  const times = requestTimes.get(address) ?? [];
+  if (times.length >= MAX_REQUESTS_PER_ADDRESS) {
+    return { ok: false, error: "rate_limited", retryInMs: times[0] + REQUEST_WINDOW_MS - now };
+  }
+  const [current] = pending.values();
+  if (current) return { ok: false, error: "busy", retryInMs: current.expiresAt - now };
+  requestTimes.set(address, [...times, now]);
+
+  const requestId = randomBytes(16).toString("hex");
+  const pin = String(randomInt(0, 1_000_000)).padStart(6, "0");
+  pending.set(requestId, { name, pin, expiresAt: now + PIN_TTL_MS, attempts: 0 });
+
+  console.log(`Pairing request from "${name}": enter PIN ${pin} on the device`);
+  console.log(`  or scan deckpilot://pair?request=${requestId}&pin=${pin}`);
+  if (certificateFingerprint) {
+    console.log(`  The device should show certificate ${certificateFingerprint}`);
+  } else {
+    console.warn("  Pairing over plain http: someone watching this network could work out the device's key.");
+    console.warn("  Set server.tls in the config to pair over https on networks you don't trust.");
+  }
+  if (process.platform === "darwin") {
+    const script = `display notification "Enter ${pin} on ${name.replace(/["\\]/g, "")}" with title "DeckPilot pairing"`;
+    Bun.spawn(["osascript", "-e", script], { stdout: "ignore", stderr: "ignore" });
+  }
+  return { ok: true, requestId, expiresInMs: PIN_TTL_MS };
+}
+
+/** The key pairing request `requestId` ends in, worked out from the PIN by
+ *  the server and the device alike, so it's never sent:
+ *  hex HMAC-SHA256(pin, "deckpilot-pair.<requestId>"). Over plain http
+ *  someone who watched the pairing could still find it by trying every
+ *  PIN; over https they see nothing. */
+function deriveKey(pin: string, requestId: string): string {
+  return createHmac("sha256", pin).update(`deckpilot-pair.${requestId}`).digest("hex");
+}
+
+/** What the device sends to show it knows the PIN, without sending it. */
+function pairingProof(key: string, requestId: string): Buffer {
+  return createHmac("sha256", Buffer.from(key, "hex")).update(`confirm.${requestId}`).digest();
+}
+
+export type PairingResult =
+  | { ok: true; deviceId: string }
+  | { ok: false; error: "expired" | "wrong_pin" | "locked" };
+
+/** Checks the device's `proof` of the PIN for a pairing request and, if it
+ *  holds, pairs the device under a new id. Too many wrong PINs, over any
+ *  number of requests, lock pairing for a while. */
+export function confirmPairing(requestId: string, proof: string): PairingResult {
+  dropExpired(Date.now());
+  if (lockedUntil) return { ok: false, error: "locked" };
+  const request = pending.get(requestId);
+  if (!request) return { ok: false, error: "expired" };
+  const key = deriveKey(request.pin, requestId);
+  const expected = pairingProof(key, requestId);
+  const given = Buffer.from(proof, "hex");
+  if (given.length !== expected.length || !timingSafeEqual(given, expected)) {
+    if (++request.attempts >= MAX_PIN_ATTEMPTS) pending.delete(requestId);
+    if (++failedPins >= MAX_FAILED_PINS) {
+      lockedUntil = Date.now() + PAIRING_LOCKOUT_MS;
+      pending.clear();
+      console.warn(`Too many wrong pairing PINs; pairing is locked for ${PAIRING_LOCKOUT_MS / 60_000} minutes`);
+      return { ok: false, error: "locked" };
+    }
+    return { ok: false, error: "wrong_pin" };
+  }
+  pending.delete(requestId);
+  failedPins = 0;
+
+  const device: StoredDevice = {
+    id: randomBytes(8).toString("hex"),
+    name: request.name,
+    key,
+    pairedAt: new Date().toISOString(),
+  };
+  devices.push(device);
+  saveDevices();
+  console.log(`Paired "${device.name}" (${device.id})`);
+  return { ok: true, deviceId: device.id };
+}
+
+/** Pending pairing requests, for showing their PINs on this machine. */
+export function getPendingPairings(): { requestId: string; name: string; pin: string; expiresAt: number }[] {
+  const now = Date.now();
+  return [...pending]
+    .filter(([, request]) => request.expiresAt >= now)
+    .map(([requestId, { name, pin, expiresAt }]) => ({ requestId, name, pin, expiresAt }));
+}
+
+export function getPairedDevices(): PairedDevice[] {
+  return devices.map(publicDevice);
+}
+
+/** Forgets a device's key; its connections are closed. */
+export function revokeDevice(deviceId: string): boolean {
+  const before = devices.length;
+  devices = devices.filter((d) => d.id !== deviceId);
+  if (devices.length === before) return false;
+  saveDevices();
+  onRevoked?.(deviceId);
+  console.log(`Revoked device ${deviceId}`);
+  return true;
+}
+
+export function setOnDeviceRevoked(cb: (deviceId: string) => void): void {
+  onRevoked = cb;
+}
+
+/** The TLS certificate devices pin when pairing, shown alongside the PIN so
+ *  the user can check the device is talking to this server. */
+export function setCertificateFingerprint(fingerprint: string | null): void {
+  certificateFingerprint = fingerprint;
+}
+
+/** Verifies a `<deviceId>.<unix seconds>.<nonce>.<signature>` token for a
+ *  `method` request to `path`, the signature being hex
+ *  HMAC-SHA256(key, "<deviceId>.<unix seconds>.<nonce>.<METHOD>.<path>"),
+ *  and returns the device it belongs to. Each token is taken once, so one
+ *  seen in transit can't be replayed, nor used for another request. A
+ *  WebSocket is only checked when it opens; revoking the device closes it. */
+export function verifyToken(token: string, method: string, path: string): PairedDevice | null {
+  const [deviceId, ts, nonce, signature] = token.split(".");
+  const device = devices.find((d) => d.id === deviceId);
+  const issued = Number(ts);
+  if (!device || !nonce || nonce.length > 64 || !signature || !Number.isInteger(issued)) return null;
+  const now = Date.now() / 1000;
+  if (Math.abs(now - issued) > TOKEN_MAX_AGE_S) return null;
+
+  const claim = `${deviceId}.${ts}.${nonce}`;
+  const expected = createHmac("sha256", Buffer.from(device.key, "hex"))
+    .update(`${claim}.${method.toUpperCase()}.${path}`)
+    .digest();
+  const given = Buffer.from(signature, "hex");
+  if (given.length !== expected.length || !timingSafeEqual(given, expected)) return null;
+
+  for (const [used, expiresAt] of usedTokens) {
+    if (expiresAt < now) usedTokens.delete(used);
+  }
+  if (usedTokens.has(claim)) return null;
+  usedTokens.set(claim, issued + TOKEN_MAX_AGE_S);
+
+  device.lastSeen = new Date().toISOString();
+  return publicDevice(device);
+}
+
+/** Pulls the token from `Authorization: Bearer ...` or, for the WebSocket
+ *  upgrade, which can't set headers from a browser, a `token` query
+ *  parameter. Query tokens end up in logs and history, so nothing else
+ *  takes them. */
+function requestToken(req: Request, url: URL): string | null {
+  const header = req.headers.get("authorization");
+  if (header?.startsWith("Bearer ")) return header.slice(7);
+  return url.pathname === "/ws" ? url.searchParams.get("token") : null;
+}
+
+/** Whether `path` is reachable without pairing: the pairing flow itself, the
+ *  health check, and static assets. */
+function isPublicPath(path: string, method: string): boolean {
+  if (path === "/api/health" || path === "/api/pair/request" || path === "/api/pair/confirm") return true;
+  if (method === "GET" && path.startsWith("/api/icons/")) return true;
+  return path !== "/ws" && !path.startsWith("/api/");
+}
+
+function isLoopback(address: string | undefined): boolean {
+  return !!address && (address === "::1" || address.startsWith("127.") || address === "::ffff:127.0.0.1");
+}
+
+function isLoopbackHost(hostname: string): boolean {
+  return hostname === "localhost" || hostname === "[::1]" || hostname.startsWith("127.");
+}
+
+/** Origins the Tauri webview loads the app from, per platform. */
+const TAURI_ORIGINS = new Set(["tauri://localhost", "http://tauri.localhost", "https://tauri.localhost"]);
+
+/** Whether a page from `origin` is the app itself: the Tauri webview, the
+ *  client served by this server (`url` being the request's), or the dev
+ *  server on this machine. Web pages elsewhere can't have these origins. */
+export function isAppOrigin(origin: string, url: URL): boolean {
+  if (TAURI_ORIGINS.has(origin) || origin === url.origin) return true;
+  try {
+    return isLoopbackHost(new URL(origin).hostname);
+  } catch {
+    return false;
+  }
+}
+
+/** Lets a request through if it comes from this machine, from a paired
+ *  device, or is for a public path; otherwise answers 401. Browsers send
+ *  the page's origin with cross-site requests, so a web page the user has
+ *  open can't use `/api` or `/ws` through loopback (or any other way): only
+ *  the app's own origins get past the first check. Loopback also has to be
+ *  addressed by a loopback name, which a rebound DNS name isn't. */
+export function authorize(
+  req: Request,
+  url: URL,
+  address: string | undefined
+): { deviceId?: string; local: boolean; address?: string } | Response {
+  const origin = req.headers.get("origin");
+  if (origin && !isAppOrigin(origin, url) && (url.pathname === "/ws" || url.pathname.startsWith("/api/"))) {
+    return Response.json({ error: "origin_not_allowed" }, { status: 403 });
+  }
+  const local = isLoopback(address) && isLoopbackHost(url.hostname);
+  const token = requestToken(req, url);
+  const device = token ? verifyToken(token, req.method, url.pathname) : null;
+  if (device) return { deviceId: device.id, local, address };
+  // CORS preflights never carry credentials
+  if (local || req.method === "OPTIONS" || getConfig().server.requirePairing === false || isPublicPath(url.pathname, req.method)) {
+    return { local, address };
+  }
+  // Readable by the app cross-origin, so it can tell it has to pair
+  const headers: Record<string, string> = origin ? { "Access-Control-Allow-Origin": origin, Vary: "Origin" } : {};
+  return Response.json({ error: token ? "invalid_token" : "pairing_required" }, { status: 401, headers });
+}
@@ -0,0 +1,35 @@
+import { X509Certificate } from "crypto";
+import { existsSync, mkdirSync, readFileSync } from "fs";
+import { join } from "path";
+import { getConfigDir } from "../config/store";
+
+export interface TlsIdentity {
+  cert: string;
+  key: string;
+  /** SHA-256 fingerprint (`AB:CD:...`), what the app pins when pairing. */
+  fingerprint: string;
+}
+
+const tlsDir = join(getConfigDir(), "tls");
+const certPath = join(tlsDir, "cert.pem");
+const keyPath = join(tlsDir, "key.pem");
+
+/** Loads the server's self-signed certificate, creating one with `openssl`
+ *  on first use. It's kept across restarts so paired apps keep trusting it. */
+export function loadTlsIdentity(): TlsIdentity {
+  if (!existsSync(certPath) || !existsSync(keyPath)) {
+    mkdirSync(tlsDir, { recursive: true, mode: 0o700 });
+    const result = Bun.spawnSync([
+      "openssl", "req", "-x509", "-nodes",
+      "-newkey", "ec", "-pkeyopt", "ec_paramgen_curve:prime256v1",
+      "-keyout", keyPath, "-out", certPath,
+      "-days", "3650", "-subj", "/CN=DeckPilot",
+    ], { stdout: "ignore", stderr: "pipe" });
+    if (!result.success) {
+      throw new Error(`Could not create a TLS certificate: ${result.stderr.toString().trim()}`);
+    }
+  }
+  const cert = readFileSync(certPath, "utf-8");
+  const key = readFileSync(keyPath, "utf-8");
+  return { cert, key, fingerprint: new X509Certificate(cert).fingerprint256 };
+}
@@ -155,7 +155,11 @@ export function createWebSocketHandler(engine: ActionEngine) {
 
         case "gamepad_button": {
           const config = getConfig();
-          const binding = config.gamepadBindings.find((b) => b.button === msg.button);
+          // A binding for this specific controller wins over a catch-all one
+          const binding =
+            config.gamepadBindings.find(
+              (b) => b.button === msg.button && msg.controller && b.controller === msg.controller
+            ) ?? config.gamepadBindings.find((b) => b.button === msg.button && !b.controller);
           if (!binding) break;
 
           // Skip client-side bindings — those are handled on the client
@@ -120,20 +120,44 @@ export type ClientActionType = "nav.page.next" | "nav.page.previous" | "nav.page
 
 export interface GamepadBinding {
   button: number;
+  /** Controller GUID this binding is limited to; unset matches any controller. */
+  controller?: string;
+  /** Display name of `controller`, kept so the editor can label disconnected pads. */
+  controllerName?: string;
   kind: "client" | "server";
   action?: Action;
   clientAction?: ClientActionType;
   label?: string;
+  /** Server bindings: still run the action if the press was queued while
+   *  the client was offline and arrives late. */
+  replayIfLate?: boolean;
 }
 
 export interface DeckPilotConfig {
   version: number;
-  server: { port: number; host: string };
+  server: {
+    port: number;
+    host: string;
+    /** Only paired devices may connect from other machines. Defaults to on. */
+    requirePairing?: boolean;
+    /** Serve `https`/`wss` with a self-signed certificate the app pins when pairing. */
+    tls?: boolean;
+  };
   activeProfile: string;
   profiles: ProfileConfig[];
   gamepadBindings: GamepadBinding[];
 }
 
+// ── Pairing ──
+
+/** A device paired with the server, as listed by `GET /api/pair/devices`. */
+export interface PairedDevice {
+  id: string;
+  name: string;
+  pairedAt: string;
+  lastSeen?: string;
+}
+
 // ── Now Playing Data ──
 
 export interface NowPlayingData {
@@ -228,7 +252,7 @@ export type ClientMessage =
   | { type: "button_press"; actionId: string; page: string }
   | { type: "button_long_press"; actionId: string; page: string }
   | { type: "slider_change"; widgetId: string; page: string; value: number }
-  | { type: "gamepad_button"; button: number }
+  | { type: "gamepad_button"; button: number; controller?: string }
   | { type: "request_config" }
   | { type: "ping" };
 